@@ -0,0 +1,7 @@
+CREATE TABLE coupon_redemption (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  coupon_id int(11) NOT NULL,
+  date_created DATETIME NOT NULL,
+  PRIMARY KEY (id),
+  FOREIGN KEY (coupon_id) REFERENCES coupon(id) ON DELETE CASCADE
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci
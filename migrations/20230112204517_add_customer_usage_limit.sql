@@ -0,0 +1,6 @@
+ALTER TABLE coupon
+  ADD COLUMN max_usage_per_customer int(11) NULL AFTER max_usage_count;
+
+ALTER TABLE coupon_redemption
+  ADD COLUMN customer_id varchar(255) NULL AFTER coupon_id,
+  ADD INDEX idx_coupon_redemption_customer (coupon_id, customer_id);
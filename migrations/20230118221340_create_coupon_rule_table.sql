@@ -0,0 +1,11 @@
+CREATE TABLE coupon_rule (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  coupon_id int(11) NOT NULL,
+  rule TEXT NOT NULL, -- JSON representation of `CouponRule`
+  PRIMARY KEY (id),
+  FOREIGN KEY (coupon_id) REFERENCES coupon(id) ON DELETE CASCADE
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
+
+-- `SEXTOU` used to be hard-coded as a coupon that is only valid on Friday
+INSERT INTO coupon_rule (coupon_id, rule)
+  SELECT id, '{"type":"weekdays","weekdays":["Fri"]}' FROM coupon WHERE UPPER(code) = 'SEXTOU';
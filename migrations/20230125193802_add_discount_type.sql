@@ -0,0 +1,3 @@
+ALTER TABLE coupon
+  ADD COLUMN discount_type varchar(20) NOT NULL DEFAULT 'percentage' AFTER discount, -- `percentage` or `fixed_amount`
+  ADD COLUMN currency char(3) NULL AFTER discount_type; -- ISO-4217 code, only for `fixed_amount` discounts
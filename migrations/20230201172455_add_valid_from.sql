@@ -0,0 +1,2 @@
+ALTER TABLE coupon
+  ADD COLUMN valid_from DATETIME NULL AFTER max_usage_per_customer;
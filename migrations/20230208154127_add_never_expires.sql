@@ -0,0 +1,2 @@
+ALTER TABLE coupon
+  ADD COLUMN never_expires BOOLEAN NOT NULL DEFAULT FALSE AFTER expiration_date;
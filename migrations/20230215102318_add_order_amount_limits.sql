@@ -0,0 +1,4 @@
+-- amounts in the minor unit of the coupon `currency`
+ALTER TABLE coupon
+  ADD COLUMN min_order_amount INT NULL AFTER currency,
+  ADD COLUMN max_discount_amount INT NULL AFTER min_order_amount;
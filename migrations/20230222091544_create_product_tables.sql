@@ -0,0 +1,17 @@
+-- OldBot license plans a coupon can be restricted to
+CREATE TABLE product (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  code varchar(255) NOT NULL UNIQUE,
+  name varchar(255) NOT NULL,
+  date_created DATETIME NOT NULL,
+  PRIMARY KEY (id)
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
+
+-- a coupon without any product applies to every product
+CREATE TABLE coupon_product (
+  coupon_id int(11) NOT NULL,
+  product_id int(11) NOT NULL,
+  PRIMARY KEY (coupon_id, product_id),
+  FOREIGN KEY (coupon_id) REFERENCES coupon(id) ON DELETE CASCADE,
+  FOREIGN KEY (product_id) REFERENCES product(id) ON DELETE CASCADE
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
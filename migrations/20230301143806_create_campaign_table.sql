@@ -0,0 +1,16 @@
+CREATE TABLE campaign (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  name varchar(255) NOT NULL UNIQUE,
+  description TEXT NULL,
+  start_date DATETIME NULL,
+  end_date DATETIME NULL,
+  budget int(11) NULL, -- in the minor unit of the `currency`
+  currency char(3) NULL,
+  active BOOLEAN NOT NULL DEFAULT 1,
+  date_created DATETIME NOT NULL,
+  PRIMARY KEY (id)
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
+
+ALTER TABLE coupon
+  ADD COLUMN campaign_id int(11) NULL AFTER code,
+  ADD FOREIGN KEY (campaign_id) REFERENCES campaign(id) ON DELETE SET NULL;
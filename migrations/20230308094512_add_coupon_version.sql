@@ -0,0 +1,3 @@
+-- incremented on every update of the coupon, it is the `ETag` of the coupon
+ALTER TABLE coupon
+  ADD COLUMN version int(11) NOT NULL DEFAULT 1 AFTER date_updated;
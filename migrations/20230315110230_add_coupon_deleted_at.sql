@@ -0,0 +1,4 @@
+-- deleted coupons are kept for the history and can be restored,
+-- their `code` is still unique so it can not be reused by a new coupon
+ALTER TABLE coupon
+  ADD COLUMN deleted_at DATETIME NULL DEFAULT NULL AFTER date_updated;
@@ -0,0 +1,15 @@
+-- append-only history of the coupon changes, written in the same transaction as the change
+CREATE TABLE coupon_audit (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  coupon_id int(11) NOT NULL,
+  action varchar(20) NOT NULL,
+  -- identity of the session that made the change
+  api_key_name varchar(255) NULL,
+  session_id varchar(36) NULL,
+  -- JSON representation of `CouponSnapshot`, `NULL` before an insert
+  before_snapshot TEXT NULL,
+  after_snapshot TEXT NULL,
+  date_created DATETIME NOT NULL,
+  PRIMARY KEY (id),
+  FOREIGN KEY (coupon_id) REFERENCES coupon(id)
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
@@ -0,0 +1,12 @@
+-- named API keys exchanged for a session in `/auth`, only the SHA-256 hash of the key is stored
+CREATE TABLE api_key (
+  id int(11) NOT NULL AUTO_INCREMENT,
+  name varchar(100) NOT NULL UNIQUE,
+  key_hash varchar(64) NOT NULL UNIQUE,
+  -- comma separated `ApiKeyScope`, e.g. `coupon:read,coupon:verify`
+  scopes varchar(255) NOT NULL,
+  -- `NULL` never expires
+  expires_at DATETIME NULL,
+  date_created DATETIME NOT NULL,
+  PRIMARY KEY (id)
+) ENGINE=InnoDB CHARSET=utf8 COLLATE=utf8_unicode_ci;
@@ -0,0 +1,3 @@
+-- `Role` of the sessions created with the key, the keys created before the roles could use every route of their scopes
+ALTER TABLE api_key
+  ADD COLUMN role varchar(20) NOT NULL DEFAULT 'editor' AFTER scopes;
@@ -0,0 +1,10 @@
+-- coupons and API keys belong to a tenant, the coupons and keys created before the tenants belong to `default`
+ALTER TABLE coupon
+  ADD COLUMN tenant varchar(100) NOT NULL DEFAULT 'default' AFTER id,
+  -- the `code` is unique per tenant, instead of globally
+  DROP INDEX code,
+  ADD UNIQUE INDEX coupon_tenant_code (tenant, code);
+
+-- sessions created with the key can only use the coupons of its tenant
+ALTER TABLE api_key
+  ADD COLUMN tenant varchar(100) NOT NULL DEFAULT 'default' AFTER name;
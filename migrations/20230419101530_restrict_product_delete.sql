@@ -0,0 +1,6 @@
+-- deleting a product linked to coupons would leave them without restrictions (valid for every product),
+-- the links must be removed first
+ALTER TABLE coupon_product
+  DROP FOREIGN KEY coupon_product_ibfk_2;
+ALTER TABLE coupon_product
+  ADD CONSTRAINT coupon_product_product_id FOREIGN KEY (product_id) REFERENCES product(id) ON DELETE RESTRICT;
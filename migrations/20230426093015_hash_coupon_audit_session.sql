@@ -0,0 +1,4 @@
+-- the session id is the credential of a Redis session, only its SHA-256 hash is kept in the history
+ALTER TABLE coupon_audit
+  CHANGE COLUMN session_id session_hash varchar(64) NULL;
+UPDATE coupon_audit SET session_hash = SHA2(session_hash, 256) WHERE session_hash IS NOT NULL;
@@ -0,0 +1,12 @@
+-- campaigns belong to a tenant, the campaigns created before the tenants belong to `default`
+ALTER TABLE campaign
+  ADD COLUMN tenant varchar(100) NOT NULL DEFAULT 'default' AFTER id,
+  -- the `name` is unique per tenant, instead of globally
+  DROP INDEX name,
+  ADD UNIQUE INDEX campaign_tenant_name (tenant, name);
+
+-- a coupon can only belong to a campaign of its tenant
+UPDATE coupon c
+  JOIN campaign ca ON ca.id = c.campaign_id
+  SET c.campaign_id = NULL
+  WHERE c.tenant <> ca.tenant;
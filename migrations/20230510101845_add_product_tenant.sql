@@ -0,0 +1,7 @@
+-- products belong to a tenant, the products created before the tenants belong to `default`
+-- (the coupons of other tenants already restricted to them keep the restriction)
+ALTER TABLE product
+  ADD COLUMN tenant varchar(100) NOT NULL DEFAULT 'default' AFTER id,
+  -- the `code` is unique per tenant, instead of globally
+  DROP INDEX code,
+  ADD UNIQUE INDEX product_tenant_code (tenant, code);
@@ -0,0 +1,5 @@
+-- discount given by the redemption in the minor unit of the order `currency`, it is added to the spent amount
+-- of the campaign budget (both are NULL for the redemptions without the order)
+ALTER TABLE coupon_redemption
+  ADD COLUMN discount_amount int(11) NULL AFTER customer_id,
+  ADD COLUMN currency char(3) NULL AFTER discount_amount;
@@ -0,0 +1,294 @@
+use super::{Role, DEFAULT_TENANT};
+use crate::coupon::FieldError;
+use actix_web::http::Method;
+use rand::distributions::{Alphanumeric, DistString};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use sqlx::types::chrono::{NaiveDateTime, Utc};
+
+
+/// Permission granted to an API key, checked by the `validator` for every authenticated route.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    #[serde(rename = "coupon:read")]
+    CouponRead,
+    #[serde(rename = "coupon:write")]
+    CouponWrite,
+    #[serde(rename = "coupon:verify")]
+    CouponVerify,
+    #[serde(rename = "coupon:redeem")]
+    CouponRedeem,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ApiKeyScope::CouponRead => "coupon:read",
+            ApiKeyScope::CouponWrite => "coupon:write",
+            ApiKeyScope::CouponVerify => "coupon:verify",
+            ApiKeyScope::CouponRedeem => "coupon:redeem",
+        };
+    }
+
+    /// Scopes of the keys from the configuration (`api_key` and `admin_api_key`).
+    pub fn all() -> Vec<ApiKeyScope> {
+        return vec![ApiKeyScope::CouponRead, ApiKeyScope::CouponWrite, ApiKeyScope::CouponVerify, ApiKeyScope::CouponRedeem];
+    }
+}
+
+impl TryFrom<&str> for ApiKeyScope {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return match s {
+            "coupon:read" => Ok(Self::CouponRead),
+            "coupon:write" => Ok(Self::CouponWrite),
+            "coupon:verify" => Ok(Self::CouponVerify),
+            "coupon:redeem" => Ok(Self::CouponRedeem),
+            other => Err(format!("{} is not a supported API key scope.", other)),
+        };
+    }
+}
+
+/// Scope required by a request to the authenticated scopes (`/coupon`, `/product` and `/campaign`).
+/// e.g. `POST /coupon/TEST/redeem` => `coupon:redeem`, `GET /product` => `coupon:read`
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiKeyScope> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if (!matches!(segments.first(), Some(&"coupon") | Some(&"product") | Some(&"campaign"))){
+        return None;
+    }
+
+    if (segments[0] == "coupon" && segments.len() == 3){
+        if (method == Method::GET && segments[1] == "verify"){
+            return Some(ApiKeyScope::CouponVerify);
+        }
+        if (method == Method::POST && segments[2] == "quote"){
+            return Some(ApiKeyScope::CouponVerify);
+        }
+        if (method == Method::POST && segments[2] == "redeem"){
+            return Some(ApiKeyScope::CouponRedeem);
+        }
+    }
+    if (method == Method::GET){
+        return Some(ApiKeyScope::CouponRead);
+    }
+    return Some(ApiKeyScope::CouponWrite);
+}
+
+// e.g. `coupon:read,coupon:verify`
+pub fn parse_scopes(scopes: &str) -> Result<Vec<ApiKeyScope>, String> {
+    return scopes.split(',')
+        .filter(|scope| !scope.is_empty())
+        .map(ApiKeyScope::try_from)
+        .collect();
+}
+
+pub fn join_scopes(scopes: &[ApiKeyScope]) -> String {
+    return scopes.iter().map(ApiKeyScope::as_str).collect::<Vec<&str>>().join(",");
+}
+
+/// Only the hash of the key is stored, the key has enough entropy to not need a salt.
+pub fn hash_api_key(api_key: &str) -> String {
+    return hex::encode(Sha256::digest(api_key.as_bytes()));
+}
+
+/// Compare the keys in constant time, the hashes have the same length whatever the length of the keys.
+pub fn api_keys_match(api_key: &str, other_api_key: &str) -> bool {
+    return hash_api_key(api_key).as_bytes().ct_eq(hash_api_key(other_api_key).as_bytes()).into();
+}
+
+/// Random key returned once, when the API key is created.
+pub fn generate_api_key() -> String {
+    return Alphanumeric.sample_string(&mut rand::thread_rng(), 40);
+}
+
+/// Row of the `api_key` table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredApiKey {
+    pub id: i32,
+    pub name: String,
+    pub tenant: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub role: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub date_created: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyInsertRequest {
+    pub name: String,
+    // sessions created with the key can only use the coupons of this tenant,
+    // the tenant of the admin session (`default` for the `admin_api_key`) when it is not sent
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub scopes: Vec<ApiKeyScope>,
+    // `editor` by default, the scopes still limit what the key can do
+    #[serde(default)]
+    pub role: Role,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyInsert {
+    pub name: String,
+    pub tenant: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub role: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl ApiKeyInsert {
+    /// Validate the request and hash the generated `api_key`.
+    pub fn new(request: ApiKeyInsertRequest, api_key: &str) -> Result<Self, FieldError> {
+        let name = request.name.trim().to_string();
+        if (name.is_empty() || name.len() > 100){
+            return Err(FieldError::new("name", "API key name must have between 1 and 100 characters."));
+        }
+        // names of the sessions created with the keys from the configuration
+        if (name == "default" || name == "admin"){
+            return Err(FieldError::new("name", format!("API key name `{}` is reserved.", name)));
+        }
+        // the tenant is part of the coupon cache keys
+        let tenant = request.tenant.as_deref().unwrap_or(DEFAULT_TENANT).trim().to_string();
+        if (tenant.is_empty() || tenant.len() > 100 || !tenant.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')){
+            return Err(FieldError::new("tenant", "API key tenant must have between 1 and 100 letters, digits, `-` or `_`."));
+        }
+        if (request.scopes.is_empty()){
+            return Err(FieldError::new("scopes", "API key must have at least one scope."));
+        }
+        if let Some(expires_at) = request.expires_at {
+            if (expires_at <= Utc::now().naive_utc()){
+                return Err(FieldError::new("expires_at", "API key expiration date must be in the future."));
+            }
+        }
+
+        let mut scopes: Vec<ApiKeyScope> = Vec::new();
+        for scope in request.scopes {
+            if (!scopes.contains(&scope)){
+                scopes.push(scope);
+            }
+        }
+
+        return Ok(Self {
+            name,
+            tenant,
+            key_hash: hash_api_key(api_key),
+            scopes: join_scopes(&scopes),
+            role: request.role.as_str().to_string(),
+            expires_at: request.expires_at,
+        });
+    }
+}
+
+/// API key without its hash, returned by the admin endpoints.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    pub tenant: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub role: Role,
+    pub expires_at: Option<NaiveDateTime>,
+    pub date_created: NaiveDateTime,
+}
+
+impl TryFrom<StoredApiKey> for ApiKeyResponse {
+    type Error = String;
+    fn try_from(api_key: StoredApiKey) -> Result<Self, Self::Error> {
+        return Ok( Self {
+            id: api_key.id,
+            name: api_key.name,
+            tenant: api_key.tenant,
+            scopes: parse_scopes(&api_key.scopes)?,
+            role: Role::try_from(api_key.role.as_str())?,
+            expires_at: api_key.expires_at,
+            date_created: api_key.date_created,
+        });
+    }
+}
+
+/// Response of `POST /api-key`, the only time the key itself is returned.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyCreatedResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    pub key: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiKeyInsert, ApiKeyInsertRequest, ApiKeyScope, Role, api_keys_match, hash_api_key, parse_scopes, required_scope};
+    use actix_web::http::Method;
+    use claim::{assert_err, assert_ok};
+    use chrono::{Duration, Utc};
+
+    fn request(name: &str, scopes: Vec<ApiKeyScope>) -> ApiKeyInsertRequest {
+        return ApiKeyInsertRequest { name: name.to_string(), tenant: None, scopes, role: Role::default(), expires_at: None };
+    }
+
+    #[test]
+    fn routes_require_their_scope(){
+        assert_eq!(required_scope(&Method::GET, "/coupon"), Some(ApiKeyScope::CouponRead));
+        assert_eq!(required_scope(&Method::GET, "/coupon/TEST"), Some(ApiKeyScope::CouponRead));
+        assert_eq!(required_scope(&Method::GET, "/coupon/1/history"), Some(ApiKeyScope::CouponRead));
+        assert_eq!(required_scope(&Method::GET, "/coupon/verify/TEST"), Some(ApiKeyScope::CouponVerify));
+        assert_eq!(required_scope(&Method::POST, "/coupon/TEST/quote"), Some(ApiKeyScope::CouponVerify));
+        assert_eq!(required_scope(&Method::POST, "/coupon/TEST/redeem"), Some(ApiKeyScope::CouponRedeem));
+        assert_eq!(required_scope(&Method::POST, "/coupon"), Some(ApiKeyScope::CouponWrite));
+        assert_eq!(required_scope(&Method::PATCH, "/coupon/TEST"), Some(ApiKeyScope::CouponWrite));
+        assert_eq!(required_scope(&Method::POST, "/coupon/1/restore"), Some(ApiKeyScope::CouponWrite));
+        assert_eq!(required_scope(&Method::GET, "/product/PRO"), Some(ApiKeyScope::CouponRead));
+        assert_eq!(required_scope(&Method::DELETE, "/campaign/1"), Some(ApiKeyScope::CouponWrite));
+        assert_eq!(required_scope(&Method::GET, "/api-key"), None);
+        assert_eq!(required_scope(&Method::GET, "/health_check"), None);
+    }
+
+    #[test]
+    fn scopes_are_parsed(){
+        assert_eq!(parse_scopes("coupon:read,coupon:redeem").unwrap(), vec![ApiKeyScope::CouponRead, ApiKeyScope::CouponRedeem]);
+        assert_eq!(parse_scopes("").unwrap(), vec![]);
+        assert_err!(parse_scopes("coupon:read,coupon:delete"));
+    }
+
+    #[test]
+    fn only_the_hash_of_the_key_is_stored(){
+        let api_key = ApiKeyInsert::new(request(" checkout ", vec![ApiKeyScope::CouponVerify, ApiKeyScope::CouponVerify]), "secret").unwrap();
+
+        assert_eq!(api_key.name, "checkout");
+        assert_eq!(api_key.scopes, "coupon:verify");
+        assert_eq!(api_key.role, "editor");
+        assert_eq!(api_key.tenant, "default");
+        assert_eq!(api_key.key_hash, hash_api_key("secret"));
+        assert_eq!(api_key.key_hash.len(), 64);
+        assert_ne!(api_key.key_hash, hash_api_key("secret2"));
+    }
+
+    #[test]
+    fn api_keys_are_compared(){
+        assert!(api_keys_match("secret", "secret"));
+        assert!(!api_keys_match("secret", "secret2"));
+        assert!(!api_keys_match("secret", ""));
+    }
+
+    #[test]
+    fn invalid_api_key_is_rejected(){
+        assert_err!(ApiKeyInsert::new(request("", vec![ApiKeyScope::CouponRead]), "secret"));
+        assert_err!(ApiKeyInsert::new(request("admin", vec![ApiKeyScope::CouponRead]), "secret"));
+        assert_err!(ApiKeyInsert::new(request("checkout", vec![]), "secret"));
+
+        let mut invalid_tenant = request("checkout", vec![ApiKeyScope::CouponRead]);
+        invalid_tenant.tenant = Some("acme:1".to_string());
+        assert_err!(ApiKeyInsert::new(invalid_tenant, "secret"));
+
+        let mut expired = request("checkout", vec![ApiKeyScope::CouponRead]);
+        expired.expires_at = Some(Utc::now().naive_utc() - Duration::days(1));
+        assert_err!(ApiKeyInsert::new(expired, "secret"));
+
+        let mut expiring = request("checkout", vec![ApiKeyScope::CouponRead]);
+        expiring.expires_at = Some(Utc::now().naive_utc() + Duration::days(1));
+        assert_ok!(ApiKeyInsert::new(expiring, "secret"));
+    }
+}
@@ -0,0 +1,49 @@
+use super::api_key::ApiKeyInsertRequest;
+use super::{api_key_service, Role, Session};
+use crate::coupon::CouponError;
+use actix_web::{
+    web, get, post, delete, HttpResponse,
+    web::Data,
+};
+use anyhow::anyhow;
+use sqlx::MySqlPool;
+
+
+/*
+    named API keys, registered in the `/api-key` scope and only available to admin sessions
+*/
+#[tracing::instrument( name = "Get all API keys", skip(pool) )]
+#[get("")]
+pub async fn get_api_keys(session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let tenant = check_admin(&session)?;
+    let api_keys = api_key_service::list_api_keys(tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(api_keys));
+}
+
+#[tracing::instrument( name = "Post API key", skip(pool) )]
+#[post("")]
+pub async fn add_api_key(request: web::Json<ApiKeyInsertRequest>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let tenant = check_admin(&session)?;
+    let api_key = api_key_service::create_api_key(request.0, tenant, &pool).await?;
+    return Ok(HttpResponse::Created().json(api_key));
+}
+
+#[tracing::instrument( name = "Delete API key", skip(pool) )]
+#[delete("/{id}")]
+pub async fn delete_api_key(param: web::Path<i32>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let tenant = check_admin(&session)?;
+    api_key_service::remove_api_key(param.into_inner(), tenant, &pool).await?;
+    return Ok(HttpResponse::NoContent().finish());
+}
+
+/// Returns the tenant whose keys the admin session manages, `None` for the session of the `admin_api_key`
+/// from the configuration that manages the keys of every tenant (its `admin` name is reserved).
+fn check_admin(session: &Session) -> Result<Option<&str>, CouponError> {
+    if (session.role != Role::Admin){
+        return Err(CouponError::ForbiddenError(anyhow!("Only admin sessions can manage the API keys.")));
+    }
+    if (session.api_key_name == "admin"){
+        return Ok(None);
+    }
+    return Ok(Some(&session.tenant));
+}
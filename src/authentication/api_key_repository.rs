@@ -0,0 +1,139 @@
+use super::api_key::{ApiKeyInsert, StoredApiKey};
+use sqlx::{MySqlPool, query, query_as};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+pub async fn insert_api_key(api_key: ApiKeyInsert, pool: &MySqlPool) -> Result<u64, sqlx::Error> {
+    let result = query!(
+        r#"
+            INSERT INTO api_key
+            (name, tenant, key_hash, scopes, role, expires_at, date_created)
+            VALUES
+            (?, ?, ?, ?, ?, ?, NOW())
+        "#,
+        api_key.name,
+        api_key.tenant,
+        api_key.key_hash,
+        api_key.scopes,
+        api_key.role,
+        api_key.expires_at,
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.last_insert_id());
+}
+
+/// Get the API keys of the `tenant`, or of every tenant when it is `None`.
+pub async fn get_all_api_keys(tenant: Option<&str>, pool: &MySqlPool) -> Result<Vec<StoredApiKey>, sqlx::Error> {
+    let api_keys = query_as!(StoredApiKey,
+        r#"SELECT id
+        , name
+        , tenant
+        , key_hash
+        , scopes
+        , role
+        , expires_at as `expires_at: NaiveDateTime`
+        , date_created as `date_created: NaiveDateTime`
+        FROM api_key WHERE (? IS NULL OR tenant = ?) ORDER BY name"#, tenant, tenant)
+    .fetch_all(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(api_keys);
+}
+
+pub async fn get_api_key_by_id(id: i32, pool: &MySqlPool) -> Result<Option<StoredApiKey>, sqlx::Error> {
+    let api_key = query_as!(StoredApiKey,
+        r#"SELECT id
+        , name
+        , tenant
+        , key_hash
+        , scopes
+        , role
+        , expires_at as `expires_at: NaiveDateTime`
+        , date_created as `date_created: NaiveDateTime`
+        FROM api_key WHERE id = ?
+        "#, id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(api_key);
+}
+
+pub async fn get_api_key_by_name(name: &String, pool: &MySqlPool) -> Result<Option<StoredApiKey>, sqlx::Error> {
+    let api_key = query_as!(StoredApiKey,
+        r#"SELECT id
+        , name
+        , tenant
+        , key_hash
+        , scopes
+        , role
+        , expires_at as `expires_at: NaiveDateTime`
+        , date_created as `date_created: NaiveDateTime`
+        FROM api_key WHERE name = ?
+        "#, name
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(api_key);
+}
+
+/// Get the API key that was not expired yet by the hash of the key.
+pub async fn get_active_api_key_by_hash(key_hash: &String, pool: &MySqlPool) -> Result<Option<StoredApiKey>, sqlx::Error> {
+    let api_key = query_as!(StoredApiKey,
+        r#"SELECT id
+        , name
+        , tenant
+        , key_hash
+        , scopes
+        , role
+        , expires_at as `expires_at: NaiveDateTime`
+        , date_created as `date_created: NaiveDateTime`
+        FROM api_key WHERE key_hash = ?
+        AND (expires_at IS NULL OR expires_at > NOW())
+        "#, key_hash
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(api_key);
+}
+
+/// Delete the API key if it belongs to the `tenant`, or to any tenant when it is `None`.
+pub async fn delete_api_key_by_id(id: i32, tenant: Option<&str>, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let result = query!(
+        r#"DELETE FROM api_key
+            WHERE id = ? AND (? IS NULL OR tenant = ?)
+        "#, id, tenant, tenant
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute delete query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.rows_affected() > 0);
+}
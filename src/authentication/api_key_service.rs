@@ -0,0 +1,81 @@
+use super::api_key::{ApiKeyCreatedResponse, ApiKeyInsert, ApiKeyInsertRequest, ApiKeyResponse, StoredApiKey, generate_api_key, hash_api_key};
+use super::api_key_repository;
+use crate::coupon::CouponError;
+use sqlx::{MySqlPool};
+use anyhow::{Result, anyhow};
+use sqlx::types::chrono::Utc;
+use std::convert::TryFrom;
+
+/// The `tenant` of the admin session, `None` manages the keys of every tenant (see `api_key_controller`).
+pub async fn list_api_keys(tenant: Option<&str>, pool: &MySqlPool) -> Result<Vec<ApiKeyResponse>, CouponError> {
+    return api_key_repository::get_all_api_keys(tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?
+        .into_iter()
+        .map(ApiKeyResponse::try_from)
+        .collect::<Result<Vec<ApiKeyResponse>, String>>()
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse ApiKeyResponse: {}.", e))));
+}
+
+/// Create an API key, the generated key is only returned by this function.
+/// An admin session bound to a `tenant` can only create keys of its tenant.
+pub async fn create_api_key(mut api_key_request: ApiKeyInsertRequest, tenant: Option<&str>, pool: &MySqlPool) -> Result<ApiKeyCreatedResponse, CouponError> {
+    if let Some(tenant) = tenant {
+        match api_key_request.tenant.as_deref().map(str::trim) {
+            None => api_key_request.tenant = Some(tenant.to_string()),
+            Some(requested_tenant) if requested_tenant == tenant => {},
+            Some(requested_tenant) => return Err(CouponError::ForbiddenError(anyhow!(format!(
+                "Admin session of tenant `{}` can not create API keys of tenant `{}`.", tenant, requested_tenant
+            )))),
+        }
+    }
+
+    let key = generate_api_key();
+    let api_key_insert = ApiKeyInsert::new(api_key_request, &key)
+        .map_err(CouponError::FieldValidationError)?;
+
+    let api_key = api_key_repository::get_api_key_by_name(&api_key_insert.name, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (api_key.is_some()){
+        return Err(CouponError::AlreadyExistsError(anyhow!(format!("API key with name `{}` already exists.", api_key_insert.name))));
+    }
+
+    let inserted_id = api_key_repository::insert_api_key(api_key_insert, pool).await
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Something went wrong and the API key was not inserted: {}", e))))?;
+
+    let inserted_id = i32::try_from(inserted_id)
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to read inserted_id: {}", e))))?;
+
+    let api_key = api_key_repository::get_api_key_by_id(inserted_id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?
+        .ok_or(CouponError::InternalError(anyhow!(format!("API key with id `{}` was not inserted.", inserted_id))))?;
+
+    let api_key = ApiKeyResponse::try_from(api_key)
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse ApiKeyResponse: {}.", e))))?;
+
+    return Ok(ApiKeyCreatedResponse { api_key, key });
+}
+
+/// Sessions already created with the key are kept until they expire.
+pub async fn remove_api_key(id: i32, tenant: Option<&str>, pool: &MySqlPool) -> Result<(), CouponError> {
+    let deleted = api_key_repository::delete_api_key_by_id(id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (!deleted){
+        return Err(CouponError::NotFoundError(anyhow!(format!("API key with id `{}` not found.", id))));
+    }
+    return Ok(());
+}
+
+/// Find the API key, that was not expired yet, used to authenticate in `/auth`.
+pub async fn find_active_api_key(key: &str, pool: &MySqlPool) -> Result<Option<StoredApiKey>, CouponError> {
+    return api_key_repository::get_active_api_key_by_hash(&hash_api_key(key), pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()));
+}
+
+/// Find the API key, that was not expired yet, of a session.
+// `map_or` instead of `is_none_or` that needs Rust 1.82
+#[allow(clippy::unnecessary_map_or)]
+pub async fn find_active_api_key_by_name(name: &String, pool: &MySqlPool) -> Result<Option<StoredApiKey>, CouponError> {
+    let api_key = api_key_repository::get_api_key_by_name(name, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    return Ok(api_key.filter(|api_key| api_key.expires_at.map_or(true, |expires_at| expires_at > Utc::now().naive_utc())));
+}
@@ -1,16 +1,23 @@
 
 use actix_web::{
-    web, post,
-    dev::{ServiceRequest}, HttpResponse,
+    web, get, post,
+    dev::{Payload, ServiceRequest}, FromRequest, HttpMessage, HttpRequest, HttpResponse,
     web::Data,
+    http::header::RETRY_AFTER,
 };
+use std::future::{ready, Ready};
 use redis::{AsyncCommands};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
 use anyhow::{Result};
+use sqlx::MySqlPool;
+use sqlx::types::chrono::{NaiveDateTime, Utc};
+use chrono::Duration;
 use uuid::Uuid;
 
-use crate::configuration::ApiKey;
+use super::{ApiKeyScope, AuthThrottle, Role, SessionTokens, StoredApiKey, api_key_service, api_keys_match, is_session_revoked, parse_scopes, required_scope, revoke_session};
+use crate::configuration::SessionMode;
+use crate::configuration::{AdminApiKey, ApiKey, SessionTtl};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Bearer {
@@ -22,6 +29,90 @@ pub struct ApiKeyRequest {
     pub api_key: String,
 }
 
+/// Tenant of the keys from the configuration, and of the coupons and API keys created before the tenants.
+pub const DEFAULT_TENANT: &str = "default";
+
+pub fn default_tenant() -> String {
+    return DEFAULT_TENANT.to_string();
+}
+
+/// Stored in redis as the value of the session id, and added to the request extensions by the `validator`.
+// sessions created before the value was stored (an empty value) are the default session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    // the redis key of the session, it is not part of the stored value
+    #[serde(skip)]
+    pub id: String,
+    // name of the key used to authenticate, `default` (`api_key`), `admin` (`admin_api_key`)
+    // or the name of the key stored in the `api_key` table
+    #[serde(default)]
+    pub api_key_name: String,
+    // checked by `authorize_role` in the coupon, product and campaign scopes
+    #[serde(default)]
+    pub role: Role,
+    // the session can only use the coupons of its tenant
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    // sessions created before the scopes were stored could only use the keys from the configuration
+    #[serde(default = "ApiKeyScope::all")]
+    pub scopes: Vec<ApiKeyScope>,
+    // set by the `validator` from the time to live of the redis key or the expiration of the signed token
+    #[serde(skip)]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+// same values as the serde defaults, the session of the `api_key` from the configuration before the scopes, roles and tenants
+impl Default for Session {
+    fn default() -> Self {
+        return Self {
+            id: String::new(),
+            api_key_name: String::new(),
+            role: Role::default(),
+            tenant: default_tenant(),
+            scopes: ApiKeyScope::all(),
+            expires_at: None,
+        };
+    }
+}
+
+/// Response of `GET /auth/session`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionResponse {
+    pub session_id: String,
+    pub api_key_name: String,
+    pub role: Role,
+    pub tenant: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl From<Session> for SessionResponse {
+    fn from(session: Session) -> Self {
+        return Self {
+            session_id: session.id,
+            api_key_name: session.api_key_name,
+            role: session.role,
+            tenant: session.tenant,
+            scopes: session.scopes,
+            expires_at: session.expires_at,
+        };
+    }
+}
+
+// Get the session of a request authenticated by the `validator`
+impl FromRequest for Session {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        return ready(
+            request.extensions().get::<Session>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Request is not authenticated."))
+        );
+    }
+}
+
 #[tracing::instrument(name = "Validator", skip(request))]
 // when sending a request to any route under auth middleware send a dummy bearer authentication token
 pub async fn validator(request: ServiceRequest, _: actix_web_httpauth::extractors::bearer::BearerAuth,) -> Result<ServiceRequest, actix_web::Error> {
@@ -42,6 +133,10 @@ pub async fn validator(request: ServiceRequest, _: actix_web_httpauth::extractor
     if (redis.is_none()){
         return Err(actix_web::error::ErrorInternalServerError("Failed to get `redis` data from app data."));
     }
+    let session_tokens = request.app_data::<web::Data<SessionTokens>>();
+    if (session_tokens.is_none()){
+        return Err(actix_web::error::ErrorInternalServerError("Failed to get `session_tokens` data from app data."));
+    }
 
     // Decode the `Authorization` header value from base64
     let decoded = base64::decode(request_bearer.replace("Bearer ", ""))
@@ -50,64 +145,211 @@ pub async fn validator(request: ServiceRequest, _: actix_web_httpauth::extractor
     let bearer = String::from_utf8(decoded)
         .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to parse decoded base64 to string: {}.", e)))?;
 
-    // Convert base64 to string
-    let session_id;
-    // split Bearer token and get the first part, that contains the session id
-    if let Some(bearer_request) = bearer.split(":").next(){
-        session_id = bearer_request;
-    } else {
-        return Err(actix_web::error::ErrorBadRequest("Bearer header is invalid."));
-    }
+    // split Bearer token, the first part contains the session id and the second the signed session token (if any)
+    let (session_id, session_token) = bearer.split_once(':').unwrap_or((bearer.as_str(), ""));
 
-    // get connection to the redis database
-    let mut con = redis.unwrap().get_async_connection()
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to get `redis` connection: {}.", e)))?;
+    let session;
+    if (session_token.is_empty()){
+        // get connection to the redis database
+        let mut con = redis.unwrap().get_async_connection()
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to get `redis` connection: {}.", e)))?;
 
-    // query redis using the `session_id` from Bearer as key
-    let result: Option<String> = con.get(session_id).await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to query `redis`: {}.", e)))?;
+        // query redis using the `session_id` from Bearer as key, with its time to live
+        let (result, ttl): (Option<String>, i64) = redis::pipe()
+            .get(session_id)
+            .ttl(session_id)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to query `redis`: {}.", e)))?;
 
-    if let None = result {
-        return Err(actix_web::error::ErrorUnauthorized("Bearer token is invalid or has expired."));
+        let mut redis_session: Session = match result {
+            // sessions created before the value was stored
+            Some(session) if session.is_empty() => Session::default(),
+            Some(session) => serde_json::from_str(&session)
+                .map_err(|e| {
+                    tracing::error!("Failed to parse the session {}: {:?}", session_id, e);
+                    return actix_web::error::ErrorUnauthorized("Bearer token is invalid or has expired.");
+                })?,
+            None => return Err(actix_web::error::ErrorUnauthorized("Bearer token is invalid or has expired.")),
+        };
+        redis_session.id = session_id.to_string();
+        redis_session.expires_at = Some(Utc::now().naive_utc() + Duration::seconds(ttl.max(0)));
+        session = redis_session;
+    } else {
+        // validated locally, Redis is only queried for the revoked sessions
+        let claims = session_tokens.unwrap().verify(session_token)
+            .map_err(actix_web::error::ErrorUnauthorized)?;
+        if (claims.sid != session_id || is_session_revoked(redis.unwrap(), &claims.sid).await){
+            return Err(actix_web::error::ErrorUnauthorized("Bearer token is invalid or has expired."));
+        }
+        session = claims.session;
+    }
+
+    if let Some(scope) = required_scope(request.method(), request.path()) {
+        if (!session.scopes.contains(&scope)){
+            return Err(actix_web::error::ErrorForbidden(format!("API key `{}` does not have the `{}` scope.", session.api_key_name, scope.as_str())));
+        }
     }
+    request.extensions_mut().insert(session);
 
     return Ok(request);
 }
 
 
-#[tracing::instrument(name = "Authenticate", skip(request, redis, api_key))]
+#[tracing::instrument(name = "Authenticate", skip(request, http_request, redis, api_key, admin_api_key, session_tokens, session_ttl, throttle, pool))]
 // when sending a request to any route under auth middleware send a dummy bearer authentication token
 #[post("/auth")]
-pub async fn authenticate(request: web::Json<ApiKeyRequest>, redis: Data<redis::Client>, api_key: Data<ApiKey>) -> Result<HttpResponse, actix_web::Error> {
+#[allow(clippy::too_many_arguments)]
+pub async fn authenticate(request: web::Json<ApiKeyRequest>, http_request: HttpRequest, redis: Data<redis::Client>, api_key: Data<ApiKey>, admin_api_key: Data<AdminApiKey>, session_tokens: Data<SessionTokens>, session_ttl: Data<SessionTtl>, throttle: Data<AuthThrottle>, pool: Data<MySqlPool>) -> Result<HttpResponse, actix_web::Error> {
 
-    let api_key = api_key.0.expose_secret().to_string();
-    if (request.api_key != api_key){
-        return Err(actix_web::error::ErrorUnauthorized("Request token is invalid"));
+    let client_ip = throttle.client_ip(&http_request);
+    if let Some(retry_after) = throttle.retry_after(&client_ip).await {
+        tracing::warn!(client_ip = %client_ip, retry_after, "Authentication attempt of a locked out client");
+        return Err(too_many_attempts(retry_after));
     }
 
-    let mut conn = redis
-        .get_async_connection()
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to get `redis` connection: {}.", e)))?;
+    let is_admin = admin_api_key.0.as_ref()
+        .map(|admin_api_key| api_keys_match(&request.api_key, admin_api_key.0.expose_secret()))
+        .unwrap_or(false);
+    let is_default = api_keys_match(&request.api_key, api_key.0.expose_secret());
+    let bearer;
+    if (is_admin || is_default){
+        let session = Session {
+            api_key_name: if (is_admin) { "admin".to_string() } else { "default".to_string() },
+            role: if (is_admin) { Role::Admin } else { Role::Editor },
+            tenant: default_tenant(),
+            scopes: ApiKeyScope::all(),
+            ..Default::default()
+        };
+        bearer = create_session(session, None, session_ttl.0, &redis, &session_tokens).await?;
+    } else {
+        let stored_api_key = match api_key_service::find_active_api_key(&request.api_key, &pool).await? {
+            Some(stored_api_key) => stored_api_key,
+            None => return Err(failed_attempt(&throttle, &client_ip).await),
+        };
+        let expires_at = stored_api_key.expires_at;
+        bearer = create_session(session_of_api_key(stored_api_key)?, expires_at, session_ttl.0, &redis, &session_tokens).await?;
+    }
+
+    throttle.reset(&client_ip).await;
+    return Ok(HttpResponse::Ok().json(bearer));
+}
+
+// Count the failed attempt and get the error response, `429` if the client is now locked out
+async fn failed_attempt(throttle: &AuthThrottle, client_ip: &str) -> actix_web::Error {
+    let lockout = throttle.record_failure(client_ip).await;
+    tracing::warn!(client_ip = %client_ip, lockout, "Failed authentication attempt");
+    return match lockout {
+        Some(retry_after) => too_many_attempts(retry_after),
+        None => actix_web::error::ErrorUnauthorized("Request token is invalid"),
+    };
+}
+
+fn too_many_attempts(retry_after: usize) -> actix_web::Error {
+    let response = HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, retry_after.to_string()))
+        .finish();
+    return actix_web::error::InternalError::from_response(
+        format!("Too many failed attempts, try again in {} seconds.", retry_after),
+        response,
+    ).into();
+}
+
+/*
+    session endpoints, registered in the `/auth` scope (authenticated)
+*/
+#[tracing::instrument(name = "Logout", skip(redis))]
+#[post("/logout")]
+pub async fn logout(session: Session, redis: Data<redis::Client>) -> Result<HttpResponse, actix_web::Error> {
+    revoke_session(&redis, &session.id, seconds_to_expire(&session)).await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to revoke the session: {}.", e)))?;
+    return Ok(HttpResponse::NoContent().finish());
+}
+
+/// Replace the session by a new one (new session id and time to live), the current one is revoked.
+#[tracing::instrument(name = "Refresh session", skip(redis, session_tokens, session_ttl, pool))]
+#[post("/refresh")]
+pub async fn refresh_session(session: Session, redis: Data<redis::Client>, session_tokens: Data<SessionTokens>, session_ttl: Data<SessionTtl>, pool: Data<MySqlPool>) -> Result<HttpResponse, actix_web::Error> {
+    let bearer;
+    if (session.api_key_name == "default" || session.api_key_name == "admin"){
+        let new_session = Session { id: String::new(), expires_at: None, ..session.clone() };
+        bearer = create_session(new_session, None, session_ttl.0, &redis, &session_tokens).await?;
+    } else {
+        // the key is read again, so the session of a deleted or expired key can not be refreshed
+        let stored_api_key = api_key_service::find_active_api_key_by_name(&session.api_key_name, &pool).await?
+            .ok_or_else(|| actix_web::error::ErrorUnauthorized("API key of the session was deleted or has expired."))?;
+        let expires_at = stored_api_key.expires_at;
+        bearer = create_session(session_of_api_key(stored_api_key)?, expires_at, session_ttl.0, &redis, &session_tokens).await?;
+    }
+
+    revoke_session(&redis, &session.id, seconds_to_expire(&session)).await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to revoke the session: {}.", e)))?;
 
-    let session_id = Uuid::new_v4();
-    // we are only using the session id for its `key`, the `value` actually is not being used
-    // so lets insert it empty to save some memory.
-    let session_token = "".to_string();
-    
-    // 1 hour
-    let expiration = 1 * 60 * 60;
-    // insert on redis the session as session_id = session_token
-    conn.set_ex(session_id.to_string(), session_token.to_string(), expiration)
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to insert session token: {}.", e)))?;
-
-
-    let bearer_base64 = base64::encode(format!("{}:{}", session_id.to_string(), session_token.to_string()));
-    let bearer = format!("Bearer {}", bearer_base64);
-
-    // request.extensions_mut().insert(Bearer { token: String::from(request_token) });
     return Ok(HttpResponse::Ok().json(bearer));
 }
 
+#[tracing::instrument(name = "Get session")]
+#[get("/session")]
+pub async fn get_session(session: Session) -> Result<HttpResponse, actix_web::Error> {
+    return Ok(HttpResponse::Ok().json(SessionResponse::from(session)));
+}
+
+fn session_of_api_key(api_key: StoredApiKey) -> Result<Session, actix_web::Error> {
+    let scopes = parse_scopes(&api_key.scopes)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to parse API key scopes: {}.", e)))?;
+    let role = Role::try_from(api_key.role.as_str())
+        .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to parse API key role: {}.", e)))?;
+    return Ok(Session {
+        api_key_name: api_key.name,
+        role,
+        tenant: api_key.tenant,
+        scopes,
+        ..Default::default()
+    });
+}
+
+fn seconds_to_expire(session: &Session) -> usize {
+    return session.expires_at
+        .map(|expires_at| (expires_at - Utc::now().naive_utc()).num_seconds().max(0) as usize)
+        .unwrap_or(0);
+}
+
+/// Create the session with a new id, it expires in `session_ttl` seconds or when the API key expires.
+/// Returns the bearer token of the session.
+async fn create_session(mut session: Session, api_key_expires_at: Option<NaiveDateTime>, session_ttl: usize, redis: &redis::Client, session_tokens: &SessionTokens) -> Result<String, actix_web::Error> {
+    let session_id = Uuid::new_v4();
+    session.id = session_id.to_string();
+
+    let mut expiration = session_ttl;
+    // the session does not outlive the key
+    if let Some(expires_at) = api_key_expires_at {
+        let seconds_to_expire = (expires_at - Utc::now().naive_utc()).num_seconds().max(1);
+        expiration = expiration.min(seconds_to_expire as usize);
+    }
+
+    let session_token;
+    if (session_tokens.mode() == SessionMode::Signed){
+        // the bearer carries the signed session, it is not stored in Redis
+        session_token = session_tokens.sign(&session, expiration)
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    } else {
+        let mut conn = redis
+            .get_async_connection()
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to get `redis` connection: {}.", e)))?;
+
+        // the bearer only carries the session id, the `value` is the `Session` looked up by the `validator`
+        session_token = "".to_string();
+        let session = serde_json::to_string(&session)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to serialize session: {}.", e)))?;
+
+        // insert on redis the session as session_id = session_token
+        conn.set_ex::<_, _, ()>(session_id.to_string(), session, expiration)
+            .await
+            .map_err(|e| actix_web::error::ErrorInternalServerError(format!("Failed to insert session token: {}.", e)))?;
+    }
+
+    let bearer_base64 = base64::encode(format!("{}:{}", session_id, session_token));
+    return Ok(format!("Bearer {}", bearer_base64));
+}
@@ -0,0 +1,143 @@
+use crate::configuration::AuthThrottleSettings;
+use actix_web::HttpRequest;
+use redis::{AsyncCommands, aio::Connection};
+
+
+/// Failed attempts counter (by client IP) of `/auth`, stored in Redis.
+/// Redis errors are only logged and the attempt is allowed, like the other uses of Redis that are not the sessions.
+#[derive(Clone, Debug)]
+pub struct AuthThrottle {
+    client: redis::Client,
+    settings: AuthThrottleSettings,
+}
+
+impl AuthThrottle {
+    pub fn new(client: redis::Client, settings: AuthThrottleSettings) -> Self {
+        return Self { client, settings };
+    }
+
+    /// IP used to count the failed attempts of the client.
+    pub fn client_ip(&self, request: &HttpRequest) -> String {
+        if (self.settings.trust_forwarded_for) {
+            let forwarded_ip = request.headers().get("X-Forwarded-For")
+                .and_then(|header| header.to_str().ok())
+                .and_then(last_forwarded_for);
+            if let Some(ip) = forwarded_ip {
+                return ip.to_string();
+            }
+        }
+        return request.connection_info().peer_addr().unwrap_or("unknown").to_string();
+    }
+
+    /// Seconds until the client can try again, `None` if it is not locked out.
+    pub async fn retry_after(&self, ip: &str) -> Option<usize> {
+        let mut con = self.connection().await?;
+
+        let ttl: i64 = con.ttl(lockout_key(ip)).await
+            .map_err(|error| tracing::warn!("Failed to get the `/auth` lockout of `{}`: {:?}", ip, error))
+            .ok()?;
+        // `-2` when the key does not exist
+        return if (ttl > 0) { Some(ttl as usize) } else { None };
+    }
+
+    /// Count a failed attempt, returns the seconds the client is locked out for (if it was).
+    pub async fn record_failure(&self, ip: &str) -> Option<usize> {
+        let mut con = self.connection().await?;
+
+        let (failed_attempts, ): (usize, ) = redis::pipe()
+            .atomic()
+            .incr(failures_key(ip), 1)
+            .expire(failures_key(ip), self.settings.max_lockout_seconds).ignore()
+            .query_async(&mut con)
+            .await
+            .map_err(|error| tracing::warn!("Failed to count the failed `/auth` attempt of `{}`: {:?}", ip, error))
+            .ok()?;
+
+        let lockout = lockout_seconds(failed_attempts, &self.settings)?;
+        let result: Result<(), redis::RedisError> = con.set_ex(lockout_key(ip), failed_attempts, lockout).await;
+        if let Err(error) = result {
+            tracing::warn!("Failed to lock out `{}` from `/auth`: {:?}", ip, error);
+        }
+        return Some(lockout);
+    }
+
+    /// Forget the failed attempts, after the client authenticates.
+    pub async fn reset(&self, ip: &str) {
+        let Some(mut con) = self.connection().await else { return };
+
+        let result: Result<(), redis::RedisError> = con.del(failures_key(ip)).await;
+        if let Err(error) = result {
+            tracing::warn!("Failed to reset the failed `/auth` attempts of `{}`: {:?}", ip, error);
+        }
+    }
+
+    async fn connection(&self) -> Option<Connection> {
+        return self.client.get_async_connection().await
+            .map_err(|error| tracing::warn!("Failed to get `redis` connection: {:?}", error))
+            .ok();
+    }
+}
+
+/// Lockout after the failed attempts, it doubles on every attempt after `max_failed_attempts`.
+/// e.g. with the default settings: 5 => 30s, 6 => 60s, 7 => 120s ... up to 1 hour
+pub fn lockout_seconds(failed_attempts: usize, settings: &AuthThrottleSettings) -> Option<usize> {
+    if (settings.max_failed_attempts == 0 || failed_attempts < settings.max_failed_attempts){
+        return None;
+    }
+    let exponent = (failed_attempts - settings.max_failed_attempts).min(31) as u32;
+    let lockout = settings.lockout_seconds.saturating_mul(2_usize.saturating_pow(exponent));
+    return Some(lockout.clamp(1, settings.max_lockout_seconds.max(1)));
+}
+
+/// Right-most address of `X-Forwarded-For`, the one added by the trusted proxy.
+// the addresses before it are sent by the client and can be anything
+pub fn last_forwarded_for(header: &str) -> Option<&str> {
+    return header.rsplit(',')
+        .map(str::trim)
+        .find(|ip| !ip.is_empty());
+}
+
+fn failures_key(ip: &str) -> String {
+    return format!("auth_failures:{}", ip);
+}
+
+fn lockout_key(ip: &str) -> String {
+    return format!("auth_lockout:{}", ip);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_forwarded_for, lockout_seconds};
+    use crate::configuration::AuthThrottleSettings;
+
+    #[test]
+    fn lockout_starts_after_the_max_failed_attempts(){
+        let settings = AuthThrottleSettings::default();
+        assert_eq!(lockout_seconds(0, &settings), None);
+        assert_eq!(lockout_seconds(4, &settings), None);
+        assert_eq!(lockout_seconds(5, &settings), Some(30));
+    }
+
+    #[test]
+    fn lockout_doubles_up_to_the_max_lockout(){
+        let settings = AuthThrottleSettings::default();
+        assert_eq!(lockout_seconds(6, &settings), Some(60));
+        assert_eq!(lockout_seconds(7, &settings), Some(120));
+        assert_eq!(lockout_seconds(12, &settings), Some(3600));
+        assert_eq!(lockout_seconds(1000, &settings), Some(3600));
+    }
+
+    #[test]
+    fn zero_max_failed_attempts_disables_the_lockout(){
+        let settings = AuthThrottleSettings { max_failed_attempts: 0, ..AuthThrottleSettings::default() };
+        assert_eq!(lockout_seconds(1000, &settings), None);
+    }
+
+    #[test]
+    fn client_ip_is_the_address_added_by_the_proxy(){
+        assert_eq!(last_forwarded_for("203.0.113.7"), Some("203.0.113.7"));
+        assert_eq!(last_forwarded_for("1.2.3.4, 203.0.113.7"), Some("203.0.113.7"));
+        assert_eq!(last_forwarded_for("1.2.3.4,203.0.113.7 , "), Some("203.0.113.7"));
+        assert_eq!(last_forwarded_for(" , "), None);
+    }
+}
@@ -1,3 +1,17 @@
 pub mod auth;
+pub mod auth_throttle;
+pub mod api_key;
+pub mod api_key_controller;
+pub mod api_key_service;
+pub mod api_key_repository;
+pub mod session_token;
+pub mod role;
 
 pub use auth::*;
+pub use auth_throttle::*;
+pub use api_key::*;
+pub use api_key_controller::*;
+pub use api_key_service::*;
+pub use api_key_repository::*;
+pub use session_token::*;
+pub use role::*;
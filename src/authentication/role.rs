@@ -0,0 +1,137 @@
+use super::{ApiKeyScope, Session, required_scope};
+use actix_web::{HttpMessage, dev::{Service, ServiceRequest, ServiceResponse}};
+use serde::{Serialize, Deserialize};
+use std::future::Future;
+
+
+/// Role of the session, it limits the scopes the session can use whatever the scopes of its API key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // only verify coupons and quote orders, e.g. a storefront
+    Verifier,
+    // `verifier` and read coupons, products and campaigns
+    Reader,
+    // every coupon, product and campaign route, the role of the `api_key` from the configuration
+    // (and of the sessions created before the roles)
+    #[default]
+    Editor,
+    // `editor`, manage the API keys and list the deleted coupons
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Role::Verifier => "verifier",
+            Role::Reader => "reader",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        };
+    }
+
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        return match self {
+            Role::Verifier => scope == ApiKeyScope::CouponVerify,
+            Role::Reader => scope == ApiKeyScope::CouponVerify || scope == ApiKeyScope::CouponRead,
+            Role::Editor | Role::Admin => true,
+        };
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return match s {
+            "verifier" => Ok(Self::Verifier),
+            "reader" => Ok(Self::Reader),
+            "editor" => Ok(Self::Editor),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("{} is not a supported role.", other)),
+        };
+    }
+}
+
+/// Middleware that rejects the request with `403` when the role of the session does not allow it.
+/// Registered with `wrap_fn` before the `validator` (`startup.rs`), so the `validator` runs first
+/// and the session is already in the request extensions.
+pub fn authorize_role<S, B>(request: ServiceRequest, service: &S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let response = check_role(&request).map(|_| service.call(request));
+    async move {
+        return response?.await;
+    }
+}
+
+pub fn check_role(request: &ServiceRequest) -> Result<(), actix_web::Error> {
+    let role = match request.extensions().get::<Session>() {
+        Some(session) => session.role,
+        None => return Err(actix_web::error::ErrorUnauthorized("Request is not authenticated.")),
+    };
+
+    if let Some(scope) = required_scope(request.method(), request.path()) {
+        if (!role.allows(scope)){
+            return Err(actix_web::error::ErrorForbidden(format!("Role `{}` does not allow `{}`.", role.as_str(), scope.as_str())));
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Role, check_role};
+    use crate::authentication::{ApiKeyScope, Session};
+    use actix_web::{HttpMessage, http::Method, test::TestRequest};
+    use claim::{assert_err, assert_ok};
+
+    fn request(method: Method, path: &str, role: Option<Role>) -> actix_web::dev::ServiceRequest {
+        let request = TestRequest::default().method(method).uri(path).to_srv_request();
+        if let Some(role) = role {
+            request.extensions_mut().insert(Session { role, ..Default::default() });
+        }
+        return request;
+    }
+
+    #[test]
+    fn roles_allow_their_scopes(){
+        assert!(Role::Verifier.allows(ApiKeyScope::CouponVerify));
+        assert!(!Role::Verifier.allows(ApiKeyScope::CouponRead));
+        assert!(!Role::Verifier.allows(ApiKeyScope::CouponRedeem));
+        assert!(Role::Reader.allows(ApiKeyScope::CouponRead));
+        assert!(!Role::Reader.allows(ApiKeyScope::CouponWrite));
+        assert!(ApiKeyScope::all().into_iter().all(|scope| Role::Editor.allows(scope) && Role::Admin.allows(scope)));
+    }
+
+    #[test]
+    fn verifier_can_only_verify(){
+        assert_ok!(check_role(&request(Method::GET, "/coupon/verify/TEST", Some(Role::Verifier))));
+        assert_ok!(check_role(&request(Method::POST, "/coupon/TEST/quote", Some(Role::Verifier))));
+        assert_err!(check_role(&request(Method::GET, "/coupon/TEST", Some(Role::Verifier))));
+        assert_err!(check_role(&request(Method::DELETE, "/coupon/TEST", Some(Role::Verifier))));
+        assert_err!(check_role(&request(Method::POST, "/coupon/TEST/redeem", Some(Role::Verifier))));
+    }
+
+    #[test]
+    fn reader_can_not_write(){
+        assert_ok!(check_role(&request(Method::GET, "/coupon", Some(Role::Reader))));
+        assert_ok!(check_role(&request(Method::GET, "/product/PRO", Some(Role::Reader))));
+        assert_err!(check_role(&request(Method::POST, "/coupon", Some(Role::Reader))));
+        assert_err!(check_role(&request(Method::PUT, "/campaign/1", Some(Role::Reader))));
+        assert_ok!(check_role(&request(Method::PUT, "/campaign/1", Some(Role::Editor))));
+    }
+
+    #[test]
+    fn request_without_session_is_rejected(){
+        assert_err!(check_role(&request(Method::GET, "/coupon", None)));
+    }
+
+    #[test]
+    fn roles_are_parsed(){
+        assert_eq!(Role::try_from("verifier").unwrap(), Role::Verifier);
+        assert_eq!(Role::try_from(Role::Admin.as_str()).unwrap(), Role::Admin);
+        assert_err!(Role::try_from("owner"));
+    }
+}
@@ -0,0 +1,198 @@
+use super::Session;
+use crate::configuration::SessionMode;
+use hmac::{Hmac, Mac};
+use redis::AsyncCommands;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::types::chrono::{NaiveDateTime, Utc};
+
+
+const MIN_SIGNING_KEY_LENGTH: usize = 32;
+
+/// Payload of a signed session token.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionClaims {
+    // the session id, also used as the key of the denylist
+    pub sid: String,
+    // expiration as a unix timestamp (in seconds)
+    pub exp: i64,
+    #[serde(flatten)]
+    pub session: Session,
+}
+
+/// Issues and validates the signed session tokens: `<base64 claims>.<base64 HMAC-SHA256 of the claims>`.
+#[derive(Debug)]
+pub struct SessionTokens {
+    mode: SessionMode,
+    signing_key: Option<Secret<String>>,
+}
+
+impl SessionTokens {
+    pub fn new(mode: SessionMode, signing_key: Option<Secret<String>>) -> Result<Self, String> {
+        if let Some(signing_key) = signing_key.as_ref() {
+            if (signing_key.expose_secret().len() < MIN_SIGNING_KEY_LENGTH){
+                return Err(format!("`session_signing_key` must have at least {} characters.", MIN_SIGNING_KEY_LENGTH));
+            }
+        }
+        if (mode == SessionMode::Signed && signing_key.is_none()){
+            return Err("`session_signing_key` is required by the `signed` session mode.".to_string());
+        }
+        return Ok(Self { mode, signing_key });
+    }
+
+    pub fn mode(&self) -> SessionMode {
+        return self.mode;
+    }
+
+    fn mac(&self) -> Result<Hmac<Sha256>, String> {
+        let signing_key = self.signing_key.as_ref()
+            .ok_or_else(|| "Signed session tokens are not enabled.".to_string())?;
+        return Hmac::<Sha256>::new_from_slice(signing_key.expose_secret().as_bytes())
+            .map_err(|e| format!("Invalid session signing key: {}.", e));
+    }
+
+    /// Sign the session, the token expires in `expiration` seconds.
+    pub fn sign(&self, session: &Session, expiration: usize) -> Result<String, String> {
+        let claims = SessionClaims {
+            sid: session.id.clone(),
+            exp: Utc::now().timestamp() + expiration as i64,
+            session: session.clone(),
+        };
+        let claims = serde_json::to_vec(&claims)
+            .map_err(|e| format!("Failed to serialize session claims: {}.", e))?;
+
+        let mut mac = self.mac()?;
+        mac.update(&claims);
+        let signature = mac.finalize().into_bytes();
+
+        return Ok(format!("{}.{}", base64::encode(claims), base64::encode(signature)));
+    }
+
+    /// Check the signature (in constant time) and the expiration of the token and return its claims.
+    pub fn verify(&self, token: &str) -> Result<SessionClaims, String> {
+        let (claims, signature) = token.split_once('.')
+            .ok_or_else(|| "Session token is malformed.".to_string())?;
+        let claims = base64::decode(claims)
+            .map_err(|e| format!("Failed to decode session claims: {}.", e))?;
+        let signature = base64::decode(signature)
+            .map_err(|e| format!("Failed to decode session signature: {}.", e))?;
+
+        let mut mac = self.mac()?;
+        mac.update(&claims);
+        mac.verify_slice(&signature)
+            .map_err(|_| "Session token signature is invalid.".to_string())?;
+
+        let mut claims: SessionClaims = serde_json::from_slice(&claims)
+            .map_err(|e| format!("Failed to parse session claims: {}.", e))?;
+        if (claims.exp <= Utc::now().timestamp()){
+            return Err("Session token has expired.".to_string());
+        }
+        claims.session.id = claims.sid.clone();
+        claims.session.expires_at = NaiveDateTime::from_timestamp_opt(claims.exp, 0);
+
+        return Ok(claims);
+    }
+}
+
+fn denylist_key(session_id: &str) -> String {
+    return format!("session_denylist:{}", session_id);
+}
+
+/// Revoke the session: delete it from Redis and add it to the denylist of the signed tokens,
+/// until `expiration` (in seconds) when the token would have expired anyway.
+pub async fn revoke_session(redis: &redis::Client, session_id: &str, expiration: usize) -> Result<(), redis::RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+    connection.del::<_, ()>(session_id).await?;
+    if (expiration > 0){
+        connection.set_ex::<_, _, ()>(denylist_key(session_id), 1, expiration).await?;
+    }
+    return Ok(());
+}
+
+/// Check if the signed session was revoked.
+/// A Redis failure is only logged, so the signed tokens keep working during a Redis outage.
+pub async fn is_session_revoked(redis: &redis::Client, session_id: &str) -> bool {
+    let result: Result<bool, redis::RedisError> = async {
+        let mut connection = redis.get_async_connection().await?;
+        return connection.exists(denylist_key(session_id)).await;
+    }.await;
+
+    return match result {
+        Ok(revoked) => revoked,
+        Err(error) => {
+            tracing::warn!("Failed to check the session denylist, the session is accepted: {:?}", error);
+            false
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionTokens, Session};
+    use crate::authentication::{ApiKeyScope, Role};
+    use crate::configuration::SessionMode;
+    use claim::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    fn tokens(signing_key: &str) -> SessionTokens {
+        return SessionTokens::new(SessionMode::Signed, Some(Secret::new(signing_key.to_string()))).unwrap();
+    }
+
+    fn session() -> Session {
+        return Session { id: "1234".to_string(), api_key_name: "checkout".to_string(), role: Role::Verifier, tenant: "acme".to_string(), scopes: vec![ApiKeyScope::CouponVerify], expires_at: None };
+    }
+
+    #[test]
+    fn signed_token_is_verified(){
+        let tokens = tokens("0123456789abcdef0123456789abcdef");
+        let token = tokens.sign(&session(), 60).unwrap();
+
+        let claims = tokens.verify(&token).unwrap();
+        assert_eq!(claims.sid, "1234");
+        assert_eq!(claims.session.id, "1234");
+        assert_eq!(claims.session.api_key_name, "checkout");
+        assert_eq!(claims.session.tenant, "acme");
+        assert_eq!(claims.session.scopes, vec![ApiKeyScope::CouponVerify]);
+        assert_eq!(claims.session.expires_at.unwrap().timestamp(), claims.exp);
+    }
+
+    #[test]
+    fn tampered_token_is_rejected(){
+        let tokens = tokens("0123456789abcdef0123456789abcdef");
+        let token = tokens.sign(&session(), 60).unwrap();
+        let (_, signature) = token.split_once('.').unwrap();
+
+        let mut admin_session = session();
+        admin_session.role = Role::Admin;
+        let admin_token = tokens.sign(&admin_session, 60).unwrap();
+        let (admin_claims, _) = admin_token.split_once('.').unwrap();
+
+        assert_err!(tokens.verify(&format!("{}.{}", admin_claims, signature)));
+        assert_err!(tokens.verify("1234"));
+        assert_err!(tokens.verify(""));
+    }
+
+    #[test]
+    fn token_signed_with_another_key_is_rejected(){
+        let token = tokens("0123456789abcdef0123456789abcdef").sign(&session(), 60).unwrap();
+        assert_err!(tokens("fedcba9876543210fedcba9876543210").verify(&token));
+
+        let redis_tokens = SessionTokens::new(SessionMode::Redis, None).unwrap();
+        assert_err!(redis_tokens.verify(&token));
+    }
+
+    #[test]
+    fn expired_token_is_rejected(){
+        let tokens = tokens("0123456789abcdef0123456789abcdef");
+        let token = tokens.sign(&session(), 0).unwrap();
+        assert_err!(tokens.verify(&token));
+    }
+
+    #[test]
+    fn signed_mode_requires_a_long_signing_key(){
+        assert_err!(SessionTokens::new(SessionMode::Signed, None));
+        assert_err!(SessionTokens::new(SessionMode::Redis, Some(Secret::new("short".to_string()))));
+        assert_ok!(SessionTokens::new(SessionMode::Redis, None));
+    }
+}
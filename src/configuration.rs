@@ -16,6 +16,14 @@ pub struct Settings {
 #[derive(Debug, Clone, Deserialize)]
 pub struct ApiKey(pub Secret<String>);
 
+// Wrapper type to retrieve the optional admin key from the app data (retrieval is type-based)
+#[derive(Debug, Clone)]
+pub struct AdminApiKey(pub Option<ApiKey>);
+
+// Wrapper type to retrieve the session time to live (in seconds) from the app data
+#[derive(Debug, Clone)]
+pub struct SessionTtl(pub usize);
+
 
 /// The possible runtime environment for our application.
 #[derive(Debug, Clone, Deserialize)]
@@ -31,6 +39,85 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub api_key: ApiKey,
+    // sessions authenticated with this key have the `admin` role, e.g. they can list the deleted coupons
+    #[serde(default)]
+    pub admin_api_key: Option<ApiKey>,
+    // time to live of the cached coupons in seconds, `0` disables the cache
+    #[serde(default = "default_coupon_cache_ttl", deserialize_with = "deserialize_number_from_string")]
+    pub coupon_cache_ttl: usize,
+    // time to live of the sessions created in `/auth` in seconds
+    #[serde(default = "default_session_ttl", deserialize_with = "deserialize_number_from_string")]
+    pub session_ttl: usize,
+    // `redis` stores the sessions in Redis, `signed` issues tokens validated without Redis
+    #[serde(default)]
+    pub session_mode: SessionMode,
+    // HMAC-SHA256 key of the signed session tokens, required by the `signed` mode
+    #[serde(default)]
+    pub session_signing_key: Option<Secret<String>>,
+    #[serde(default)]
+    pub auth_throttle: AuthThrottleSettings,
+}
+
+/// Lockout of the clients (by IP) after failed attempts in `/auth`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthThrottleSettings {
+    // failed attempts before the first lockout, `0` disables the lockout
+    #[serde(default = "default_max_failed_attempts", deserialize_with = "deserialize_number_from_string")]
+    pub max_failed_attempts: usize,
+    // duration of the first lockout in seconds, it doubles on every failed attempt after it
+    #[serde(default = "default_lockout_seconds", deserialize_with = "deserialize_number_from_string")]
+    pub lockout_seconds: usize,
+    // the lockout never takes longer than this, and the failed attempts are forgotten after it
+    #[serde(default = "default_max_lockout_seconds", deserialize_with = "deserialize_number_from_string")]
+    pub max_lockout_seconds: usize,
+    // read the client IP from the right-most `X-Forwarded-For` address, only enable it when the app is
+    // only reachable through one proxy that appends the client address to the header
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for AuthThrottleSettings {
+    fn default() -> Self {
+        return Self {
+            max_failed_attempts: default_max_failed_attempts(),
+            lockout_seconds: default_lockout_seconds(),
+            max_lockout_seconds: default_max_lockout_seconds(),
+            trust_forwarded_for: false,
+        };
+    }
+}
+
+fn default_max_failed_attempts() -> usize {
+    return 5;
+}
+
+fn default_lockout_seconds() -> usize {
+    return 30;
+}
+
+fn default_max_lockout_seconds() -> usize {
+    // 1 hour
+    return 60 * 60;
+}
+
+/// How `/auth` issues the sessions, the `validator` accepts both kinds of bearer tokens
+/// (if the `session_signing_key` is set) so the mode can be switched without logging out the clients.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMode {
+    #[default]
+    Redis,
+    Signed,
+}
+
+fn default_coupon_cache_ttl() -> usize {
+    // 5 minutes
+    return 5 * 60;
+}
+
+fn default_session_ttl() -> usize {
+    // 1 hour
+    return 60 * 60;
 }
 
 #[derive(Debug, Clone, Deserialize)]
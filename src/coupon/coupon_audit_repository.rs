@@ -0,0 +1,118 @@
+use super::model::{Coupon, CouponAudit, CouponAuditAction, CouponAuditActor, CouponRule, CouponSnapshot};
+use super::coupon_repository::parse_rule;
+use sqlx::{MySql, MySqlPool, Transaction, query, query_as};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+/// Get the current state of the coupon (deleted or not) as JSON, to be recorded in the audit.
+/// The coupon row is locked (`FOR UPDATE`) until the end of the transaction, so the snapshot taken
+/// before a change is the state that the change was applied to.
+pub async fn get_snapshot(coupon_id: i32, transaction: &mut Transaction<'_, MySql>) -> Result<Option<String>, sqlx::Error> {
+    let coupon = query_as!(Coupon,
+        r#"SELECT id
+        , code
+        , campaign_id
+        , discount
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
+        , max_usage_count
+        , max_usage_per_customer
+        , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
+        , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
+        , date_created as `date_created: Option<NaiveDateTime>`
+        , date_updated as `date_updated: NaiveDateTime`
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE id = ?
+        FOR UPDATE
+        "#, coupon_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    let coupon = match coupon {
+        Some(coupon) => coupon,
+        None => return Ok(None),
+    };
+
+    let rows = query!(
+        r#"SELECT rule
+            FROM coupon_rule
+            WHERE coupon_id = ?
+            ORDER BY id
+        "#, coupon_id
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    let rules = rows.into_iter()
+        .map(|row| parse_rule(&row.rule))
+        .collect::<Result<Vec<CouponRule>, sqlx::Error>>()?;
+
+    let snapshot = serde_json::to_string(&CouponSnapshot { coupon, rules })
+        .map_err(|error| sqlx::Error::Protocol(format!("Failed to serialize coupon snapshot: {}", error)))?;
+
+    return Ok(Some(snapshot));
+}
+
+/// Record a change of the coupon, it must be called in the transaction of the change.
+pub async fn insert_audit(coupon_id: i32, action: CouponAuditAction, actor: &CouponAuditActor, before: Option<String>, after: Option<String>, transaction: &mut Transaction<'_, MySql>) -> Result<(), sqlx::Error> {
+    query!(
+        r#"
+            INSERT INTO coupon_audit
+            (coupon_id, action, api_key_name, session_hash, before_snapshot, after_snapshot, date_created)
+            VALUES
+            (?, ?, ?, ?, ?, ?, NOW())
+        "#,
+        coupon_id,
+        action.as_str(),
+        actor.api_key_name,
+        actor.session_hash,
+        before,
+        after,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    return Ok(());
+}
+
+pub async fn get_audits_by_coupon_id(coupon_id: i32, pool: &MySqlPool) -> Result<Vec<CouponAudit>, sqlx::Error> {
+    let audits = query_as!(CouponAudit,
+        r#"SELECT id
+        , coupon_id
+        , action
+        , api_key_name
+        , session_hash
+        , before_snapshot
+        , after_snapshot
+        , date_created as `date_created: NaiveDateTime`
+        FROM coupon_audit WHERE coupon_id = ?
+        ORDER BY id
+        "#, coupon_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(audits);
+}
@@ -0,0 +1,93 @@
+use super::model::CouponResponse;
+use redis::{AsyncCommands, aio::Connection};
+
+
+/// Read-through cache of the coupons, stored in Redis by tenant and id and by tenant and code.
+/// Redis errors are only logged, so the callers can fall back to MySQL when it is unavailable.
+#[derive(Clone, Debug)]
+pub struct CouponCache {
+    client: redis::Client,
+    // time to live of the cached coupons, in seconds
+    ttl: usize,
+}
+
+impl CouponCache {
+    pub fn new(client: redis::Client, ttl: usize) -> Self {
+        return Self { client, ttl };
+    }
+
+    pub async fn get_by_id(&self, tenant: &str, id: i32) -> Option<CouponResponse> {
+        return self.get(&id_key(tenant, id)).await;
+    }
+
+    pub async fn get_by_code(&self, tenant: &str, code: &str) -> Option<CouponResponse> {
+        return self.get(&code_key(tenant, code)).await;
+    }
+
+    /// Cache the coupon of the tenant under both its id and code keys.
+    pub async fn set(&self, tenant: &str, coupon: &CouponResponse) {
+        if (self.ttl == 0){
+            return;
+        }
+        let value = match serde_json::to_string(coupon) {
+            Ok(value) => value,
+            Err(error) => {
+                tracing::error!("Failed to serialize coupon `{}` to cache: {:?}", coupon.code, error);
+                return;
+            }
+        };
+        let Some(mut con) = self.connection().await else { return };
+
+        let result: Result<(), redis::RedisError> = redis::pipe()
+            .atomic()
+            .set_ex(id_key(tenant, coupon.id), &value, self.ttl).ignore()
+            .set_ex(code_key(tenant, &coupon.code), &value, self.ttl).ignore()
+            .query_async(&mut con)
+            .await;
+        if let Err(error) = result {
+            tracing::warn!("Failed to cache coupon `{}`: {:?}", coupon.code, error);
+        }
+    }
+
+    /// Remove the coupon from the cache, must be called every time the coupon changes.
+    pub async fn invalidate(&self, tenant: &str, id: i32, code: &str) {
+        let Some(mut con) = self.connection().await else { return };
+
+        let result: Result<(), redis::RedisError> = con.del(&[id_key(tenant, id), code_key(tenant, code)]).await;
+        if let Err(error) = result {
+            tracing::warn!("Failed to invalidate cached coupon `{}`: {:?}", code, error);
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<CouponResponse> {
+        if (self.ttl == 0){
+            return None;
+        }
+        let mut con = self.connection().await?;
+
+        let value: Option<String> = con.get(key).await
+            .map_err(|error| tracing::warn!("Failed to get `{}` from cache: {:?}", key, error))
+            .ok()?;
+
+        // an entry that can't be parsed (e.g. from an older version of the API) is treated as a cache miss
+        return value.and_then(|value| serde_json::from_str(&value)
+            .map_err(|error| tracing::warn!("Failed to parse cached `{}`: {:?}", key, error))
+            .ok()
+        );
+    }
+
+    async fn connection(&self) -> Option<Connection> {
+        return self.client.get_async_connection().await
+            .map_err(|error| tracing::warn!("Failed to get `redis` connection, skipping the coupon cache: {:?}", error))
+            .ok();
+    }
+}
+
+// the same code can be used by several tenants
+fn id_key(tenant: &str, id: i32) -> String {
+    return format!("coupon:{}:id:{}", tenant, id);
+}
+
+fn code_key(tenant: &str, code: &str) -> String {
+    return format!("coupon:{}:code:{}", tenant, code);
+}
@@ -0,0 +1,47 @@
+use super::model::{CouponError, CampaignRequest};
+use crate::authentication::Session;
+use super::{coupon_cache::CouponCache, coupon_campaign_service};
+use actix_web::{
+    web, get, post, put, delete, HttpResponse,
+    web::Data,
+};
+use sqlx::MySqlPool;
+
+
+/*
+    coupon campaigns, registered in the `/campaign` scope
+*/
+#[tracing::instrument( name = "Get all campaigns", skip(pool) )]
+#[get("")]
+pub async fn get_campaigns(session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let campaigns = coupon_campaign_service::list_campaigns(&session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(campaigns));
+}
+
+#[tracing::instrument( name = "Get campaign", skip(pool) )]
+#[get("/{id}")]
+pub async fn get_campaign(param: web::Path<i32>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let campaign = coupon_campaign_service::find_campaign(param.into_inner(), &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(campaign));
+}
+
+#[tracing::instrument( name = "Post campaign", skip(pool) )]
+#[post("")]
+pub async fn add_campaign(request: web::Json<CampaignRequest>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let campaign = coupon_campaign_service::create_campaign(request.0, &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Created().json(campaign));
+}
+
+#[tracing::instrument( name = "Put campaign", skip(pool) )]
+#[put("/{id}")]
+pub async fn update_campaign(param: web::Path<i32>, request: web::Json<CampaignRequest>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    coupon_campaign_service::edit_campaign(param.into_inner(), request.0, &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().finish());
+}
+
+#[tracing::instrument( name = "Delete campaign", skip(pool, cache) )]
+#[delete("/{id}")]
+pub async fn delete_campaign(param: web::Path<i32>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    coupon_campaign_service::remove_campaign(param.into_inner(), &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::NoContent().finish());
+}
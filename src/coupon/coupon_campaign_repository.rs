@@ -0,0 +1,176 @@
+use super::model::{Campaign, CampaignInsert, CampaignStats};
+use sqlx::{MySqlPool, query, query_as};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+pub async fn insert_campaign(campaign: CampaignInsert, tenant: &str, pool: &MySqlPool) -> Result<u64, sqlx::Error> {
+    let result = query!(
+        r#"
+            INSERT INTO campaign
+            (tenant, name, description, start_date, end_date, budget, currency, active, date_created)
+            VALUES
+            (?, ?, ?, ?, ?, ?, ?, ?, NOW())
+        "#,
+        tenant,
+        campaign.name,
+        campaign.description,
+        campaign.start_date,
+        campaign.end_date,
+        campaign.budget,
+        campaign.currency,
+        campaign.active,
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.last_insert_id());
+}
+
+pub async fn update_campaign_by_id(id: i32, tenant: &str, campaign: CampaignInsert, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    query!(
+        r#"
+            UPDATE campaign SET
+            name = ?,
+            description = ?,
+            start_date = ?,
+            end_date = ?,
+            budget = ?,
+            currency = ?,
+            active = ?
+            WHERE id = ? AND tenant = ?
+        "#,
+        campaign.name,
+        campaign.description,
+        campaign.start_date,
+        campaign.end_date,
+        campaign.budget,
+        campaign.currency,
+        campaign.active,
+        id,
+        tenant
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute update query: {:?}", error);
+        error
+    })?;
+
+    return Ok(());
+}
+
+pub async fn get_all_campaigns(tenant: &str, pool: &MySqlPool) -> Result<Vec<Campaign>, sqlx::Error> {
+    let campaigns = query_as!(Campaign,
+        r#"SELECT id
+        , name
+        , description
+        , start_date as `start_date: NaiveDateTime`
+        , end_date as `end_date: NaiveDateTime`
+        , budget
+        , currency
+        , active as `active: bool`
+        , date_created as `date_created: NaiveDateTime`
+        FROM campaign WHERE tenant = ? ORDER BY id"#, tenant)
+    .fetch_all(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(campaigns);
+}
+
+pub async fn get_campaign_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Option<Campaign>, sqlx::Error> {
+    let campaign = query_as!(Campaign,
+        r#"SELECT id
+        , name
+        , description
+        , start_date as `start_date: NaiveDateTime`
+        , end_date as `end_date: NaiveDateTime`
+        , budget
+        , currency
+        , active as `active: bool`
+        , date_created as `date_created: NaiveDateTime`
+        FROM campaign WHERE id = ? AND tenant = ?
+        "#, id, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(campaign);
+}
+
+pub async fn get_campaign_by_name(name: &String, tenant: &str, pool: &MySqlPool) -> Result<Option<Campaign>, sqlx::Error> {
+    let campaign = query_as!(Campaign,
+        r#"SELECT id
+        , name
+        , description
+        , start_date as `start_date: NaiveDateTime`
+        , end_date as `end_date: NaiveDateTime`
+        , budget
+        , currency
+        , active as `active: bool`
+        , date_created as `date_created: NaiveDateTime`
+        FROM campaign WHERE name = ? AND tenant = ?
+        "#, name, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(campaign);
+}
+
+// The coupons of the campaign are kept, they just don't belong to a campaign anymore
+pub async fn delete_campaign_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    query!(
+        r#"DELETE FROM campaign
+            WHERE id = ? AND tenant = ?
+        "#, id, tenant
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute delete query: {:?}", error);
+        error
+    })?;
+
+    return Ok(());
+}
+
+/// Aggregate the redemptions of every coupon of the campaign.
+/// The deleted coupons are also counted, their redemptions still happened.
+/// Only the discounts in the `currency` of the campaign are added to the `spent_amount`.
+pub async fn get_campaign_stats(campaign_id: i32, currency: Option<&String>, tenant: &str, pool: &MySqlPool) -> Result<CampaignStats, sqlx::Error> {
+    let stats = query_as!(CampaignStats,
+        r#"SELECT COUNT(DISTINCT c.id) as coupon_count
+        , COUNT(DISTINCT r.coupon_id) as redeemed_coupon_count
+        , COUNT(r.id) as redemption_count
+        , COUNT(DISTINCT r.customer_id) as customer_count
+        , CAST(COALESCE(SUM(CASE WHEN r.currency = ? THEN r.discount_amount END), 0) AS SIGNED) as spent_amount
+        FROM coupon c
+        LEFT JOIN coupon_redemption r ON r.coupon_id = c.id
+        WHERE c.campaign_id = ? AND c.tenant = ?
+        "#, currency, campaign_id, tenant
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(stats);
+}
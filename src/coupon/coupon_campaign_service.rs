@@ -0,0 +1,118 @@
+use super::model::{Campaign, CampaignInsert, CampaignRequest, CampaignResponse, CouponError, CouponResponse, FieldError};
+use super::{coupon_cache::CouponCache, coupon_campaign_repository, coupon_repository};
+use sqlx::{MySqlPool};
+use anyhow::{Result, anyhow};
+use std::convert::TryFrom;
+
+pub async fn list_campaigns(tenant: &str, pool: &MySqlPool) -> Result<Vec<Campaign>, CouponError> {
+    return coupon_campaign_repository::get_all_campaigns(tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()));
+}
+
+async fn find_campaign_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Campaign, CouponError> {
+    let result = coupon_campaign_repository::get_campaign_by_id(id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    return result.ok_or(CouponError::NotFoundError(anyhow!(format!("Campaign with id `{}` not found.", id))));
+}
+
+/// Get the campaign of the tenant with its coupons and their aggregated redemption stats.
+pub async fn find_campaign(id: i32, tenant: &str, pool: &MySqlPool) -> Result<CampaignResponse, CouponError> {
+    let campaign = find_campaign_by_id(id, tenant, pool).await?;
+
+    let stats = coupon_campaign_repository::get_campaign_stats(campaign.id, campaign.currency.as_ref(), tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    let remaining_budget = campaign.budget.map(|budget| i64::from(budget) - stats.spent_amount);
+
+    let coupons = coupon_repository::get_by_campaign_id(campaign.id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    let coupon_ids: Vec<i32> = coupons.iter().map(|coupon| coupon.id).collect();
+    let rules = coupon_repository::get_rules_by_coupon_ids(&coupon_ids, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    let coupons = coupons
+        .into_iter()
+        .map(CouponResponse::try_from)
+        .collect::<Result<Vec<CouponResponse>, String>>()
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponResponse: {}.", e))))?
+        .into_iter()
+        .map(|mut coupon| {
+            coupon.rules = rules.iter()
+                .filter(|(coupon_id, _)| *coupon_id == coupon.id)
+                .map(|(_, rule)| rule.clone())
+                .collect();
+            return coupon;
+        })
+        .collect();
+
+    return Ok(CampaignResponse { campaign, stats, remaining_budget, coupons });
+}
+
+pub async fn create_campaign(campaign_request: CampaignRequest, tenant: &str, pool: &MySqlPool) -> Result<CampaignResponse, CouponError> {
+    let campaign_insert: CampaignInsert = campaign_request.try_into()
+        .map_err(CouponError::FieldValidationError)?;
+
+    check_campaign_name_is_available(&campaign_insert.name, None, tenant, pool).await?;
+
+    let inserted_id = coupon_campaign_repository::insert_campaign(campaign_insert, tenant, pool).await
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Something went wrong and the campaign was not inserted: {}", e))))?;
+
+    let inserted_id = i32::try_from(inserted_id)
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to read inserted_id: {}", e))))?;
+
+    return find_campaign(inserted_id, tenant, pool).await;
+}
+
+/// Update the campaign, setting `active` to `false` invalidates all of its coupons at once.
+pub async fn edit_campaign(id: i32, campaign_request: CampaignRequest, tenant: &str, pool: &MySqlPool) -> Result<(), CouponError> {
+    let campaign = find_campaign_by_id(id, tenant, pool).await?;
+
+    let campaign_update: CampaignInsert = campaign_request.try_into()
+        .map_err(CouponError::FieldValidationError)?;
+
+    check_campaign_name_is_available(&campaign_update.name, Some(campaign.id), tenant, pool).await?;
+
+    coupon_campaign_repository::update_campaign_by_id(campaign.id, tenant, campaign_update, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    return Ok(());
+}
+
+pub async fn remove_campaign(id: i32, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
+    let campaign = find_campaign_by_id(id, tenant, pool).await?;
+    let coupons = coupon_repository::get_by_campaign_id(campaign.id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    coupon_campaign_repository::delete_campaign_by_id(campaign.id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    // the `campaign_id` of the coupons was set to NULL
+    for coupon in coupons {
+        cache.invalidate(tenant, coupon.id, &coupon.code).await;
+    }
+    return Ok(());
+}
+
+/// Check that the campaign a coupon is being added to exists in the tenant of the coupon.
+pub async fn check_campaign_exists(campaign_id: Option<i32>, tenant: &str, pool: &MySqlPool) -> Result<(), CouponError> {
+    if let Some(campaign_id) = campaign_id {
+        let campaign = coupon_campaign_repository::get_campaign_by_id(campaign_id, tenant, pool).await
+            .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+        if (campaign.is_none()){
+            return Err(CouponError::FieldValidationError(FieldError::new("campaign_id", format!("Campaign with id `{}` not found.", campaign_id))));
+        }
+    }
+    return Ok(());
+}
+
+async fn check_campaign_name_is_available(name: &String, campaign_id: Option<i32>, tenant: &str, pool: &MySqlPool) -> Result<(), CouponError> {
+    let campaign = coupon_campaign_repository::get_campaign_by_name(name, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    if let Some(campaign) = campaign {
+        if (Some(campaign.id) != campaign_id){
+            return Err(CouponError::AlreadyExistsError(anyhow!(format!("Campaign with name `{}` already exists.", name))));
+        }
+    }
+    return Ok(());
+}
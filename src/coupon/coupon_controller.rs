@@ -1,52 +1,104 @@
-use super::model::{CouponInsertRequest, CouponError, CouponUpdateRequest};
-use super::coupon_service;
+use super::model::{CouponInsertRequest, CouponError, CouponUpdateRequest, CouponPatchRequest, CustomerQuery, CouponQuoteRequest, VerifyQuery, CouponBatchRequest, CouponListQuery, CouponAuditActor, coupon_etag, is_not_modified};
+use crate::{authentication::{Role, Session}, startup::ApplicationBaseUrl};
+use super::{coupon_cache::CouponCache, coupon_service};
 use actix_web::{
-    web, get, post, put, delete, HttpResponse, Responder,
+    web, get, post, put, patch, delete, HttpResponse, Responder,
     web::Data,
+    http::header::{ETag, IfMatch, IfNoneMatch},
 };
+use anyhow::anyhow;
 use sqlx::MySqlPool;
 
 
-#[tracing::instrument( name = "Get all coupons", skip(pool) )]
+#[tracing::instrument( name = "Get all coupons", skip(pool, base_url) )]
 #[get("")]
-pub async fn get_all_coupons(pool: Data::<MySqlPool>) -> Result<impl Responder, CouponError> {
-    let coupons = coupon_service::get_all(&pool).await?;
+pub async fn get_all_coupons(query: web::Query<CouponListQuery>, session: Session, pool: Data::<MySqlPool>, base_url: Data::<ApplicationBaseUrl>) -> Result<impl Responder, CouponError> {
+    if (query.deleted == Some(true) && session.role != Role::Admin){
+        return Err(CouponError::ForbiddenError(anyhow!("Only admin sessions can list the deleted coupons.")));
+    }
+    let coupons = coupon_service::get_all(query.into_inner(), &session.tenant, &base_url.0, &pool).await?;
     return Ok(web::Json(coupons));
 }
 
-#[tracing::instrument( name = "Get coupon", skip(pool) )]
+#[tracing::instrument( name = "Get coupon", skip(pool, cache) )]
 #[get("/{id_or_code}")]
-pub async fn get_coupon(param: web::Path<String>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
-    let coupon = coupon_service::get_by_id_or_code(param.into_inner(), &pool).await?;
-    return Ok(HttpResponse::Ok().json(coupon));
+pub async fn get_coupon(param: web::Path<String>, if_none_match: web::Header<IfNoneMatch>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let coupon = coupon_service::get_by_id_or_code(param.into_inner(), &session.tenant, &cache, &pool).await?;
+    let etag = ETag(coupon_etag(coupon.version));
+    if (is_not_modified(Some(&if_none_match), coupon.version)){
+        return Ok(HttpResponse::NotModified().insert_header(etag).finish());
+    }
+    return Ok(HttpResponse::Ok().insert_header(etag).json(coupon));
 }
 
-#[tracing::instrument( name = "Put coupon", skip(pool) )]
+#[tracing::instrument( name = "Put coupon", skip(pool, cache) )]
 #[put("/{id_or_code}")]
-pub async fn update_coupon(params: web::Path<String>, request: web::Json<CouponUpdateRequest>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
-    coupon_service::update(params.into_inner(), request.0, &pool).await?;
+pub async fn update_coupon(params: web::Path<String>, request: web::Json<CouponUpdateRequest>, if_match: web::Header<IfMatch>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    coupon_service::update(params.into_inner(), request.0, Some(&if_match), &session.tenant, &CouponAuditActor::from(&session), &cache, &pool).await?;
     return Ok(HttpResponse::Ok().finish());
 }
 
-#[tracing::instrument( name = "Delete coupon", skip(pool) )]
+#[tracing::instrument( name = "Patch coupon", skip(pool, cache) )]
+#[patch("/{id_or_code}")]
+pub async fn patch_coupon(params: web::Path<String>, request: web::Json<CouponPatchRequest>, if_match: web::Header<IfMatch>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let coupon = coupon_service::patch(params.into_inner(), request.0, Some(&if_match), &session.tenant, &CouponAuditActor::from(&session), &cache, &pool).await?;
+    return Ok(HttpResponse::Ok().insert_header(ETag(coupon_etag(coupon.version))).json(coupon));
+}
+
+#[tracing::instrument( name = "Delete coupon", skip(pool, cache) )]
 #[delete("/{id_or_code}")]
-pub async fn delete_coupon(param: web::Path<String>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
-    coupon_service::delete(param.into_inner(), &pool).await?;
+pub async fn delete_coupon(param: web::Path<String>, if_match: web::Header<IfMatch>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    coupon_service::delete(param.into_inner(), Some(&if_match), &session.tenant, &CouponAuditActor::from(&session), &cache, &pool).await?;
     return Ok(HttpResponse::NoContent().finish());
 }
 
-#[tracing::instrument( name = "Post coupon", skip(pool) )]
+#[tracing::instrument( name = "Restore coupon", skip(pool, cache) )]
+#[post("/{id}/restore")]
+pub async fn restore_coupon(param: web::Path<i32>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let coupon = coupon_service::restore(param.into_inner(), &session.tenant, &CouponAuditActor::from(&session), &cache, &pool).await?;
+    return Ok(HttpResponse::Ok().insert_header(ETag(coupon_etag(coupon.version))).json(coupon));
+}
+
+#[tracing::instrument( name = "Get coupon history", skip(pool) )]
+#[get("/{id}/history")]
+pub async fn get_coupon_history(param: web::Path<i32>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let history = coupon_service::get_history(param.into_inner(), &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(history));
+}
+
+#[tracing::instrument( name = "Post coupon", skip(pool, cache) )]
 #[post("")]
-pub async fn add_coupon(request: web::Json<CouponInsertRequest>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
-    let coupon = coupon_service::insert(request.0, &pool).await?;
+pub async fn add_coupon(request: web::Json<CouponInsertRequest>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let coupon = coupon_service::insert(request.0, &session.tenant, &CouponAuditActor::from(&session), &cache, &pool).await?;
     return Ok(HttpResponse::Created().json(coupon));
 }
 
-#[tracing::instrument( name = "Verify coupon", skip(pool) )]
+#[tracing::instrument( name = "Post coupon batch", skip(pool) )]
+#[post("/batch")]
+pub async fn add_coupon_batch(request: web::Json<CouponBatchRequest>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let batch = coupon_service::insert_batch(request.0, &session.tenant, &CouponAuditActor::from(&session), &pool).await?;
+    return Ok(HttpResponse::Created().json(batch));
+}
+
+#[tracing::instrument( name = "Verify coupon", skip(pool, cache) )]
 #[get("/verify/{id_or_code}")]
-pub async fn verify_coupon(param: web::Path<String>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
-    let valid_coupon = coupon_service::is_valid(param.into_inner(), &pool).await?;
-    return Ok(HttpResponse::Ok().body(valid_coupon.to_string()));
+pub async fn verify_coupon(param: web::Path<String>, query: web::Query<VerifyQuery>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let verification = coupon_service::is_valid(param.into_inner(), query.into_inner(), &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::Ok().json(verification));
 }
 
 
+
+#[tracing::instrument( name = "Redeem coupon", skip(pool, cache) )]
+#[post("/{id_or_code}/redeem")]
+pub async fn redeem_coupon(param: web::Path<String>, query: web::Query<VerifyQuery>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let redemption = coupon_service::redeem(param.into_inner(), query.into_inner(), &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::Created().json(redemption));
+}
+
+#[tracing::instrument( name = "Quote coupon", skip(pool, cache) )]
+#[post("/{id_or_code}/quote")]
+pub async fn quote_coupon(param: web::Path<String>, request: web::Json<CouponQuoteRequest>, query: web::Query<CustomerQuery>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let quote = coupon_service::quote(param.into_inner(), request.0, query.into_inner(), &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::Ok().json(quote));
+}
@@ -0,0 +1,66 @@
+use super::model::{CouponError, ProductInsertRequest};
+use crate::authentication::Session;
+use super::{coupon_cache::CouponCache, coupon_product_service};
+use actix_web::{
+    web, get, post, put, delete, HttpResponse,
+    web::Data,
+};
+use sqlx::MySqlPool;
+
+
+/*
+    product catalog, registered in the `/product` scope
+*/
+#[tracing::instrument( name = "Get all products", skip(pool) )]
+#[get("")]
+pub async fn get_products(session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let products = coupon_product_service::list_products(&session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(products));
+}
+
+#[tracing::instrument( name = "Get product", skip(pool) )]
+#[get("/{code}")]
+pub async fn get_product(param: web::Path<String>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let product = coupon_product_service::find_product(param.into_inner(), &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Ok().json(product));
+}
+
+#[tracing::instrument( name = "Post product", skip(pool) )]
+#[post("")]
+pub async fn add_product(request: web::Json<ProductInsertRequest>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let product = coupon_product_service::create_product(request.0, &session.tenant, &pool).await?;
+    return Ok(HttpResponse::Created().json(product));
+}
+
+#[tracing::instrument( name = "Delete product", skip(pool) )]
+#[delete("/{code}")]
+pub async fn delete_product(param: web::Path<String>, session: Session, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    coupon_product_service::remove_product(param.into_inner(), &session.tenant, &pool).await?;
+    return Ok(HttpResponse::NoContent().finish());
+}
+
+/*
+    products a coupon is restricted to, registered in the `/coupon` scope
+*/
+#[tracing::instrument( name = "Get coupon products", skip(pool, cache) )]
+#[get("/{id_or_code}/products")]
+pub async fn get_coupon_products(param: web::Path<String>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let products = coupon_product_service::list_coupon_products(param.into_inner(), &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::Ok().json(products));
+}
+
+#[tracing::instrument( name = "Put coupon product", skip(pool, cache) )]
+#[put("/{id_or_code}/products/{product_code}")]
+pub async fn add_coupon_product(params: web::Path<(String, String)>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let (id_or_code, product_code) = params.into_inner();
+    coupon_product_service::link_coupon_product(id_or_code, product_code, &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::NoContent().finish());
+}
+
+#[tracing::instrument( name = "Delete coupon product", skip(pool, cache) )]
+#[delete("/{id_or_code}/products/{product_code}")]
+pub async fn delete_coupon_product(params: web::Path<(String, String)>, session: Session, cache: Data::<CouponCache>, pool: Data::<MySqlPool>) -> Result<HttpResponse, CouponError> {
+    let (id_or_code, product_code) = params.into_inner();
+    coupon_product_service::unlink_coupon_product(id_or_code, product_code, &session.tenant, &cache, &pool).await?;
+    return Ok(HttpResponse::NoContent().finish());
+}
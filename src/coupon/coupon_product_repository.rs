@@ -0,0 +1,189 @@
+use super::model::{Product, ProductInsert};
+use sqlx::{MySqlPool, query, query_as};
+use sqlx::mysql::MySqlDatabaseError;
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+pub async fn insert_product(product: ProductInsert, tenant: &str, pool: &MySqlPool) -> Result<u64, sqlx::Error> {
+    let result = query!(
+        r#"
+            INSERT INTO product 
+            (tenant, code, name, date_created) 
+            VALUES 
+            (?, ?, ?, NOW())
+        "#,
+        tenant,
+        product.code,
+        product.name,
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.last_insert_id());
+}
+
+pub async fn get_all_products(tenant: &str, pool: &MySqlPool) -> Result<Vec<Product>, sqlx::Error> {
+    let products = query_as!(Product,
+        r#"SELECT id
+        , code
+        , name
+        , date_created as `date_created: NaiveDateTime`
+        FROM product WHERE tenant = ? ORDER BY code"#, tenant)
+    .fetch_all(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(products);
+}
+
+pub async fn get_product_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Option<Product>, sqlx::Error> {
+    let product = query_as!(Product,
+        r#"SELECT id
+        , code
+        , name
+        , date_created as `date_created: NaiveDateTime`
+        FROM product WHERE id = ? AND tenant = ?
+        "#, id, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(product);
+}
+
+pub async fn get_product_by_code(code: &String, tenant: &str, pool: &MySqlPool) -> Result<Option<Product>, sqlx::Error> {
+    let product = query_as!(Product,
+        r#"SELECT id
+        , code
+        , name
+        , date_created as `date_created: NaiveDateTime`
+        FROM product WHERE code = ? AND tenant = ?
+        "#, code, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(product);
+}
+
+pub async fn delete_product_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    query!(
+        r#"DELETE FROM product
+            WHERE id = ? AND tenant = ?
+        "#, id, tenant
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute delete query: {:?}", error);
+        error
+    })?;
+
+    return Ok(());
+}
+
+/// Check if the error is a violation of a foreign key, e.g. a product deleted while a coupon is being restricted to it.
+pub fn is_row_referenced(error: &sqlx::Error) -> bool {
+    return match error {
+        // MySQL error 1451 `ER_ROW_IS_REFERENCED_2`
+        sqlx::Error::Database(error) => error.try_downcast_ref::<MySqlDatabaseError>()
+            .map(|error| error.number() == 1451)
+            .unwrap_or(false),
+        _ => false,
+    };
+}
+
+/// Count the coupons (including the deleted ones, so they can be restored) restricted to the product.
+pub async fn count_coupons_by_product_id(product_id: i32, pool: &MySqlPool) -> Result<i64, sqlx::Error> {
+    let result = query!(
+        r#"SELECT COUNT(*) as coupon_count
+            FROM coupon_product
+            WHERE product_id = ?
+        "#, product_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.coupon_count);
+}
+
+/// Products the coupon is restricted to, an empty list means it applies to every product.
+pub async fn get_products_by_coupon_id(coupon_id: i32, pool: &MySqlPool) -> Result<Vec<Product>, sqlx::Error> {
+    let products = query_as!(Product,
+        r#"SELECT p.id
+        , p.code
+        , p.name
+        , p.date_created as `date_created: NaiveDateTime`
+        FROM product p
+        INNER JOIN coupon_product cp ON cp.product_id = p.id
+        WHERE cp.coupon_id = ?
+        ORDER BY p.code
+        "#, coupon_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(products);
+}
+
+// Linking a product that is already linked to the coupon does nothing
+pub async fn insert_coupon_product_link(coupon_id: i32, product_id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
+    query!(
+        r#"
+            INSERT IGNORE INTO coupon_product 
+            (coupon_id, product_id) 
+            VALUES 
+            (?, ?)
+        "#,
+        coupon_id,
+        product_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    return Ok(());
+}
+
+// Returns `false` when the product was not linked to the coupon
+pub async fn delete_coupon_product_link(coupon_id: i32, product_id: i32, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let result = query!(
+        r#"DELETE FROM coupon_product
+            WHERE coupon_id = ? AND product_id = ?
+        "#, coupon_id, product_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute delete query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.rows_affected() > 0);
+}
@@ -0,0 +1,91 @@
+use super::model::{CouponError, Product, ProductInsert, ProductInsertRequest};
+use super::{coupon_cache::CouponCache, coupon_product_repository, coupon_service};
+use sqlx::{MySqlPool};
+use anyhow::{Result, anyhow};
+use std::convert::TryFrom;
+
+pub async fn list_products(tenant: &str, pool: &MySqlPool) -> Result<Vec<Product>, CouponError> {
+    return coupon_product_repository::get_all_products(tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()));
+}
+
+pub async fn find_product(code: String, tenant: &str, pool: &MySqlPool) -> Result<Product, CouponError> {
+    let result = coupon_product_repository::get_product_by_code(&code, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    return result.ok_or(CouponError::NotFoundError(anyhow!(format!("Product with code `{}` not found.", code))));
+}
+
+pub async fn create_product(product_request: ProductInsertRequest, tenant: &str, pool: &MySqlPool) -> Result<Product, CouponError> {
+    let product_insert: ProductInsert = product_request.try_into()
+        .map_err(CouponError::FieldValidationError)?;
+
+    // check if Product already exists in the tenant
+    if (find_product(product_insert.code.clone(), tenant, pool).await.is_ok()){
+        return Err(CouponError::AlreadyExistsError(anyhow!(format!("Product with code `{}` already exists.", product_insert.code))));
+    }
+
+    let inserted_id = coupon_product_repository::insert_product(product_insert, tenant, pool).await
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Something went wrong and the product was not inserted: {}", e))))?;
+
+    let inserted_id = i32::try_from(inserted_id)
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to read inserted_id: {}", e))))?;
+
+    let inserted_product = coupon_product_repository::get_product_by_id(inserted_id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    return inserted_product.ok_or(CouponError::NotFoundError(anyhow!(format!("Inserted product with id `{}` not found.", inserted_id))));
+}
+
+/// The product can only be removed once no coupon is restricted to it, otherwise the coupons
+/// would be left without restrictions and apply to every product.
+pub async fn remove_product(code: String, tenant: &str, pool: &MySqlPool) -> Result<(), CouponError> {
+    let product = find_product(code, tenant, pool).await?;
+
+    let coupon_count = coupon_product_repository::count_coupons_by_product_id(product.id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (coupon_count > 0){
+        return Err(CouponError::ConflictError(anyhow!(format!(
+            "Product with code `{}` is still linked to {} coupon(s), unlink it first.", product.code, coupon_count
+        ))));
+    }
+
+    // a coupon may have been restricted to the product after the count, the foreign key rejects the delete
+    coupon_product_repository::delete_product_by_id(product.id, tenant, pool).await
+        .map_err(|error| match coupon_product_repository::is_row_referenced(&error) {
+            true => CouponError::ConflictError(anyhow!(format!("Product with code `{}` is still linked to a coupon, unlink it first.", product.code))),
+            false => CouponError::UnexpectedError(error.into()),
+        })?;
+    return Ok(());
+}
+
+/// Products the coupon is restricted to, an empty list means it applies to every product.
+pub async fn list_coupon_products(param: String, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<Vec<Product>, CouponError> {
+    let coupon = coupon_service::get_by_id_or_code(param, tenant, cache, pool).await?;
+
+    return coupon_product_repository::get_products_by_coupon_id(coupon.id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()));
+}
+
+pub async fn link_coupon_product(param: String, product_code: String, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
+    let coupon = coupon_service::get_by_id_or_code(param, tenant, cache, pool).await?;
+    let product = find_product(product_code, tenant, pool).await?;
+
+    coupon_product_repository::insert_coupon_product_link(coupon.id, product.id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    return Ok(());
+}
+
+pub async fn unlink_coupon_product(param: String, product_code: String, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
+    let coupon = coupon_service::get_by_id_or_code(param, tenant, cache, pool).await?;
+    let product = find_product(product_code, tenant, pool).await?;
+
+    let deleted = coupon_product_repository::delete_coupon_product_link(coupon.id, product.id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (!deleted){
+        return Err(CouponError::NotFoundError(anyhow!(format!(
+            "Product with code `{}` is not linked to coupon with code `{}`.", product.code, coupon.code
+        ))));
+    }
+    return Ok(());
+}
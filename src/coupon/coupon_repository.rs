@@ -1,69 +1,310 @@
-use super::model::{Coupon, CouponInsert, CouponUpdate};
-use sqlx::{MySqlPool, query, query_as};
+use super::model::{Coupon, CouponInsert, CouponUpdate, CouponPatchRequest, CouponRedemption, CouponRule, CouponFilter, CouponAuditAction, CouponAuditActor};
+use super::coupon_audit_repository;
+use sqlx::{MySql, MySqlPool, QueryBuilder, Row, Transaction, query, query_as};
+use sqlx::mysql::MySqlDatabaseError;
 use sqlx::types::chrono::{NaiveDateTime};
 
 
-pub async fn insert(coupon: CouponInsert, pool: &MySqlPool) -> Result<u64, sqlx::Error> {
+pub async fn insert(coupon: CouponInsert, tenant: &str, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let inserted_id = insert_coupon(&coupon, tenant, actor, &mut transaction).await?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(inserted_id);
+}
+
+/// Insert every coupon in a single transaction, either all of them are inserted or none is.
+pub async fn insert_all(coupons: Vec<CouponInsert>, tenant: &str, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<Vec<u64>, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let mut inserted_ids = Vec::with_capacity(coupons.len());
+    for coupon in &coupons {
+        inserted_ids.push(insert_coupon(coupon, tenant, actor, &mut transaction).await?);
+    }
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(inserted_ids);
+}
+
+async fn insert_coupon(coupon: &CouponInsert, tenant: &str, actor: &CouponAuditActor, transaction: &mut Transaction<'_, MySql>) -> Result<u64, sqlx::Error> {
     let result = query!(
         r#"
             INSERT INTO coupon 
-            (code, discount, active, max_usage_count, expiration_date) 
+            (tenant, code, campaign_id, discount, discount_type, currency, min_order_amount, max_discount_amount, active, max_usage_count, max_usage_per_customer, valid_from, expiration_date, never_expires) 
             VALUES 
-            (?, ?, ?, ?, ?)
+            (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
+        tenant,
         coupon.code,
+        coupon.campaign_id,
         coupon.discount.as_ref(),
+        coupon.discount.discount_type().as_str(),
+        coupon.discount.currency(),
+        coupon.discount.min_order_amount(),
+        coupon.discount.max_discount_amount(),
         coupon.active,
         coupon.max_usage_count,
+        coupon.max_usage_per_customer,
+        coupon.valid_from,
         coupon.expiration_date,
+        coupon.never_expires,
     )
-    .execute(pool)
+    .execute(&mut *transaction)
     .await
     .map_err(|error| {
         tracing::error!("Failed to execute insert query: {:?}", error);
         error
     })?;
-    return Ok(result.last_insert_id());
+
+    let inserted_id = result.last_insert_id();
+    insert_rules(inserted_id, &coupon.rules, transaction).await?;
+    record_audit(inserted_id as i32, CouponAuditAction::Insert, actor, None, transaction).await?;
+
+    return Ok(inserted_id);
+}
+
+/// Check if the error is a violation of an unique key (e.g. a coupon `code` that already exists in the tenant).
+pub fn is_duplicate_entry(error: &sqlx::Error) -> bool {
+    return match error {
+        // MySQL error 1062 `ER_DUP_ENTRY`
+        sqlx::Error::Database(error) => error.try_downcast_ref::<MySqlDatabaseError>()
+            .map(|error| error.number() == 1062)
+            .unwrap_or(false),
+        _ => false,
+    };
 }
 
-pub async fn update(id: i32, coupon: CouponUpdate, pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    query!(
+/// Update the coupon and increment its `version`.
+/// With an `expected_version` nothing is updated if the coupon has another version, returns whether it was updated.
+pub async fn update(id: i32, tenant: &str, coupon: CouponUpdate, expected_version: Option<i32>, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let before = coupon_audit_repository::get_snapshot(id, &mut transaction).await?;
+
+    let result = query!(
         r#"
             UPDATE coupon SET
+            campaign_id = ?,
             discount = ?,
+            discount_type = ?,
+            currency = ?,
+            min_order_amount = ?,
+            max_discount_amount = ?,
             active = ?,
             max_usage_count = ?,
-            expiration_date = ?
-            WHERE id = ?
+            max_usage_per_customer = ?,
+            valid_from = ?,
+            expiration_date = ?,
+            never_expires = ?,
+            version = version + 1
+            WHERE id = ? AND tenant = ? AND (? IS NULL OR version = ?)
         "#,
+        coupon.campaign_id,
         coupon.discount.as_ref(),
+        coupon.discount.discount_type().as_str(),
+        coupon.discount.currency(),
+        coupon.discount.min_order_amount(),
+        coupon.discount.max_discount_amount(),
         coupon.active,
         coupon.max_usage_count,
+        coupon.max_usage_per_customer,
+        coupon.valid_from,
         coupon.expiration_date,
-        id
+        coupon.never_expires,
+        id,
+        tenant,
+        expected_version,
+        expected_version,
     )
-    .execute(pool)
+    .execute(&mut transaction)
     .await
     .map_err(|error| {
         tracing::error!("Failed to execute update query: {:?}", error);
         error
     })?;
 
-    return Ok(());
+    // the version changed since it was checked (or the coupon is not in the tenant), the transaction is rolled back on drop
+    if (result.rows_affected() == 0){
+        return Ok(false);
+    }
+
+    replace_rules(id, &coupon.rules, &mut transaction).await?;
+    record_audit(id, CouponAuditAction::Update, actor, before, &mut transaction).await?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(true);
 }
 
+/// Update only the columns present in the patch, the rules are replaced if they are present.
+/// The patch must have been validated against the current coupon (see `CouponPatchRequest::apply`).
+/// Like `update`, the `version` is incremented and checked against the `expected_version`.
+pub async fn patch_by_id(id: i32, tenant: &str, coupon: &CouponPatchRequest, expected_version: Option<i32>, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
 
-pub async fn get_all(pool: &MySqlPool) -> Result<Vec<Coupon>, sqlx::Error> {
-    let coupons = query_as!(Coupon,
-        r#"SELECT id
-        , code
-        , discount 
-        , max_usage_count
-        , active as `active: bool`
-        , expiration_date as `expiration_date: NaiveDateTime`
-        , date_created as `date_created: Option<NaiveDateTime>`
-        , date_updated as `date_updated: NaiveDateTime`
-        FROM coupon"#)
+    let before = coupon_audit_repository::get_snapshot(id, &mut transaction).await?;
+
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("UPDATE coupon SET ");
+    let mut columns = query_builder.separated(", ");
+    if let Some(campaign_id) = coupon.campaign_id {
+        columns.push("campaign_id = ").push_bind_unseparated(campaign_id);
+    }
+    if let Some(discount) = coupon.discount {
+        columns.push("discount = ").push_bind_unseparated(discount);
+    }
+    if let Some(discount_type) = coupon.discount_type {
+        columns.push("discount_type = ").push_bind_unseparated(discount_type.map(|discount_type| discount_type.as_str()));
+    }
+    if let Some(currency) = &coupon.currency {
+        columns.push("currency = ").push_bind_unseparated(currency.clone());
+    }
+    if let Some(min_order_amount) = coupon.min_order_amount {
+        columns.push("min_order_amount = ").push_bind_unseparated(min_order_amount);
+    }
+    if let Some(max_discount_amount) = coupon.max_discount_amount {
+        columns.push("max_discount_amount = ").push_bind_unseparated(max_discount_amount);
+    }
+    if let Some(active) = coupon.active {
+        columns.push("active = ").push_bind_unseparated(active);
+    }
+    if let Some(max_usage_count) = coupon.max_usage_count {
+        columns.push("max_usage_count = ").push_bind_unseparated(max_usage_count);
+    }
+    if let Some(max_usage_per_customer) = coupon.max_usage_per_customer {
+        columns.push("max_usage_per_customer = ").push_bind_unseparated(max_usage_per_customer);
+    }
+    if let Some(valid_from) = coupon.valid_from {
+        columns.push("valid_from = ").push_bind_unseparated(valid_from);
+    }
+    if let Some(expiration_date) = coupon.expiration_date {
+        columns.push("expiration_date = ").push_bind_unseparated(expiration_date);
+    }
+    if let Some(never_expires) = coupon.never_expires {
+        columns.push("never_expires = ").push_bind_unseparated(never_expires);
+    }
+    // a column always changes, so MySQL also sets `date_updated` when only the rules are patched
+    columns.push("version = version + 1");
+    query_builder.push(" WHERE id = ").push_bind(id);
+    query_builder.push(" AND tenant = ").push_bind(tenant);
+    if let Some(expected_version) = expected_version {
+        query_builder.push(" AND version = ").push_bind(expected_version);
+    }
+
+    let result = query_builder.build()
+        .execute(&mut transaction)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute update query: {:?}", error);
+            error
+        })?;
+
+    if (result.rows_affected() == 0){
+        return Ok(false);
+    }
+
+    if let Some(rules) = &coupon.rules {
+        replace_rules(id, rules.as_deref().unwrap_or_default(), &mut transaction).await?;
+    }
+    record_audit(id, CouponAuditAction::Patch, actor, before, &mut transaction).await?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(true);
+}
+
+// Record the change in the audit, `before` must have been taken at the start of the transaction
+async fn record_audit(coupon_id: i32, action: CouponAuditAction, actor: &CouponAuditActor, before: Option<String>, transaction: &mut Transaction<'_, MySql>) -> Result<(), sqlx::Error> {
+    let after = coupon_audit_repository::get_snapshot(coupon_id, transaction).await?;
+    return coupon_audit_repository::insert_audit(coupon_id, action, actor, before, after, transaction).await;
+}
+
+// the rules are replaced as a whole
+async fn replace_rules(coupon_id: i32, rules: &[CouponRule], transaction: &mut Transaction<'_, MySql>) -> Result<(), sqlx::Error> {
+    query!( 
+        r#"DELETE FROM coupon_rule
+            WHERE coupon_id = ?
+        "#, coupon_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute delete query: {:?}", error);
+        error
+    })?;
+
+    return insert_rules(coupon_id as u64, rules, transaction).await;
+}
+
+async fn insert_rules(coupon_id: u64, rules: &[CouponRule], transaction: &mut Transaction<'_, MySql>) -> Result<(), sqlx::Error> {
+    for rule in rules {
+        let rule = serde_json::to_string(rule)
+            .map_err(|error| sqlx::Error::Protocol(format!("Failed to serialize coupon rule: {}", error)))?;
+
+        query!(
+            r#"
+                INSERT INTO coupon_rule 
+                (coupon_id, rule) 
+                VALUES 
+                (?, ?)
+            "#,
+            coupon_id,
+            rule,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute insert query: {:?}", error);
+            error
+        })?;
+    }
+
+    return Ok(());
+}
+
+pub async fn get_rules_by_coupon_id(coupon_id: i32, tenant: &str, pool: &MySqlPool) -> Result<Vec<CouponRule>, sqlx::Error> {
+    let rows = query!(
+        r#"SELECT r.rule
+            FROM coupon_rule r
+            JOIN coupon c ON c.id = r.coupon_id
+            WHERE r.coupon_id = ? AND c.tenant = ?
+            ORDER BY r.id
+        "#, coupon_id, tenant
+    )
     .fetch_all(pool)
     .await
     .map_err(|error| {
@@ -71,7 +312,144 @@ pub async fn get_all(pool: &MySqlPool) -> Result<Vec<Coupon>, sqlx::Error> {
         error
     })?;
 
-   return Ok(coupons);
+    return rows.into_iter()
+        .map(|row| parse_rule(&row.rule))
+        .collect();
+}
+
+/// Get the rules of every coupon, as `(coupon_id, rule)` pairs.
+pub async fn get_rules_by_coupon_ids(coupon_ids: &[i32], tenant: &str, pool: &MySqlPool) -> Result<Vec<(i32, CouponRule)>, sqlx::Error> {
+    if (coupon_ids.is_empty()){
+        return Ok(Vec::new());
+    }
+
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new(
+        "SELECT r.coupon_id, r.rule FROM coupon_rule r JOIN coupon c ON c.id = r.coupon_id WHERE c.tenant = ");
+    query_builder.push_bind(tenant);
+    query_builder.push(" AND r.coupon_id IN (");
+    let mut separated = query_builder.separated(", ");
+    for coupon_id in coupon_ids {
+        separated.push_bind(*coupon_id);
+    }
+    separated.push_unseparated(") ORDER BY r.id");
+
+    let rows = query_builder.build()
+        .fetch_all(pool)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute select query: {:?}", error);
+            error
+        })?;
+
+    return rows.into_iter()
+        .map(|row| {
+            let rule: String = row.try_get("rule")?;
+            Ok((row.try_get("coupon_id")?, parse_rule(&rule)?))
+        })
+        .collect();
+}
+
+pub(super) fn parse_rule(rule: &str) -> Result<CouponRule, sqlx::Error> {
+    return serde_json::from_str(rule)
+        .map_err(|error| {
+            tracing::error!("Failed to parse coupon rule `{}`: {:?}", rule, error);
+            sqlx::Error::Decode(Box::new(error))
+        });
+}
+
+
+/// Get one page of the coupons matching the `filter`.
+/// The query is built at runtime since every filter is optional.
+pub async fn get_all(filter: &CouponFilter, tenant: &str, pool: &MySqlPool) -> Result<Vec<Coupon>, sqlx::Error> {
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new(
+        r#"SELECT id
+        , code
+        , campaign_id
+        , discount
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
+        , max_usage_count
+        , max_usage_per_customer
+        , active
+        , valid_from
+        , expiration_date
+        , never_expires
+        , date_created
+        , date_updated
+        , deleted_at
+        , version
+        FROM coupon"#);
+    push_filter(&mut query_builder, filter, tenant);
+
+    let order = if (filter.sort.descending) { "DESC" } else { "ASC" };
+    // `id` as tiebreaker keeps the order stable between pages
+    query_builder.push(format!(" ORDER BY {} {}, id {}", filter.sort.field.column(), order, order));
+    query_builder.push(" LIMIT ");
+    query_builder.push_bind(filter.page_size);
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(filter.offset());
+
+    let coupons = query_builder.build_query_as::<Coupon>()
+        .fetch_all(pool)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute select query: {:?}", error);
+            error
+        })?;
+
+    return Ok(coupons);
+}
+
+/// Count every coupon matching the `filter`, ignoring the pagination.
+pub async fn count_all(filter: &CouponFilter, tenant: &str, pool: &MySqlPool) -> Result<i64, sqlx::Error> {
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM coupon");
+    push_filter(&mut query_builder, filter, tenant);
+
+    let (count,): (i64,) = query_builder.build_query_as()
+        .fetch_one(pool)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute count query: {:?}", error);
+            error
+        })?;
+
+    return Ok(count);
+}
+
+fn push_filter(query_builder: &mut QueryBuilder<MySql>, filter: &CouponFilter, tenant: &str) {
+    query_builder.push(" WHERE tenant = ").push_bind(tenant.to_string());
+    if (filter.deleted){
+        query_builder.push(" AND deleted_at IS NOT NULL");
+    } else {
+        query_builder.push(" AND deleted_at IS NULL");
+    }
+    if let Some(active) = filter.active {
+        query_builder.push(" AND active = ").push_bind(active);
+    }
+    match filter.expired {
+        Some(true) => { query_builder.push(" AND never_expires = 0 AND expiration_date < NOW()"); },
+        Some(false) => { query_builder.push(" AND (never_expires = 1 OR expiration_date >= NOW())"); },
+        None => {},
+    }
+    if let Some(code_prefix) = &filter.code_prefix {
+        // escape the `LIKE` wildcards, the prefix is matched literally
+        let escaped = code_prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        query_builder.push(" AND code LIKE ").push_bind(format!("{}%", escaped));
+    }
+    if let Some(created_from) = filter.created_from {
+        query_builder.push(" AND date_created >= ").push_bind(created_from);
+    }
+    if let Some(created_to) = filter.created_to {
+        query_builder.push(" AND date_created <= ").push_bind(created_to);
+    }
+    if let Some(min_discount) = filter.min_discount {
+        query_builder.push(" AND discount >= ").push_bind(min_discount);
+    }
+    if let Some(max_discount) = filter.max_discount {
+        query_builder.push(" AND discount <= ").push_bind(max_discount);
+    }
 }
 
 pub enum Fields {
@@ -80,7 +458,7 @@ pub enum Fields {
     None,
 }
 
-pub async fn get_by_field(field: Fields, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
+pub async fn get_by_field(field: Fields, tenant: &str, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
     let field_name: &str;
     let field_value: String;
     match field {
@@ -100,14 +478,24 @@ pub async fn get_by_field(field: Fields, pool: &MySqlPool) -> Result<Option<Coup
     let coupon = query_as!(Coupon, 
         r#"SELECT id
         , code
+        , campaign_id
         , discount 
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
         , max_usage_count
+        , max_usage_per_customer
         , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
         , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
         , date_created as `date_created: Option<NaiveDateTime>`
         , date_updated as `date_updated: NaiveDateTime`
-        FROM coupon WHERE ? = ?
-        "#, field_name, field_value
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE ? = ? AND tenant = ? AND deleted_at IS NULL
+        "#, field_name, field_value, tenant
     )
     .fetch_optional(pool)
     .await
@@ -120,18 +508,28 @@ pub async fn get_by_field(field: Fields, pool: &MySqlPool) -> Result<Option<Coup
 
 }
 
-pub async fn get_by_id(id: i32, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
+pub async fn get_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
     let coupon = query_as!(Coupon, 
         r#"SELECT id
         , code
+        , campaign_id
         , discount 
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
         , max_usage_count
+        , max_usage_per_customer
         , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
         , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
         , date_created as `date_created: Option<NaiveDateTime>`
         , date_updated as `date_updated: NaiveDateTime`
-        FROM coupon WHERE id = ?
-        "#, id
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE id = ? AND tenant = ? AND deleted_at IS NULL
+        "#, id, tenant
     )
     .fetch_optional(pool)
     .await
@@ -143,18 +541,28 @@ pub async fn get_by_id(id: i32, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx
     return Ok(coupon);
 }
 
-pub async fn get_by_code(code: &String, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
+pub async fn get_by_code(code: &String, tenant: &str, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
     let coupon = query_as!(Coupon, 
         r#"SELECT id
         , code
+        , campaign_id
         , discount 
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
         , max_usage_count
+        , max_usage_per_customer
         , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
         , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
         , date_created as `date_created: Option<NaiveDateTime>`
         , date_updated as `date_updated: NaiveDateTime`
-        FROM coupon WHERE code = ?
-        "#, code
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE code = ? AND tenant = ? AND deleted_at IS NULL
+        "#, code, tenant
     )
     .fetch_optional(pool)
     .await
@@ -166,34 +574,315 @@ pub async fn get_by_code(code: &String, pool: &MySqlPool) -> Result<Option<Coupo
     return Ok(coupon);
 }
 
-pub async fn delete_by_id(id: i32, pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    query!( 
-        r#"DELETE FROM coupon
-            WHERE id = ?
-        "#, id
+pub async fn get_by_campaign_id(campaign_id: i32, tenant: &str, pool: &MySqlPool) -> Result<Vec<Coupon>, sqlx::Error> {
+    let coupons = query_as!(Coupon, 
+        r#"SELECT id
+        , code
+        , campaign_id
+        , discount 
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
+        , max_usage_count
+        , max_usage_per_customer
+        , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
+        , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
+        , date_created as `date_created: Option<NaiveDateTime>`
+        , date_updated as `date_updated: NaiveDateTime`
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE campaign_id = ? AND tenant = ? AND deleted_at IS NULL
+        "#, campaign_id, tenant
     )
-    .execute(pool)
+    .fetch_all(pool)
     .await
     .map_err(|error| {
-        tracing::error!("Failed to execute delete query: {:?}", error);
+        tracing::error!("Failed to execute select query: {:?}", error);
         error
     })?;
 
-    return Ok(());
+    return Ok(coupons);
 }
 
-pub async fn delete_by_code(code: &String, pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    query!( 
-        r#"DELETE FROM coupon
-            WHERE code = ?
-        "#, code
+/// Soft delete the coupon, its redemptions, rules and products are kept so it can be restored.
+/// With an `expected_version` nothing is deleted if the coupon has another version, returns whether it was deleted.
+pub async fn delete_by_id(id: i32, tenant: &str, expected_version: Option<i32>, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let before = coupon_audit_repository::get_snapshot(id, &mut transaction).await?;
+
+    let result = query!( 
+        r#"UPDATE coupon SET
+            deleted_at = NOW(),
+            version = version + 1
+            WHERE id = ? AND tenant = ? AND deleted_at IS NULL AND (? IS NULL OR version = ?)
+        "#, id, tenant, expected_version, expected_version
     )
-    .execute(pool)
+    .execute(&mut transaction)
     .await
     .map_err(|error| {
         tracing::error!("Failed to execute delete query: {:?}", error);
         error
     })?;
 
-    return Ok(());
-}
\ No newline at end of file
+    if (result.rows_affected() == 0){
+        return Ok(false);
+    }
+
+    record_audit(id, CouponAuditAction::Delete, actor, before, &mut transaction).await?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(true);
+}
+
+/// Get a soft deleted coupon, the other `get_*` functions only return the coupons that are not deleted.
+pub async fn get_deleted_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Option<Coupon>, sqlx::Error> {
+    let coupon = query_as!(Coupon, 
+        r#"SELECT id
+        , code
+        , campaign_id
+        , discount 
+        , discount_type
+        , currency
+        , min_order_amount
+        , max_discount_amount
+        , max_usage_count
+        , max_usage_per_customer
+        , active as `active: bool`
+        , valid_from as `valid_from: NaiveDateTime`
+        , expiration_date as `expiration_date: NaiveDateTime`
+        , never_expires as `never_expires: bool`
+        , date_created as `date_created: Option<NaiveDateTime>`
+        , date_updated as `date_updated: NaiveDateTime`
+        , deleted_at as `deleted_at: NaiveDateTime`
+        , version
+        FROM coupon WHERE id = ? AND tenant = ? AND deleted_at IS NOT NULL
+        "#, id, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(coupon);
+}
+
+/// Restore a soft deleted coupon, returns whether it was restored.
+pub async fn restore_by_id(id: i32, tenant: &str, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let before = coupon_audit_repository::get_snapshot(id, &mut transaction).await?;
+
+    let result = query!( 
+        r#"UPDATE coupon SET
+            deleted_at = NULL,
+            version = version + 1
+            WHERE id = ? AND tenant = ? AND deleted_at IS NOT NULL
+        "#, id, tenant
+    )
+    .execute(&mut transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute update query: {:?}", error);
+        error
+    })?;
+
+    if (result.rows_affected() == 0){
+        return Ok(false);
+    }
+
+    record_audit(id, CouponAuditAction::Restore, actor, before, &mut transaction).await?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(true);
+}
+
+pub async fn get_redemption_count(coupon_id: i32, tenant: &str, pool: &MySqlPool) -> Result<i64, sqlx::Error> {
+    let result = query!( 
+        r#"SELECT COUNT(*) as redemption_count
+            FROM coupon_redemption r
+            JOIN coupon c ON c.id = r.coupon_id
+            WHERE r.coupon_id = ? AND c.tenant = ?
+        "#, coupon_id, tenant
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.redemption_count);
+}
+
+pub async fn get_customer_redemption_count(coupon_id: i32, customer_id: &String, tenant: &str, pool: &MySqlPool) -> Result<i64, sqlx::Error> {
+    let result = query!( 
+        r#"SELECT COUNT(*) as redemption_count
+            FROM coupon_redemption r
+            JOIN coupon c ON c.id = r.coupon_id
+            WHERE r.coupon_id = ? AND r.customer_id = ? AND c.tenant = ?
+        "#, coupon_id, customer_id, tenant
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(result.redemption_count);
+}
+
+pub async fn get_redemption_by_id(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Option<CouponRedemption>, sqlx::Error> {
+    let redemption = query_as!(CouponRedemption, 
+        r#"SELECT r.id
+        , r.coupon_id
+        , r.customer_id
+        , r.discount_amount
+        , r.currency
+        , r.date_created as `date_created: NaiveDateTime`
+        FROM coupon_redemption r
+        JOIN coupon c ON c.id = r.coupon_id
+        WHERE r.id = ? AND c.tenant = ?
+        "#, id, tenant
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    return Ok(redemption);
+}
+
+/// Insert a redemption for the coupon if it has not reached its `max_usage_count`
+/// (nor its `max_usage_per_customer` for the given customer) yet.
+/// 
+/// The coupon row is locked (`FOR UPDATE`) for the whole transaction, so concurrent
+/// redemptions of the same coupon are serialized and can not both take the last use.
+/// Returns the id of the inserted redemption, or `None` if a usage limit was already reached
+/// or the coupon was deleted meanwhile.
+pub async fn insert_redemption(coupon_id: i32, customer_id: Option<&String>, discount: Option<(i32, &str)>, tenant: &str, pool: &MySqlPool) -> Result<Option<u64>, sqlx::Error> {
+    let mut transaction = pool.begin().await
+        .map_err(|error| {
+            tracing::error!("Failed to begin transaction: {:?}", error);
+            error
+        })?;
+
+    let coupon = query!(
+        r#"SELECT max_usage_count
+            , max_usage_per_customer
+            FROM coupon
+            WHERE id = ? AND tenant = ? AND deleted_at IS NULL
+            FOR UPDATE
+        "#, coupon_id, tenant
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute select query: {:?}", error);
+        error
+    })?;
+
+    let Some(coupon) = coupon else {
+        transaction.rollback().await?;
+        return Ok(None);
+    };
+
+    if let Some(max_usage_count) = coupon.max_usage_count {
+        let result = query!( 
+            r#"SELECT COUNT(*) as redemption_count
+                FROM coupon_redemption
+                WHERE coupon_id = ?
+            "#, coupon_id
+        )
+        .fetch_one(&mut transaction)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute select query: {:?}", error);
+            error
+        })?;
+
+        if (result.redemption_count >= i64::from(max_usage_count)){
+            transaction.rollback().await?;
+            return Ok(None);
+        }
+    }
+
+    if let Some(max_usage_per_customer) = coupon.max_usage_per_customer {
+        // the customer must be identified so its usage can be counted
+        if (customer_id.is_none()){
+            transaction.rollback().await?;
+            return Ok(None);
+        }
+
+        let result = query!( 
+            r#"SELECT COUNT(*) as redemption_count
+                FROM coupon_redemption
+                WHERE coupon_id = ? AND customer_id = ?
+            "#, coupon_id, customer_id
+        )
+        .fetch_one(&mut transaction)
+        .await
+        .map_err(|error| {
+            tracing::error!("Failed to execute select query: {:?}", error);
+            error
+        })?;
+
+        if (result.redemption_count >= i64::from(max_usage_per_customer)){
+            transaction.rollback().await?;
+            return Ok(None);
+        }
+    }
+
+    let result = query!(
+        r#"
+            INSERT INTO coupon_redemption 
+            (coupon_id, customer_id, discount_amount, currency, date_created) 
+            VALUES 
+            (?, ?, ?, ?, NOW())
+        "#,
+        coupon_id,
+        customer_id,
+        discount.map(|(discount_amount, _)| discount_amount),
+        discount.map(|(_, currency)| currency),
+    )
+    .execute(&mut transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute insert query: {:?}", error);
+        error
+    })?;
+
+    transaction.commit().await
+        .map_err(|error| {
+            tracing::error!("Failed to commit transaction: {:?}", error);
+            error
+        })?;
+
+    return Ok(Some(result.last_insert_id()));
+}
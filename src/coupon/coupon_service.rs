@@ -1,15 +1,32 @@
 use super::model::{
-    CouponInsertRequest, CouponResponse, CouponError, CouponInsert, CouponUpdateRequest,
-    CouponUpdate,
+    Coupon, CouponInsertRequest, CouponResponse, CouponError, CouponInsert, CouponUpdateRequest,
+    CouponPatchRequest, check_if_match, precondition_failed,
+    CouponAuditActor, CouponAuditResponse,
+    CouponUpdate, CouponRedemption, CouponVerification, InvalidReason, CouponDiscount,
+    CouponQuoteRequest, CouponQuote, OrderAmount, VerifyQuery, CustomerQuery,
+    CouponBatch, CouponBatchRequest, CouponBatchResponse,
+    CouponListQuery, CouponFilter, CouponPage, Pagination, FieldError,
 };
-use super::{coupon_repository};
-use chrono::{Utc, Datelike};
+use super::{coupon_cache::CouponCache, coupon_repository, coupon_audit_repository, coupon_product_repository, coupon_campaign_repository, coupon_campaign_service};
+use actix_web::http::header::IfMatch;
+use chrono::{Utc};
 use sqlx::{MySqlPool};
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow};
 use std::convert::TryFrom;
 
-pub async fn get_all(pool: &MySqlPool) -> Result<Vec<CouponResponse>, CouponError> {
-    let coupons = coupon_repository::get_all(pool).await
+pub async fn get_all(query: CouponListQuery, tenant: &str, base_url: &str, pool: &MySqlPool) -> Result<CouponPage, CouponError> {
+    let filter = CouponFilter::try_from(query.clone())
+        .map_err(CouponError::FieldValidationError)?;
+
+    let coupons = coupon_repository::get_all(&filter, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    let total_count = coupon_repository::count_all(&filter, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    // only load the rules of the coupons in the page
+    let coupon_ids: Vec<i32> = coupons.iter().map(|coupon| coupon.id).collect();
+    let rules = coupon_repository::get_rules_by_coupon_ids(&coupon_ids, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
 
     let coupons_response = coupons
@@ -17,8 +34,14 @@ pub async fn get_all(pool: &MySqlPool) -> Result<Vec<CouponResponse>, CouponErro
         // flat_map uses an iterator over the result of the mapping and as a consequence,
         // it will skip over elements for which the mapping closure returns empty or unsuccessful values
         .flat_map(|coupon| {
-            match coupon.try_into() {
-                Ok(coupon) => Some(coupon),
+            match CouponResponse::try_from(coupon) {
+                Ok(mut coupon) => {
+                    coupon.rules = rules.iter()
+                        .filter(|(coupon_id, _)| *coupon_id == coupon.id)
+                        .map(|(_, rule)| rule.clone())
+                        .collect();
+                    Some(coupon)
+                },
                 Err(coupon) => {
                     tracing::error!("Failed to try_into() {:?}", coupon);
                     None
@@ -26,146 +49,473 @@ pub async fn get_all(pool: &MySqlPool) -> Result<Vec<CouponResponse>, CouponErro
             }
         })
         .collect();
-    return Ok(coupons_response);
+
+    return Ok(CouponPage {
+        data: coupons_response,
+        pagination: Pagination::new(&query, &filter, total_count, base_url),
+    });
 }
 
-pub async fn get_by_id(id: i32, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
-    let result = coupon_repository::get_by_id(id, pool).await
+pub async fn get_by_id(id: i32, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    if let Some(coupon) = cache.get_by_id(tenant, id).await {
+        return Ok(coupon);
+    }
+
+    let result = coupon_repository::get_by_id(id, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
 
     let coupon = result.ok_or( CouponError::NotFoundError(anyhow!(format!("Coupon with id `{}` not found.", id))))?;
 
-    let coupon_response = coupon.try_into()
-        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponResponse: {}.", e))))?;
-    return Ok(coupon_response);
+    let coupon = to_coupon_response(coupon, tenant, pool).await?;
+    cache.set(tenant, &coupon).await;
+    return Ok(coupon);
 }
 
-pub async fn get_by_code(code: String, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
-    let result = coupon_repository::get_by_code(&code, pool).await
+pub async fn get_by_code(code: String, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    if let Some(coupon) = cache.get_by_code(tenant, &code).await {
+        return Ok(coupon);
+    }
+
+    let result = coupon_repository::get_by_code(&code, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
 
     let coupon = result.ok_or(CouponError::NotFoundError(anyhow!(format!("Coupon with code `{}` not found.", code))))?;
 
-    let coupon_response = coupon.try_into()
+    let coupon = to_coupon_response(coupon, tenant, pool).await?;
+    cache.set(tenant, &coupon).await;
+    return Ok(coupon);
+}
+
+// Convert a Coupon to a CouponResponse, loading the coupon rules
+async fn to_coupon_response(coupon: Coupon, tenant: &str, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    let rules = coupon_repository::get_rules_by_coupon_id(coupon.id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    let mut coupon_response: CouponResponse = coupon.try_into()
         .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponResponse: {}.", e))))?;
+    coupon_response.rules = rules;
     return Ok(coupon_response);
 }
 
-pub async fn get_by_id_or_code(param: String, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+/// Get the coupon of the tenant, the coupons of the other tenants are not found.
+pub async fn get_by_id_or_code(param: String, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
     // if the `id` param is present and it is an integer, then we get by id, otherwise by code
     match param.parse::<i32>() {
-        Ok(id) => return get_by_id(id, pool).await,
+        Ok(id) => return get_by_id(id, tenant, cache, pool).await,
         Err(_) => {},
     }
 
-    return get_by_code(param, pool).await;
+    return get_by_code(param, tenant, cache, pool).await;
 }
 
-pub async fn insert(coupon_request: CouponInsertRequest, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
-    // check if Coupon already exists
-    if let Some(_) = get_by_code(coupon_request.code.clone(), pool).await.ok() {
+pub async fn insert(coupon_request: CouponInsertRequest, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    // check if Coupon already exists in the tenant
+    if let Some(_) = get_by_code(coupon_request.code.clone(), tenant, cache, pool).await.ok() {
         return Err(CouponError::AlreadyExistsError(anyhow!(format!("Coupon with code `{}` already exists.", coupon_request.code))));
     }
     
     let coupon_insert: CouponInsert = coupon_request.try_into()
-        .map_err(|e: String| CouponError::ValidationError(e))?;
+        .map_err(|e: FieldError| CouponError::FieldValidationError(e))?;
 
-        let inserted_id = coupon_repository::insert(coupon_insert, pool).await
-        .map_err(|e| CouponError::InternalError(anyhow!(format!("Something went wrong and the coupon was not inserted: {}", e))))?;
+    coupon_campaign_service::check_campaign_exists(coupon_insert.campaign_id, tenant, pool).await?;
+
+    let code = coupon_insert.code.clone();
+    let inserted_id = coupon_repository::insert(coupon_insert, tenant, actor, pool).await
+        .map_err(|e| match coupon_repository::is_duplicate_entry(&e) {
+            // the code of a deleted coupon can not be reused
+            true => CouponError::AlreadyExistsError(anyhow!(format!("Coupon with code `{}` already exists.", code))),
+            false => CouponError::InternalError(anyhow!(format!("Something went wrong and the coupon was not inserted: {}", e))),
+        })?;
 
     let inserted_id = i32::try_from(inserted_id)
         .or_else(|e| Err(CouponError::InternalError(anyhow!(format!("Failed to read inserted_id: {}", e)))))?;
 
-    let inserted_coupon = coupon_repository::get_by_id(inserted_id, pool).await
+    let inserted_coupon = coupon_repository::get_by_id(inserted_id, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
 
     let coupon = inserted_coupon.ok_or(CouponError::NotFoundError(anyhow!(format!("Inserted coupon with id `{}` not found.", inserted_id))))?;
 
-    let coupon_response = coupon.try_into()
-        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponResponse: {}.", e))))?;
-    return Ok(coupon_response);
+    return to_coupon_response(coupon, tenant, pool).await;
+}
+
+// Attempts to generate a batch of codes that do not collide with existing coupons
+const MAX_BATCH_ATTEMPTS: u32 = 3;
+
+/// Generate and insert a batch of single-use coupons sharing the same discount configuration.
+pub async fn insert_batch(batch_request: CouponBatchRequest, tenant: &str, actor: &CouponAuditActor, pool: &MySqlPool) -> Result<CouponBatchResponse, CouponError> {
+    let campaign_id = batch_request.campaign_id;
+    let batch: CouponBatch = batch_request.try_into()
+        .map_err(CouponError::FieldValidationError)?;
+
+    coupon_campaign_service::check_campaign_exists(campaign_id, tenant, pool).await?;
+
+    for attempt in 1..=MAX_BATCH_ATTEMPTS {
+        let codes = batch.generate_codes();
+        let coupons = batch.to_coupon_inserts(&codes)
+            .map_err(CouponError::FieldValidationError)?;
+
+        match coupon_repository::insert_all(coupons, tenant, actor, pool).await {
+            Ok(_) => return Ok(CouponBatchResponse { count: codes.len(), codes }),
+            // a generated code already exists, the transaction was rolled back so we try again with new codes
+            Err(error) if coupon_repository::is_duplicate_entry(&error) => {
+                tracing::warn!("Generated coupon code already exists (attempt {} of {}).", attempt, MAX_BATCH_ATTEMPTS);
+            },
+            Err(error) => return Err(CouponError::InternalError(anyhow!(format!("Something went wrong and the coupons were not inserted: {}", error)))),
+        }
+    }
+
+    return Err(CouponError::InternalError(anyhow!(format!("Failed to generate unique coupon codes after {} attempts.", MAX_BATCH_ATTEMPTS))));
 }
 
-pub async fn update(param: String, coupon_request: CouponUpdateRequest, pool: &MySqlPool) -> Result<(), CouponError> {
+/// Replace the coupon, with an `If-Match` header it is only replaced if the coupon was not modified meanwhile.
+pub async fn update(param: String, coupon_request: CouponUpdateRequest, if_match: Option<&IfMatch>, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
     // check if coupon exists
-    let coupon = get_by_id_or_code(param, pool).await?;
+    let coupon = get_by_id_or_code(param, tenant, cache, pool).await?;
+    let expected_version = check_if_match(if_match, coupon.version)?;
 
-    let coupon_update: CouponUpdate = coupon_request.try_into().map_err(|e: String| CouponError::ValidationError(e))?;
+    let coupon_update: CouponUpdate = coupon_request.try_into().map_err(|e: FieldError| CouponError::FieldValidationError(e))?;
 
-    coupon_repository::update(coupon.id, coupon_update, &pool).await
+    coupon_campaign_service::check_campaign_exists(coupon_update.campaign_id, tenant, pool).await?;
+
+    let updated = coupon_repository::update(coupon.id, tenant, coupon_update, expected_version, actor, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
 
+    cache.invalidate(tenant, coupon.id, &coupon.code).await;
+    if (!updated){
+        return Err(precondition_failed_after_read(coupon.id, tenant, pool).await);
+    }
     return Ok(());
 }
 
-pub async fn delete(param: String, pool: &MySqlPool) -> Result<(), CouponError> {
+/// Partially update the coupon, see `CouponPatchRequest`. Returns the updated coupon.
+pub async fn patch(param: String, coupon_patch: CouponPatchRequest, if_match: Option<&IfMatch>, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    // check if coupon exists
+    let coupon = get_by_id_or_code(param, tenant, cache, pool).await?;
+    let expected_version = check_if_match(if_match, coupon.version)?;
+    let (id, code) = (coupon.id, coupon.code.clone());
+
+    // the patched coupon has the same validation as a full update
+    coupon_patch.apply(coupon)
+        .and_then(CouponUpdate::try_from)
+        .map_err(CouponError::FieldValidationError)?;
+
+    if let Some(campaign_id) = coupon_patch.campaign_id {
+        coupon_campaign_service::check_campaign_exists(campaign_id, tenant, pool).await?;
+    }
+
+    if (!coupon_patch.is_empty()){
+        let updated = coupon_repository::patch_by_id(id, tenant, &coupon_patch, expected_version, actor, pool).await
+            .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+        cache.invalidate(tenant, id, &code).await;
+        if (!updated){
+            return Err(precondition_failed_after_read(id, tenant, pool).await);
+        }
+    }
+
+    return get_by_id(id, tenant, cache, pool).await;
+}
+
+pub async fn delete(param: String, if_match: Option<&IfMatch>, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
     match param.parse::<i32>() {
-        Ok(id) => return delete_by_id(id, pool).await,
+        Ok(id) => return delete_by_id(id, if_match, tenant, actor, cache, pool).await,
         Err(_) => {},
     }
 
-    return delete_by_code(param, pool).await;
+    return delete_by_code(param, if_match, tenant, actor, cache, pool).await;
 }
 
-pub async fn delete_by_id(id: i32, pool: &MySqlPool) -> Result<(), CouponError> {
-    coupon_repository::get_by_id(id, pool).await
+pub async fn delete_by_id(id: i32, if_match: Option<&IfMatch>, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
+    let coupon = coupon_repository::get_by_id(id, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?
         .ok_or(CouponError::NotFoundError(anyhow!(format!("Coupon with id `{}` not found.", id))))?;
+    let expected_version = check_if_match(if_match, coupon.version)?;
 
-    coupon_repository::delete_by_id(id, pool).await
-        .context("Failed to delete by id")?;
-
-    coupon_repository::delete_by_id(id, &pool).await
+    let deleted = coupon_repository::delete_by_id(id, tenant, expected_version, actor, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    cache.invalidate(tenant, coupon.id, &coupon.code).await;
+    if (!deleted && expected_version.is_some()){
+        return Err(precondition_failed_after_read(coupon.id, tenant, pool).await);
+    }
     return Ok(());
 }
 
-pub async fn delete_by_code(code: String, pool: &MySqlPool) -> Result<(), CouponError> {
-    coupon_repository::get_by_code(&code, pool).await
+pub async fn delete_by_code(code: String, if_match: Option<&IfMatch>, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<(), CouponError> {
+    let coupon = coupon_repository::get_by_code(&code, tenant, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?
         .ok_or(CouponError::NotFoundError(anyhow!(format!("Coupon with code `{}` not found.", &code))))?;
+    let expected_version = check_if_match(if_match, coupon.version)?;
 
-    coupon_repository::delete_by_code(&code, pool).await
-        .context("Failed to delete by code")?;
-            
-    coupon_repository::delete_by_code(&code, &pool).await
+    let deleted = coupon_repository::delete_by_id(coupon.id, tenant, expected_version, actor, pool).await
         .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    cache.invalidate(tenant, coupon.id, &coupon.code).await;
+    if (!deleted && expected_version.is_some()){
+        return Err(precondition_failed_after_read(coupon.id, tenant, pool).await);
+    }
     return Ok(());
 }
 
-/// Verify if the coupon is valid for use, return a boolean.
-pub async fn is_valid(param: String, pool: &MySqlPool) -> Result<bool, CouponError> {
-    let coupon = get_by_id_or_code(param, pool).await?;
+/// Restore a deleted coupon, with its rules, products and redemptions.
+pub async fn restore(id: i32, tenant: &str, actor: &CouponAuditActor, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponResponse, CouponError> {
+    let coupon = coupon_repository::get_deleted_by_id(id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?
+        .ok_or(CouponError::NotFoundError(anyhow!(format!("Deleted coupon with id `{}` not found.", id))))?;
+
+    coupon_repository::restore_by_id(coupon.id, tenant, actor, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    cache.invalidate(tenant, coupon.id, &coupon.code).await;
+    return get_by_id(coupon.id, tenant, cache, pool).await;
+}
+
+/// Get the audit history of the coupon, including the changes of a deleted coupon.
+pub async fn get_history(id: i32, tenant: &str, pool: &MySqlPool) -> Result<Vec<CouponAuditResponse>, CouponError> {
+    let coupon = coupon_repository::get_by_id(id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    let deleted_coupon = coupon_repository::get_deleted_by_id(id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (coupon.is_none() && deleted_coupon.is_none()){
+        return Err(CouponError::NotFoundError(anyhow!(format!("Coupon with id `{}` not found.", id))));
+    }
+
+    // the coupons created before the audit was introduced have no `insert` entry
+    let audits = coupon_audit_repository::get_audits_by_coupon_id(id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+
+    return audits.into_iter()
+        .map(CouponAuditResponse::try_from)
+        .collect::<Result<Vec<CouponAuditResponse>, String>>()
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponAuditResponse: {}.", e))));
+}
+
+// The coupon was modified (or deleted) between the `If-Match` check and the write
+async fn precondition_failed_after_read(id: i32, tenant: &str, pool: &MySqlPool) -> CouponError {
+    return match coupon_repository::get_by_id(id, tenant, pool).await {
+        Ok(Some(coupon)) => precondition_failed(coupon.version),
+        Ok(None) => CouponError::NotFoundError(anyhow!(format!("Coupon with id `{}` not found.", id))),
+        Err(error) => CouponError::UnexpectedError(error.into()),
+    };
+}
+
+/// Verify if the coupon is valid for use, returning the reasons when it is not.
+/// When the coupon has a `max_usage_per_customer`, the `customer_id` is required.
+/// When the coupon has a `min_order_amount`, the order `amount` and `currency` are required.
+/// When the coupon is restricted to products, the `product` being purchased is required.
+pub async fn is_valid(param: String, query: VerifyQuery, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponVerification, CouponError> {
+    let order = parse_order(&query)?;
+
+    let coupon = get_by_id_or_code(param, tenant, cache, pool).await?;
+    let reasons = check_validity(&coupon, tenant, query.customer_id.as_ref(), query.product.as_ref(), order.as_ref(), pool).await?;
+    return Ok(reasons.into());
+}
+
+// The order of the query, `None` when neither the `amount` nor the `currency` are sent
+fn parse_order(query: &VerifyQuery) -> Result<Option<OrderAmount>, CouponError> {
+    return match (query.amount, query.currency.clone()) {
+        (Some(amount), Some(currency)) => Ok(Some(OrderAmount::parse(amount, currency).map_err(CouponError::ValidationError)?)),
+        (None, None) => Ok(None),
+        _ => Err(CouponError::ValidationError("Both `amount` and `currency` are required to check an order.".to_string())),
+    };
+}
+
+/// Run every validity check on the coupon, returning all the reasons it is not valid for use.
+async fn check_validity(
+    coupon: &CouponResponse,
+    tenant: &str,
+    customer_id: Option<&String>,
+    product: Option<&String>,
+    order: Option<&OrderAmount>,
+    pool: &MySqlPool,
+) -> Result<Vec<InvalidReason>, CouponError> {
+    let mut reasons = Vec::new();
 
     // Check if coupon is active
-    if (coupon.active == false){
-        println!("Coupon is not active.");
-        return Ok(false);
+    if (!coupon.active){
+        reasons.push(InvalidReason::Inactive);
+    }
+
+    let now = Utc::now().naive_utc();
+
+    // Check if coupon validity period has started
+    if let Some(valid_from) = coupon.valid_from {
+        if (valid_from > now){
+            reasons.push(InvalidReason::NotYetValid);
+        }
     }
 
     // Check if coupon is expired
     match (coupon.expiration_date) {
         Some(expiration) => {
-            if (expiration < Utc::now().naive_utc()){
-                println!("Coupon is expired.");
-                return Ok(false);
+            if (expiration < now){
+                reasons.push(InvalidReason::Expired);
             }
         },
         None => {
-            println!("Coupon doesn't have an expiration date.");
-            return Ok(false);
-        }
+            if (!coupon.never_expires){
+                reasons.push(InvalidReason::NoExpirationDate);
+            }
+        },
     };
 
-    // This is a special coupon that can only be used on Friday
-    if (coupon.code.to_uppercase() == "SEXTOU"){
-        // Verify if today is Friday
-        let weekday = Utc::now().date_naive().weekday().to_string();
-        if (weekday.to_uppercase() != "FRIDAY"){
-            println!("Today is not friday.");
-            return Ok(false);
+    // Check if the campaign of the coupon is active and running
+    if let Some(campaign_id) = coupon.campaign_id {
+        let campaign = coupon_campaign_repository::get_campaign_by_id(campaign_id, tenant, pool).await
+            .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+        if let Some(campaign) = campaign {
+            reasons.extend(campaign.check(now));
+        }
+    }
+
+    // Check the coupon rules (weekdays, hour windows, date ranges...)
+    for rule in &coupon.rules {
+        if let Err(reason) = rule.check(now) {
+            if (!reasons.contains(&reason)){
+                reasons.push(reason);
+            }
+        }
+    }
+
+    // Check if coupon has reached its maximum usage count
+    if let Some(max_usage_count) = coupon.max_usage_count {
+        let redemption_count = coupon_repository::get_redemption_count(coupon.id, tenant, pool).await
+            .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+        if (redemption_count >= i64::from(max_usage_count)){
+            reasons.push(InvalidReason::UsageLimitReached);
+        }
+    }
+
+    // Check if the customer has reached its maximum usage count for the coupon
+    if let Some(max_usage_per_customer) = coupon.max_usage_per_customer {
+        match customer_id {
+            Some(customer_id) => {
+                let redemption_count = coupon_repository::get_customer_redemption_count(coupon.id, customer_id, tenant, pool).await
+                    .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+                if (redemption_count >= i64::from(max_usage_per_customer)){
+                    reasons.push(InvalidReason::CustomerUsageLimitReached);
+                }
+            },
+            None => reasons.push(InvalidReason::CustomerRequired),
+        }
+    }
+
+    // Check if the coupon applies to the product being purchased
+    let products = coupon_product_repository::get_products_by_coupon_id(coupon.id, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?;
+    if (!products.is_empty()){
+        match product {
+            Some(product) => {
+                if (!products.iter().any(|coupon_product| &coupon_product.code == product)){
+                    reasons.push(InvalidReason::ProductNotEligible);
+                }
+            },
+            None => reasons.push(InvalidReason::ProductRequired),
+        }
+    }
+
+    // Check if the order reaches the minimum order amount
+    if (coupon.min_order_amount.is_some()){
+        match order {
+            Some(order) => {
+                let meets_min_order_amount = parse_discount(coupon)?
+                    .meets_min_order_amount(order.amount(), order.currency())
+                    .map_err(CouponError::ValidationError)?;
+                if (!meets_min_order_amount){
+                    reasons.push(InvalidReason::BelowMinOrderAmount);
+                }
+            },
+            None => reasons.push(InvalidReason::OrderAmountRequired),
         }
     }
 
-    return Ok(true);
+    return Ok(reasons);
+}
+
+/// Redeem one use of the coupon by the customer, failing if it is not valid for use.
+/// The same query as `is_valid`, so the coupons with a `min_order_amount` require the order.
+pub async fn redeem(param: String, query: VerifyQuery, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponRedemption, CouponError> {
+    let order = parse_order(&query)?;
+
+    let coupon = get_by_id_or_code(param, tenant, cache, pool).await?;
+    let customer_id = query.customer_id;
+
+    let reasons = check_validity(&coupon, tenant, customer_id.as_ref(), query.product.as_ref(), order.as_ref(), pool).await?;
+    if (!reasons.is_empty()){
+        return Err(invalid_coupon_error(&coupon, &reasons));
+    }
+
+    // the discount of the order is recorded for the spent amount of the campaign budget
+    let discount_amount = match order.as_ref() {
+        Some(order) => {
+            let discount_amount = parse_discount(&coupon)?
+                .apply(order.amount(), order.currency())
+                .map_err(CouponError::ValidationError)?;
+            let discount_amount = order.to_minor_units(discount_amount)
+                .ok_or(CouponError::ValidationError("Discount amount is too large.".to_string()))?;
+            Some((discount_amount, order.currency().code()))
+        },
+        None => None,
+    };
+
+    // the usage counts are checked again inside the insert transaction, since another
+    // redemption may have taken the last use after `check_validity`
+    let inserted_id = match coupon_repository::insert_redemption(coupon.id, customer_id.as_ref(), discount_amount, tenant, pool).await {
+        Ok(Some(inserted_id)) => inserted_id,
+        Ok(None) => return Err(redemption_refused(&coupon, tenant, pool).await),
+        Err(error) => return Err(CouponError::UnexpectedError(error.into())),
+    };
+
+    let inserted_id = i32::try_from(inserted_id)
+        .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to read inserted_id: {}", e))))?;
+
+    let redemption = coupon_repository::get_redemption_by_id(inserted_id, tenant, pool).await
+        .map_err(|error| CouponError::UnexpectedError(error.into()))?
+        .ok_or(CouponError::NotFoundError(anyhow!(format!("Inserted redemption with id `{}` not found.", inserted_id))))?;
+
+    return Ok(redemption);
+}
+
+// The redemption was not inserted, either the coupon was deleted or it reached its usage limit meanwhile
+async fn redemption_refused(coupon: &CouponResponse, tenant: &str, pool: &MySqlPool) -> CouponError {
+    return match coupon_repository::get_by_id(coupon.id, tenant, pool).await {
+        Ok(Some(_)) => CouponError::InvalidCouponError(anyhow!(format!("Coupon with code `{}` has reached its usage limit.", coupon.code))),
+        Ok(None) => CouponError::NotFoundError(anyhow!(format!("Coupon with code `{}` not found.", coupon.code))),
+        Err(error) => CouponError::UnexpectedError(error.into()),
+    };
+}
+
+/// Apply the coupon to an order amount, failing if the coupon is not valid for use.
+pub async fn quote(param: String, quote_request: CouponQuoteRequest, query: CustomerQuery, tenant: &str, cache: &CouponCache, pool: &MySqlPool) -> Result<CouponQuote, CouponError> {
+    let order = OrderAmount::parse(quote_request.amount, quote_request.currency)
+        .map_err(CouponError::ValidationError)?;
+
+    let coupon = get_by_id_or_code(param, tenant, cache, pool).await?;
+
+    let reasons = check_validity(&coupon, tenant, query.customer_id.as_ref(), query.product.as_ref(), Some(&order), pool).await?;
+    if (!reasons.is_empty()){
+        return Err(invalid_coupon_error(&coupon, &reasons));
+    }
+
+    let discount_amount = parse_discount(&coupon)?
+        .apply(order.amount(), order.currency())
+        .map_err(CouponError::ValidationError)?;
+
+    return Ok(CouponQuote::new(&order, discount_amount));
+}
+
+fn parse_discount(coupon: &CouponResponse) -> Result<CouponDiscount, CouponError> {
+    return CouponDiscount::parse(
+        coupon.discount,
+        coupon.discount_type,
+        coupon.currency.clone(),
+        coupon.min_order_amount,
+        coupon.max_discount_amount,
+    )
+    .map_err(|e| CouponError::InternalError(anyhow!(format!("Failed to parse CouponDiscount: {}.", e))));
+}
+
+fn invalid_coupon_error(coupon: &CouponResponse, reasons: &[InvalidReason]) -> CouponError {
+    let reasons: Vec<&str> = reasons.iter().map(|reason| reason.as_str()).collect();
+    return CouponError::InvalidCouponError(anyhow!(format!("Coupon with code `{}` is not valid for use: {}.", coupon.code, reasons.join(", "))));
 }
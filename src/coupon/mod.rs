@@ -1,11 +1,27 @@
+pub mod coupon_cache;
 pub mod coupon_controller;
+pub mod coupon_campaign_controller;
+pub mod coupon_product_controller;
 pub mod coupon_service;
+pub mod coupon_campaign_service;
+pub mod coupon_product_service;
 pub mod coupon_repository;
+pub mod coupon_audit_repository;
+pub mod coupon_campaign_repository;
+pub mod coupon_product_repository;
 pub mod health_check;
 pub mod model;
 
+pub use coupon_cache::*;
 pub use coupon_controller::*;
+pub use coupon_campaign_controller::*;
+pub use coupon_product_controller::*;
 pub use coupon_service::*;
+pub use coupon_campaign_service::*;
+pub use coupon_product_service::*;
 pub use coupon_repository::*;
+pub use coupon_audit_repository::*;
+pub use coupon_campaign_repository::*;
+pub use coupon_product_repository::*;
 pub use health_check::*;
 pub use model::*;
@@ -0,0 +1,193 @@
+use super::{CouponResponse, FieldError, InvalidReason};
+use super::coupon_discount::validate_currency;
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+/// A group of coupons (e.g. a marketing campaign), deactivating it invalidates all of its coupons.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Campaign {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDateTime>,
+    pub end_date: Option<NaiveDateTime>,
+    pub budget: Option<i32>, // in the minor unit of the `currency`
+    pub currency: Option<String>,
+    pub active: bool,
+    pub date_created: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CampaignInsert {
+    pub name: String,
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDateTime>,
+    pub end_date: Option<NaiveDateTime>,
+    pub budget: Option<i32>,
+    pub currency: Option<String>,
+    pub active: bool,
+}
+
+// Used to both create and update (PUT) a campaign
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CampaignRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDateTime>,
+    pub end_date: Option<NaiveDateTime>,
+    pub budget: Option<i32>,
+    pub currency: Option<String>,
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CampaignStats {
+    pub coupon_count: i64,
+    pub redeemed_coupon_count: i64,
+    pub redemption_count: i64,
+    pub customer_count: i64,
+    // sum of the discounts of the redemptions in the campaign `currency`, in its minor unit
+    pub spent_amount: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CampaignResponse {
+    #[serde(flatten)]
+    pub campaign: Campaign,
+    pub stats: CampaignStats,
+    // `budget` minus the `spent_amount`, negative when the budget was exceeded
+    pub remaining_budget: Option<i64>,
+    pub coupons: Vec<CouponResponse>,
+}
+
+impl TryFrom<CampaignRequest> for CampaignInsert {
+    type Error = FieldError;
+    fn try_from(campaign: CampaignRequest) -> Result<Self, Self::Error> {
+        let name = campaign.name.trim().to_string();
+        if (name.is_empty() || name.len() > 255){
+            return Err(FieldError::new("name", "Campaign name must have between 1 and 255 characters."));
+        }
+        // the id is used as the path param
+        if (name.parse::<i32>().is_ok()){
+            return Err(FieldError::new("name", format!("Campaign name `{}` cannot be a number.", name)));
+        }
+        if let (Some(start_date), Some(end_date)) = (campaign.start_date, campaign.end_date) {
+            if (start_date >= end_date){
+                return Err(FieldError::new("start_date", "Campaign start date must be before the end date."));
+            }
+        }
+        match (campaign.budget, campaign.currency.as_ref()) {
+            (Some(budget), Some(currency)) => {
+                if (budget <= 0){
+                    return Err(FieldError::new("budget", "Campaign budget must be higher than 0."));
+                }
+                validate_currency(currency).map_err(|error| FieldError::new("currency", error))?;
+            },
+            (Some(_), None) => return Err(FieldError::new("currency", "Currency is required for the campaign budget.")),
+            (None, Some(_)) => return Err(FieldError::new("currency", "Currency is only allowed for campaigns with a budget.")),
+            (None, None) => {},
+        }
+
+        return Ok( Self {
+            name,
+            description: campaign.description,
+            start_date: campaign.start_date,
+            end_date: campaign.end_date,
+            budget: campaign.budget,
+            currency: campaign.currency,
+            active: campaign.active,
+        });
+    }
+}
+
+impl Campaign {
+    /// Check if the coupons of the campaign can be used at the given moment.
+    pub fn check(&self, now: NaiveDateTime) -> Vec<InvalidReason> {
+        let mut reasons = Vec::new();
+        if (!self.active){
+            reasons.push(InvalidReason::CampaignInactive);
+        }
+        if let Some(start_date) = self.start_date {
+            if (start_date > now){
+                reasons.push(InvalidReason::CampaignNotStarted);
+            }
+        }
+        if let Some(end_date) = self.end_date {
+            if (end_date < now){
+                reasons.push(InvalidReason::CampaignEnded);
+            }
+        }
+        return reasons;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Campaign, CampaignInsert, CampaignRequest, InvalidReason};
+    use chrono::NaiveDateTime;
+    use claim::{assert_err, assert_ok};
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        return NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    }
+
+    fn campaign_request() -> CampaignRequest {
+        return CampaignRequest {
+            name: "Black Friday".to_string(),
+            description: None,
+            start_date: Some(datetime("2023-11-24 00:00:00")),
+            end_date: Some(datetime("2023-11-27 23:59:59")),
+            budget: Some(500000),
+            currency: Some("BRL".to_string()),
+            active: true,
+        };
+    }
+
+    fn campaign(active: bool) -> Campaign {
+        let request = campaign_request();
+        return Campaign {
+            id: 1,
+            name: request.name,
+            description: None,
+            start_date: request.start_date,
+            end_date: request.end_date,
+            budget: None,
+            currency: None,
+            active,
+            date_created: datetime("2023-01-01 00:00:00"),
+        };
+    }
+
+    #[test]
+    fn valid_campaign_is_accepted(){
+        assert_ok!(CampaignInsert::try_from(campaign_request()));
+    }
+
+    #[test]
+    fn invalid_campaign_is_rejected(){
+        let mut request = campaign_request();
+        request.name = " ".to_string();
+        assert_err!(CampaignInsert::try_from(request));
+
+        let mut request = campaign_request();
+        request.end_date = request.start_date;
+        assert_err!(CampaignInsert::try_from(request));
+
+        let mut request = campaign_request();
+        request.currency = None;
+        assert_err!(CampaignInsert::try_from(request));
+
+        let mut request = campaign_request();
+        request.budget = Some(0);
+        assert_err!(CampaignInsert::try_from(request));
+    }
+
+    #[test]
+    fn campaign_period_and_status_are_checked(){
+        assert!(campaign(true).check(datetime("2023-11-25 12:00:00")).is_empty());
+        assert_eq!(campaign(false).check(datetime("2023-11-25 12:00:00")), vec![InvalidReason::CampaignInactive]);
+        assert_eq!(campaign(true).check(datetime("2023-11-01 00:00:00")), vec![InvalidReason::CampaignNotStarted]);
+        assert_eq!(campaign(true).check(datetime("2023-12-01 00:00:00")), vec![InvalidReason::CampaignEnded]);
+    }
+}
@@ -1,69 +1,231 @@
-use super::CouponDiscount;
+use super::{CouponDiscount, CouponRule, DiscountType};
 use actix_web::{ 
     ResponseError,
     http::{StatusCode},
 };
+use rust_decimal::Decimal;
 use serde::{Serialize, Deserialize};
 // use chrono::NaiveDateTime;
 use sqlx::types::chrono::{NaiveDateTime};
 
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct Coupon {
     pub id: i32,
     pub code: String,
+    pub campaign_id: Option<i32>,
     pub discount: i32,
+    pub discount_type: String,
+    pub currency: Option<String>,
+    pub min_order_amount: Option<i32>,
+    pub max_discount_amount: Option<i32>,
     pub active: bool,
-    pub max_usage_count: Option<i32>, // not actually being used currently, we will also need a new field to track the `current usage` count for the coupon
+    pub max_usage_count: Option<i32>, // the `current usage` count is tracked by the `coupon_redemption` table
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    pub never_expires: bool,
     pub date_created: Option<NaiveDateTime>,
     pub date_updated: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>, // coupons are soft deleted, see `coupon_service::restore`
+    pub version: i32, // incremented on every update, see `coupon_etag`
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CouponInsert {
     pub code: String,
+    pub campaign_id: Option<i32>,
     pub discount: CouponDiscount,
     pub active: bool,
     pub max_usage_count: Option<i32>,
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    pub never_expires: bool,
+    pub rules: Vec<CouponRule>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CouponInsertRequest {
     pub code: String,
+    pub campaign_id: Option<i32>,
     pub discount: i32,
+    #[serde(default)]
+    pub discount_type: DiscountType,
+    pub currency: Option<String>,
+    pub min_order_amount: Option<i32>,
+    pub max_discount_amount: Option<i32>,
     pub active: bool,
     pub max_usage_count: Option<i32>,
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub never_expires: bool,
+    #[serde(default)]
+    pub rules: Vec<CouponRule>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CouponUpdate {
+    pub campaign_id: Option<i32>,
     pub discount: CouponDiscount,
     pub active: bool,
     pub max_usage_count: Option<i32>,
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    pub never_expires: bool,
+    pub rules: Vec<CouponRule>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CouponUpdateRequest {
+    pub campaign_id: Option<i32>,
     pub discount: i32,
+    #[serde(default)]
+    pub discount_type: DiscountType,
+    pub currency: Option<String>,
+    pub min_order_amount: Option<i32>,
+    pub max_discount_amount: Option<i32>,
     pub active: bool,
     pub max_usage_count: Option<i32>,
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub never_expires: bool,
+    #[serde(default)]
+    pub rules: Vec<CouponRule>,
+}
+
+// JSON Merge Patch (RFC 7396) of a coupon: a missing field is left unchanged and `null` clears it.
+// The nullable fields are `Option<Option<_>>` to tell a missing field (`None`) from a `null` one (`Some(None)`).
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CouponPatchRequest {
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub campaign_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub discount: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub discount_type: Option<Option<DiscountType>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub currency: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub min_order_amount: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub max_discount_amount: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub active: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub max_usage_count: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub max_usage_per_customer: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub valid_from: Option<Option<NaiveDateTime>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub expiration_date: Option<Option<NaiveDateTime>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub never_expires: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub rules: Option<Option<Vec<CouponRule>>>,
+}
+
+// A present field is always `Some`, even when it is `null`
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    return Deserialize::deserialize(deserializer).map(Some);
+}
+
+impl CouponPatchRequest {
+    /// Apply the patch to the current coupon, the result must be validated as a whole
+    /// (e.g. `currency` is required by a `fixed_amount` discount even if only `discount_type` was sent).
+    pub fn apply(&self, coupon: CouponResponse) -> Result<CouponUpdateRequest, FieldError> {
+        return Ok( CouponUpdateRequest {
+            campaign_id: self.campaign_id.unwrap_or(coupon.campaign_id),
+            discount: not_null("discount", self.discount)?.unwrap_or(coupon.discount),
+            discount_type: not_null("discount_type", self.discount_type)?.unwrap_or(coupon.discount_type),
+            currency: self.currency.clone().unwrap_or(coupon.currency),
+            min_order_amount: self.min_order_amount.unwrap_or(coupon.min_order_amount),
+            max_discount_amount: self.max_discount_amount.unwrap_or(coupon.max_discount_amount),
+            active: not_null("active", self.active)?.unwrap_or(coupon.active),
+            max_usage_count: self.max_usage_count.unwrap_or(coupon.max_usage_count),
+            max_usage_per_customer: self.max_usage_per_customer.unwrap_or(coupon.max_usage_per_customer),
+            valid_from: self.valid_from.unwrap_or(coupon.valid_from),
+            expiration_date: self.expiration_date.unwrap_or(coupon.expiration_date),
+            never_expires: not_null("never_expires", self.never_expires)?.unwrap_or(coupon.never_expires),
+            // `null` removes every rule
+            rules: self.rules.clone().map(Option::unwrap_or_default).unwrap_or(coupon.rules),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.campaign_id.is_none()
+            && self.discount.is_none()
+            && self.discount_type.is_none()
+            && self.currency.is_none()
+            && self.min_order_amount.is_none()
+            && self.max_discount_amount.is_none()
+            && self.active.is_none()
+            && self.max_usage_count.is_none()
+            && self.max_usage_per_customer.is_none()
+            && self.valid_from.is_none()
+            && self.expiration_date.is_none()
+            && self.never_expires.is_none()
+            && self.rules.is_none();
+    }
+}
+
+// The columns that are `NOT NULL` can not be cleared
+fn not_null<T>(field: &str, value: Option<Option<T>>) -> Result<Option<T>, FieldError> {
+    return match value {
+        Some(None) => Err(FieldError::new(field, format!("`{}` cannot be null.", field))),
+        Some(value) => Ok(value),
+        None => Ok(None),
+    };
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CouponResponse {
     pub id: i32,
     pub code: String,
+    pub campaign_id: Option<i32>,
     pub discount: i32,
+    pub discount_type: DiscountType,
+    pub currency: Option<String>,
+    pub min_order_amount: Option<i32>,
+    pub max_discount_amount: Option<i32>,
     pub active: bool,
     pub max_usage_count: Option<i32>,
+    pub max_usage_per_customer: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
     pub expiration_date: Option<NaiveDateTime>,
+    pub never_expires: bool,
     pub date_created: Option<NaiveDateTime>,
     pub date_updated: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub version: i32,
+    pub rules: Vec<CouponRule>,
+}
+
+// Query parameters identifying the customer that is using the coupon and the product (code) being purchased
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CustomerQuery {
+    pub customer_id: Option<String>,
+    pub product: Option<String>,
+}
+
+// Query parameters of the coupon verification and redemption, the order is needed to check the `min_order_amount`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerifyQuery {
+    pub customer_id: Option<String>,
+    pub product: Option<String>,
+    pub amount: Option<Decimal>,
+    pub currency: Option<String>,
 }
 
 // Convert a Coupon to a CouponResponse
@@ -73,56 +235,160 @@ impl TryFrom<Coupon> for CouponResponse {
         return Ok( Self {
             id: coupon.id,
             code: coupon.code,
+            campaign_id: coupon.campaign_id,
             discount: coupon.discount,
+            discount_type: DiscountType::try_from(coupon.discount_type)?,
+            currency: coupon.currency,
+            min_order_amount: coupon.min_order_amount,
+            max_discount_amount: coupon.max_discount_amount,
             active: coupon.active,
             max_usage_count: coupon.max_usage_count,
+            max_usage_per_customer: coupon.max_usage_per_customer,
+            valid_from: coupon.valid_from,
             expiration_date: coupon.expiration_date,
+            never_expires: coupon.never_expires,
             date_created: coupon.date_created,
             date_updated: coupon.date_updated,
+            deleted_at: coupon.deleted_at,
+            version: coupon.version,
+            // rules are stored in their own table and loaded separately
+            rules: Vec::new(),
         });
     }
 }
 
 impl TryFrom<CouponUpdateRequest> for CouponUpdate {
-    type Error = String;
+    type Error = FieldError;
     fn try_from(coupon: CouponUpdateRequest) -> Result<Self, Self::Error> {
-        let discount = CouponDiscount::parse(coupon.discount)?;
+        let discount = CouponDiscount::parse(
+            coupon.discount,
+            coupon.discount_type,
+            coupon.currency,
+            coupon.min_order_amount,
+            coupon.max_discount_amount,
+        )?;
+        validate_max_usage_per_customer(coupon.max_usage_per_customer)?;
+        validate_expiration(coupon.expiration_date, coupon.never_expires)?;
+        validate_validity_period(coupon.valid_from, coupon.expiration_date)?;
+        validate_rules(&coupon.rules)?;
         return Ok( Self {
+            campaign_id: coupon.campaign_id,
             discount,
             active: coupon.active,
             max_usage_count: coupon.max_usage_count,
+            max_usage_per_customer: coupon.max_usage_per_customer,
+            valid_from: coupon.valid_from,
             expiration_date: coupon.expiration_date,
+            never_expires: coupon.never_expires,
+            rules: coupon.rules,
         });
     }
 }
 
 impl TryFrom<CouponInsertRequest> for CouponInsert {
-    type Error = String;
+    type Error = FieldError;
     fn try_from(coupon: CouponInsertRequest) -> Result<Self, Self::Error> {
-        let discount = CouponDiscount::parse(coupon.discount)?;
+        let discount = CouponDiscount::parse(
+            coupon.discount,
+            coupon.discount_type,
+            coupon.currency,
+            coupon.min_order_amount,
+            coupon.max_discount_amount,
+        )?;
+        validate_max_usage_per_customer(coupon.max_usage_per_customer)?;
+        validate_expiration(coupon.expiration_date, coupon.never_expires)?;
+        validate_validity_period(coupon.valid_from, coupon.expiration_date)?;
+        validate_rules(&coupon.rules)?;
         return Ok( Self {
             code: coupon.code,
+            campaign_id: coupon.campaign_id,
             discount,
             active: coupon.active,
             max_usage_count: coupon.max_usage_count,
+            max_usage_per_customer: coupon.max_usage_per_customer,
+            valid_from: coupon.valid_from,
             expiration_date: coupon.expiration_date,
+            never_expires: coupon.never_expires,
+            rules: coupon.rules,
         });
     }
 }
 
+fn validate_max_usage_per_customer(max_usage_per_customer: Option<i32>) -> Result<(), FieldError> {
+    if let Some(max_usage_per_customer) = max_usage_per_customer {
+        if (max_usage_per_customer < 1){
+            return Err(FieldError::new("max_usage_per_customer", "Max usage per customer cannot be less than 1."));
+        }
+    }
+    return Ok(());
+}
+
+fn validate_expiration(expiration_date: Option<NaiveDateTime>, never_expires: bool) -> Result<(), FieldError> {
+    if (never_expires && expiration_date.is_some()){
+        return Err(FieldError::new("expiration_date", "A coupon that never expires cannot have an expiration date."));
+    }
+    if (!never_expires && expiration_date.is_none()){
+        return Err(FieldError::new("expiration_date", "Expiration date is required unless `never_expires` is set."));
+    }
+    return Ok(());
+}
+
+fn validate_validity_period(valid_from: Option<NaiveDateTime>, expiration_date: Option<NaiveDateTime>) -> Result<(), FieldError> {
+    if let (Some(valid_from), Some(expiration_date)) = (valid_from, expiration_date) {
+        if (valid_from >= expiration_date){
+            return Err(FieldError::new("valid_from", "Valid from date must be before the expiration date."));
+        }
+    }
+    return Ok(());
+}
+
+fn validate_rules(rules: &[CouponRule]) -> Result<(), FieldError> {
+    for (index, rule) in rules.iter().enumerate() {
+        rule.validate().map_err(|error| FieldError::new(&format!("rules[{}]", index), error))?;
+    }
+    return Ok(());
+}
 
 impl From<Coupon> for CouponInsertRequest {
     fn from(coupon: Coupon) -> Self {
         return Self {
             code: coupon.code,
+            campaign_id: coupon.campaign_id,
             discount: coupon.discount,
+            discount_type: DiscountType::try_from(coupon.discount_type).unwrap_or_default(),
+            currency: coupon.currency,
+            min_order_amount: coupon.min_order_amount,
+            max_discount_amount: coupon.max_discount_amount,
             active: coupon.active,
             max_usage_count: coupon.max_usage_count,
+            max_usage_per_customer: coupon.max_usage_per_customer,
+            valid_from: coupon.valid_from,
             expiration_date: coupon.expiration_date,
+            never_expires: coupon.never_expires,
+            rules: Vec::new(),
         };
     }
 }
 
+/// A validation error of a single field of the request, e.g. `expiration_date`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        return Self { field: field.to_string(), message: message.into() };
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}", self.message);
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum CouponError {
     #[error("{0}")]
@@ -135,6 +401,21 @@ pub enum CouponError {
     // ValidationError has one String parameter
     #[error("{0}")]
     ValidationError(String),
+    // ValidationError of a specific field of the request
+    #[error("{0}")]
+    FieldValidationError(FieldError),
+    // The coupon exists but can not be used (inactive, expired, usage limit reached...)
+    #[error("{0}")]
+    InvalidCouponError(#[source] anyhow::Error),
+    // The request conflicts with the current state (e.g. deleting a product still linked to coupons)
+    #[error("{0}")]
+    ConflictError(#[source] anyhow::Error),
+    // The `If-Match` header does not match the current version of the coupon
+    #[error("{0}")]
+    PreconditionFailedError(#[source] anyhow::Error),
+    // The session is not allowed to perform the request (e.g. only admins can list deleted coupons)
+    #[error("{0}")]
+    ForbiddenError(#[source] anyhow::Error),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -146,6 +427,11 @@ impl ResponseError for CouponError {
             CouponError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             CouponError::NotFoundError(_) => StatusCode::NOT_FOUND,
             CouponError::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            CouponError::FieldValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            CouponError::InvalidCouponError(_) => StatusCode::CONFLICT,
+            CouponError::ConflictError(_) => StatusCode::CONFLICT,
+            CouponError::PreconditionFailedError(_) => StatusCode::PRECONDITION_FAILED,
+            CouponError::ForbiddenError(_) => StatusCode::FORBIDDEN,
             CouponError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -157,3 +443,92 @@ impl ResponseError for CouponError {
 //         return error_chain_fmt(self, f);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::{CouponPatchRequest, CouponResponse, CouponUpdate};
+    use crate::coupon::DiscountType;
+    use claim::{assert_err, assert_ok};
+    use serde_json::json;
+
+    fn coupon() -> CouponResponse {
+        return CouponResponse {
+            id: 1,
+            code: "TEST".to_string(),
+            campaign_id: None,
+            discount: 10,
+            discount_type: DiscountType::Percentage,
+            currency: None,
+            min_order_amount: None,
+            max_discount_amount: None,
+            active: true,
+            max_usage_count: Some(5),
+            max_usage_per_customer: None,
+            valid_from: None,
+            expiration_date: None,
+            never_expires: true,
+            date_created: None,
+            date_updated: None,
+            deleted_at: None,
+            version: 1,
+            rules: vec![],
+        };
+    }
+
+    fn patch(body: serde_json::Value) -> CouponPatchRequest {
+        return serde_json::from_value(body).unwrap();
+    }
+
+    #[test]
+    fn missing_fields_are_left_unchanged(){
+        let coupon_patch = patch(json!({"active": false}));
+        let coupon_update = coupon_patch.apply(coupon()).unwrap();
+
+        assert!(!coupon_update.active);
+        assert_eq!(coupon_update.discount, 10);
+        assert_eq!(coupon_update.max_usage_count, Some(5));
+    }
+
+    #[test]
+    fn null_fields_are_cleared(){
+        let coupon_patch = patch(json!({"max_usage_count": null}));
+        assert_eq!(coupon_patch.max_usage_count, Some(None));
+
+        let coupon_update = coupon_patch.apply(coupon()).unwrap();
+        assert_eq!(coupon_update.max_usage_count, None);
+    }
+
+    #[test]
+    fn empty_patch_is_empty(){
+        assert!(patch(json!({})).is_empty());
+        assert!(!patch(json!({"rules": null})).is_empty());
+    }
+
+    #[test]
+    fn not_null_fields_cannot_be_cleared(){
+        for field in ["discount", "discount_type", "active", "never_expires"] {
+            let error = patch(json!({ field: null })).apply(coupon()).unwrap_err();
+            assert_eq!(error.field, field);
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected(){
+        assert_err!(serde_json::from_value::<CouponPatchRequest>(json!({"code": "OTHER"})));
+    }
+
+    #[test]
+    fn patched_coupon_is_validated_as_a_whole(){
+        // `fixed_amount` discounts require a currency
+        let coupon_update = patch(json!({"discount_type": "fixed_amount"})).apply(coupon()).unwrap();
+        let error = CouponUpdate::try_from(coupon_update).unwrap_err();
+        assert_eq!(error.field, "currency");
+
+        let coupon_update = patch(json!({"discount_type": "fixed_amount", "discount": 500, "currency": "BRL"})).apply(coupon()).unwrap();
+        assert_ok!(CouponUpdate::try_from(coupon_update));
+
+        let coupon_update = patch(json!({"discount": 91})).apply(coupon()).unwrap();
+        let error = CouponUpdate::try_from(coupon_update).unwrap_err();
+        assert_eq!(error.field, "discount");
+    }
+}
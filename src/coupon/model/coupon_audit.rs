@@ -0,0 +1,165 @@
+use super::{Coupon, CouponRule};
+use crate::authentication::Session;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CouponAuditAction {
+    Insert,
+    Update,
+    Patch,
+    Delete,
+    Restore,
+}
+
+impl CouponAuditAction {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            CouponAuditAction::Insert => "insert",
+            CouponAuditAction::Update => "update",
+            CouponAuditAction::Patch => "patch",
+            CouponAuditAction::Delete => "delete",
+            CouponAuditAction::Restore => "restore",
+        };
+    }
+}
+
+impl TryFrom<String> for CouponAuditAction {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        return match s.as_str() {
+            "insert" => Ok(Self::Insert),
+            "update" => Ok(Self::Update),
+            "patch" => Ok(Self::Patch),
+            "delete" => Ok(Self::Delete),
+            "restore" => Ok(Self::Restore),
+            other => Err(format!("{} is not a supported audit action.", other)),
+        };
+    }
+}
+
+/// Identity of the session that changed the coupon.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CouponAuditActor {
+    pub api_key_name: Option<String>,
+    // SHA-256 of the session id, the id itself authenticates a Redis session so it is never stored
+    pub session_hash: Option<String>,
+}
+
+impl From<&Session> for CouponAuditActor {
+    fn from(session: &Session) -> Self {
+        return Self {
+            api_key_name: Some(session.api_key_name.clone()).filter(|name| !name.is_empty()),
+            session_hash: Some(&session.id).filter(|id| !id.is_empty()).map(|id| hex::encode(Sha256::digest(id.as_bytes()))),
+        };
+    }
+}
+
+/// State of the coupon recorded in the audit: the coupon row with its rules.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CouponSnapshot {
+    #[serde(flatten)]
+    pub coupon: Coupon,
+    pub rules: Vec<CouponRule>,
+}
+
+/// Row of the append-only `coupon_audit` table, the snapshots are JSON (`CouponSnapshot`).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CouponAudit {
+    pub id: i32,
+    pub coupon_id: i32,
+    pub action: String,
+    pub api_key_name: Option<String>,
+    pub session_hash: Option<String>,
+    pub before_snapshot: Option<String>,
+    pub after_snapshot: Option<String>,
+    pub date_created: NaiveDateTime,
+}
+
+/// Entry of `GET /coupon/{id}/history`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponAuditResponse {
+    pub id: i32,
+    pub coupon_id: i32,
+    pub action: CouponAuditAction,
+    pub api_key_name: Option<String>,
+    pub session_hash: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub date_created: NaiveDateTime,
+}
+
+impl TryFrom<CouponAudit> for CouponAuditResponse {
+    type Error = String;
+    fn try_from(audit: CouponAudit) -> Result<Self, Self::Error> {
+        return Ok( Self {
+            id: audit.id,
+            coupon_id: audit.coupon_id,
+            action: CouponAuditAction::try_from(audit.action)?,
+            api_key_name: audit.api_key_name,
+            session_hash: audit.session_hash,
+            before: parse_snapshot(audit.before_snapshot)?,
+            after: parse_snapshot(audit.after_snapshot)?,
+            date_created: audit.date_created,
+        });
+    }
+}
+
+fn parse_snapshot(snapshot: Option<String>) -> Result<Option<serde_json::Value>, String> {
+    return snapshot
+        .map(|snapshot| serde_json::from_str(&snapshot).map_err(|error| format!("Failed to parse coupon snapshot: {}", error)))
+        .transpose();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CouponAudit, CouponAuditAction, CouponAuditActor, CouponAuditResponse};
+    use crate::authentication::{Role, Session};
+    use claim::{assert_err, assert_ok};
+    use sqlx::types::chrono::NaiveDateTime;
+
+    fn audit(action: &str, after_snapshot: Option<&str>) -> CouponAudit {
+        return CouponAudit {
+            id: 1,
+            coupon_id: 2,
+            action: action.to_string(),
+            api_key_name: Some("admin".to_string()),
+            session_hash: None,
+            before_snapshot: None,
+            after_snapshot: after_snapshot.map(str::to_string),
+            date_created: NaiveDateTime::parse_from_str("2023-03-22 14:09:15", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+    }
+
+    #[test]
+    fn audit_is_converted_to_response(){
+        let response = CouponAuditResponse::try_from(audit("update", Some(r#"{"discount": 10}"#))).unwrap();
+
+        assert_eq!(response.action, CouponAuditAction::Update);
+        assert_eq!(response.before, None);
+        assert_eq!(response.after.unwrap()["discount"], 10);
+    }
+
+    #[test]
+    fn invalid_audit_is_rejected(){
+        assert_err!(CouponAuditResponse::try_from(audit("merge", None)));
+        assert_err!(CouponAuditResponse::try_from(audit("update", Some("{"))));
+        assert_ok!(CouponAuditResponse::try_from(audit("delete", None)));
+    }
+
+    #[test]
+    fn actor_is_read_from_the_session(){
+        let session = Session { id: "1234".to_string(), api_key_name: "default".to_string(), role: Role::Editor, tenant: "default".to_string(), scopes: vec![], expires_at: None };
+        let actor = CouponAuditActor::from(&session);
+        assert_eq!(actor.api_key_name.as_deref(), Some("default"));
+        // SHA-256 of `1234`
+        assert_eq!(actor.session_hash.as_deref(), Some("03ac674216f3e15c761ee1a5e255f067953623c8b388b4459e13f978d7c846f4"));
+
+        let actor = CouponAuditActor::from(&Session::default());
+        assert!(actor.api_key_name.is_none() && actor.session_hash.is_none());
+    }
+}
@@ -0,0 +1,251 @@
+use super::{CouponInsert, CouponInsertRequest, CouponRule, DiscountType, FieldError};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+use std::collections::HashSet;
+
+
+const MAX_BATCH_COUNT: u32 = 1000;
+const MIN_CODE_LENGTH: u32 = 6;
+const MAX_CODE_LENGTH: u32 = 32;
+const MAX_PREFIX_LENGTH: usize = 32;
+
+/// Characters used to generate the random part of the codes.
+/// Ambiguous characters (`0/O`, `1/I/L`) are never used, so codes can be read out loud or typed by hand.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeAlphabet {
+    #[default]
+    Alphanumeric,
+    Letters,
+    Numbers,
+}
+
+impl CodeAlphabet {
+    pub fn chars(&self) -> &'static [u8] {
+        return match self {
+            CodeAlphabet::Alphanumeric => b"23456789ABCDEFGHJKMNPQRSTUVWXYZ",
+            CodeAlphabet::Letters => b"ABCDEFGHJKMNPQRSTUVWXYZ",
+            CodeAlphabet::Numbers => b"23456789",
+        };
+    }
+}
+
+fn default_code_length() -> u32 {
+    return 8;
+}
+
+// Every generated coupon is single-use (`max_usage_count` = 1) and shares the discount configuration
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponBatchRequest {
+    #[serde(default)]
+    pub prefix: String,
+    pub count: u32,
+    pub campaign_id: Option<i32>,
+    #[serde(default = "default_code_length")]
+    pub code_length: u32,
+    #[serde(default)]
+    pub alphabet: CodeAlphabet,
+    pub discount: i32,
+    #[serde(default)]
+    pub discount_type: DiscountType,
+    pub currency: Option<String>,
+    pub min_order_amount: Option<i32>,
+    pub max_discount_amount: Option<i32>,
+    pub active: bool,
+    pub valid_from: Option<NaiveDateTime>,
+    pub expiration_date: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub never_expires: bool,
+    #[serde(default)]
+    pub rules: Vec<CouponRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CouponBatchResponse {
+    pub count: usize,
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct CouponBatch {
+    prefix: String,
+    count: u32,
+    code_length: u32,
+    alphabet: CodeAlphabet,
+    // shared configuration of the coupons, only the `code` changes
+    template: CouponInsertRequest,
+}
+
+impl TryFrom<CouponBatchRequest> for CouponBatch {
+    type Error = FieldError;
+    fn try_from(batch: CouponBatchRequest) -> Result<Self, Self::Error> {
+        if (batch.count < 1 || batch.count > MAX_BATCH_COUNT){
+            return Err(FieldError::new("count", format!("Count must be between 1 and {}.", MAX_BATCH_COUNT)));
+        }
+        if (batch.code_length < MIN_CODE_LENGTH || batch.code_length > MAX_CODE_LENGTH){
+            return Err(FieldError::new("code_length", format!("Code length must be between {} and {}.", MIN_CODE_LENGTH, MAX_CODE_LENGTH)));
+        }
+        if (batch.prefix.len() > MAX_PREFIX_LENGTH){
+            return Err(FieldError::new("prefix", format!("Prefix cannot have more than {} characters.", MAX_PREFIX_LENGTH)));
+        }
+        if (!batch.prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')){
+            return Err(FieldError::new("prefix", "Prefix can only have letters, numbers, `-` and `_`."));
+        }
+        // a numeric code would be mistaken for an id by `GET /coupon/{id_or_code}`
+        if (batch.alphabet == CodeAlphabet::Numbers && !batch.prefix.chars().any(|c| c.is_ascii_alphabetic())){
+            return Err(FieldError::new("prefix", "A prefix with at least one letter is required for `numbers` codes."));
+        }
+        // keep the chance of a collision low, so generating the codes doesn't need many attempts
+        let possible_codes = (batch.alphabet.chars().len() as u128).checked_pow(batch.code_length).unwrap_or(u128::MAX);
+        if (possible_codes < u128::from(batch.count) * 1000){
+            return Err(FieldError::new("code_length", "Code length is too short to generate that many unique codes."));
+        }
+
+        let template = CouponInsertRequest {
+            code: batch.prefix.clone(),
+            campaign_id: batch.campaign_id,
+            discount: batch.discount,
+            discount_type: batch.discount_type,
+            currency: batch.currency,
+            min_order_amount: batch.min_order_amount,
+            max_discount_amount: batch.max_discount_amount,
+            active: batch.active,
+            max_usage_count: Some(1),
+            max_usage_per_customer: None,
+            valid_from: batch.valid_from,
+            expiration_date: batch.expiration_date,
+            never_expires: batch.never_expires,
+            rules: batch.rules,
+        };
+        // validate the shared configuration once, instead of failing on every generated coupon
+        CouponInsert::try_from(template.clone())?;
+
+        return Ok( Self {
+            prefix: batch.prefix,
+            count: batch.count,
+            code_length: batch.code_length,
+            alphabet: batch.alphabet,
+            template,
+        });
+    }
+}
+
+impl CouponBatch {
+    /// Generate `count` random codes, unique within the batch.
+    /// Codes may still collide with existing coupons, which is detected when inserting them.
+    pub fn generate_codes(&self) -> Vec<String> {
+        let chars = self.alphabet.chars();
+        let mut rng = rand::thread_rng();
+        let mut generated = HashSet::new();
+        let mut codes = Vec::new();
+
+        while (codes.len() < self.count as usize) {
+            let random_part: String = (0..self.code_length)
+                .map(|_| chars[rng.gen_range(0..chars.len())] as char)
+                .collect();
+            let code = format!("{}{}", self.prefix, random_part);
+            // a numeric code would be mistaken for an id, e.g. `23456789` without a prefix
+            if (code.parse::<i32>().is_err() && generated.insert(code.clone())){
+                codes.push(code);
+            }
+        }
+
+        return codes;
+    }
+
+    pub fn to_coupon_inserts(&self, codes: &[String]) -> Result<Vec<CouponInsert>, FieldError> {
+        return codes.iter()
+            .map(|code| {
+                let mut coupon = self.template.clone();
+                coupon.code = code.clone();
+                CouponInsert::try_from(coupon)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CouponBatch, CouponBatchRequest, CodeAlphabet};
+    use crate::coupon::DiscountType;
+    use claim::{assert_err, assert_ok};
+
+    fn batch_request(prefix: &str, count: u32, code_length: u32, alphabet: CodeAlphabet) -> CouponBatchRequest {
+        return CouponBatchRequest {
+            prefix: prefix.to_string(),
+            count,
+            campaign_id: None,
+            code_length,
+            alphabet,
+            discount: 10,
+            discount_type: DiscountType::Percentage,
+            currency: None,
+            min_order_amount: None,
+            max_discount_amount: None,
+            active: true,
+            valid_from: None,
+            expiration_date: None,
+            never_expires: true,
+            rules: vec![],
+        };
+    }
+
+    #[test]
+    fn generated_codes_are_unique_and_unambiguous(){
+        let batch = CouponBatch::try_from(batch_request("GIVEAWAY-", 500, 6, CodeAlphabet::Alphanumeric)).unwrap();
+
+        let codes = batch.generate_codes();
+
+        assert_eq!(codes.len(), 500);
+        let unique: std::collections::HashSet<&String> = codes.iter().collect();
+        assert_eq!(unique.len(), 500);
+        for code in codes {
+            let random_part = code.strip_prefix("GIVEAWAY-").unwrap();
+            assert_eq!(random_part.len(), 6);
+            assert!(!random_part.contains(|c| "0O1IL".contains(c)), "ambiguous character in `{}`", code);
+        }
+    }
+
+    #[test]
+    fn alphabets_never_have_ambiguous_characters(){
+        for alphabet in [CodeAlphabet::Alphanumeric, CodeAlphabet::Letters, CodeAlphabet::Numbers] {
+            assert!(!alphabet.chars().iter().any(|c| b"0O1IL".contains(c)));
+        }
+    }
+
+    #[test]
+    fn every_generated_coupon_is_single_use(){
+        let batch = CouponBatch::try_from(batch_request("", 3, 8, CodeAlphabet::Letters)).unwrap();
+
+        let coupons = batch.to_coupon_inserts(&batch.generate_codes()).unwrap();
+
+        assert_eq!(coupons.len(), 3);
+        assert!(coupons.iter().all(|coupon| coupon.max_usage_count == Some(1)));
+    }
+
+    #[test]
+    fn invalid_batch_is_rejected(){
+        assert_err!(CouponBatch::try_from(batch_request("", 0, 8, CodeAlphabet::Alphanumeric)));
+        assert_err!(CouponBatch::try_from(batch_request("", 1001, 8, CodeAlphabet::Alphanumeric)));
+        assert_err!(CouponBatch::try_from(batch_request("", 10, 5, CodeAlphabet::Alphanumeric)));
+        assert_err!(CouponBatch::try_from(batch_request("", 10, 33, CodeAlphabet::Alphanumeric)));
+        assert_err!(CouponBatch::try_from(batch_request("BAD PREFIX", 10, 8, CodeAlphabet::Alphanumeric)));
+        assert_err!(CouponBatch::try_from(batch_request("", 10, 8, CodeAlphabet::Numbers)));
+        assert_err!(CouponBatch::try_from(batch_request("2023", 10, 8, CodeAlphabet::Numbers)));
+        assert_ok!(CouponBatch::try_from(batch_request("GIFT-", 10, 8, CodeAlphabet::Numbers)));
+        // 8^6 codes are not enough for 1000 coupons with a low chance of collision
+        assert_err!(CouponBatch::try_from(batch_request("GIFT-", 1000, 6, CodeAlphabet::Numbers)));
+    }
+
+    #[test]
+    fn invalid_discount_configuration_is_rejected(){
+        let mut request = batch_request("", 10, 8, CodeAlphabet::Alphanumeric);
+        request.discount = 91;
+        assert_err!(CouponBatch::try_from(request));
+
+        let mut request = batch_request("", 10, 8, CodeAlphabet::Alphanumeric);
+        request.never_expires = false;
+        assert_err!(CouponBatch::try_from(request));
+    }
+}
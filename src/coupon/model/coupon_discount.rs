@@ -1,20 +1,176 @@
+use super::FieldError;
+use iso_currency::Currency;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Serialize, Deserialize};
 
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountType {
+    // `discount` is a percentage of the order amount
+    #[default]
+    Percentage,
+    // `discount` is an amount in the minor unit of the `currency` (e.g. cents)
+    FixedAmount,
+}
+
+impl DiscountType {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            DiscountType::Percentage => "percentage",
+            DiscountType::FixedAmount => "fixed_amount",
+        };
+    }
+}
+
+impl TryFrom<String> for DiscountType {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        return match s.as_str() {
+            "percentage" => Ok(Self::Percentage),
+            "fixed_amount" => Ok(Self::FixedAmount),
+            other => Err(format!(
+                "{} is not a supported discount type. Use either 'percentage' or 'fixed_amount'.",
+                other
+            )),
+        };
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
-pub struct CouponDiscount(i32);
+pub struct CouponDiscount {
+    value: i32,
+    discount_type: DiscountType,
+    currency: Option<String>,
+    // both limits are in the minor unit of the `currency`, like `fixed_amount` discounts
+    min_order_amount: Option<i32>,
+    max_discount_amount: Option<i32>,
+}
 
 impl CouponDiscount {
-    pub fn parse(d: i32) -> Result<Self, String> {
-        if (d > 90){
-            return Err("Discount cannot be higher than 90.".to_string());
+    pub fn parse(
+        d: i32,
+        discount_type: DiscountType,
+        currency: Option<String>,
+        min_order_amount: Option<i32>,
+        max_discount_amount: Option<i32>,
+    ) -> Result<Self, FieldError> {
+        let has_order_limits = min_order_amount.is_some() || max_discount_amount.is_some();
+
+        match discount_type {
+            DiscountType::Percentage => {
+                if (d > 90){
+                    return Err(FieldError::new("discount", "Discount cannot be higher than 90."));
+                }
+                if (d < 0){
+                    return Err(FieldError::new("discount", "Discount cannot be less than 0."));
+                }
+                if (currency.is_some() && !has_order_limits){
+                    return Err(FieldError::new("currency", "Currency is only allowed for `fixed_amount` discounts or coupons with order amount limits."));
+                }
+            },
+            DiscountType::FixedAmount => {
+                if (d <= 0){
+                    return Err(FieldError::new("discount", "Fixed amount discount must be higher than 0."));
+                }
+                if (currency.is_none()){
+                    return Err(FieldError::new("currency", "Currency is required for `fixed_amount` discounts."));
+                }
+            },
+        }
+
+        if (has_order_limits && currency.is_none()){
+            return Err(FieldError::new("currency", "Currency is required for coupons with order amount limits."));
+        }
+        if let Some(currency) = currency.as_ref() {
+            validate_currency(currency).map_err(|error| FieldError::new("currency", error))?;
+        }
+        if let Some(min_order_amount) = min_order_amount {
+            if (min_order_amount <= 0){
+                return Err(FieldError::new("min_order_amount", "Minimum order amount must be higher than 0."));
+            }
+        }
+        if let Some(max_discount_amount) = max_discount_amount {
+            if (max_discount_amount <= 0){
+                return Err(FieldError::new("max_discount_amount", "Maximum discount amount must be higher than 0."));
+            }
+        }
+
+        return Ok( Self { value: d, discount_type, currency, min_order_amount, max_discount_amount } );
+    }
+
+    pub fn discount_type(&self) -> DiscountType {
+        return self.discount_type;
+    }
+
+    pub fn currency(&self) -> Option<&String> {
+        return self.currency.as_ref();
+    }
+
+    pub fn min_order_amount(&self) -> Option<i32> {
+        return self.min_order_amount;
+    }
+
+    pub fn max_discount_amount(&self) -> Option<i32> {
+        return self.max_discount_amount;
+    }
+
+    /// Check if an order `amount` in `currency` reaches the minimum order amount of the coupon.
+    pub fn meets_min_order_amount(&self, amount: Decimal, currency: Currency) -> Result<bool, String> {
+        return match self.min_order_amount {
+            Some(min_order_amount) => Ok(amount >= self.to_decimal(min_order_amount, currency)?),
+            None => Ok(true),
+        };
+    }
+
+    /// Compute the discount for an order `amount` in `currency`.
+    /// 
+    /// Percentage discounts are rounded to the minor unit of the currency, with halves rounded
+    /// away from zero (e.g. 12.345 BRL becomes 12.35 BRL). The discount is capped at the
+    /// `max_discount_amount` and at the `amount`, so the final amount is never negative.
+    pub fn apply(&self, amount: Decimal, currency: Currency) -> Result<Decimal, String> {
+        let exponent = currency.exponent()
+            .ok_or(format!("Currency `{}` does not have a minor unit.", currency.code()))?;
+
+        let mut discount = match self.discount_type {
+            DiscountType::Percentage => {
+                (amount * Decimal::from(self.value) / Decimal::ONE_HUNDRED)
+                    .round_dp_with_strategy(u32::from(exponent), RoundingStrategy::MidpointAwayFromZero)
+            },
+            DiscountType::FixedAmount => self.to_decimal(self.value, currency)?,
+        };
+
+        if let Some(max_discount_amount) = self.max_discount_amount {
+            discount = discount.min(self.to_decimal(max_discount_amount, currency)?);
         }
-        if (d < 0){
-            return Err("Discount cannot be less than 0.".to_string());
+
+        return Ok(discount.min(amount));
+    }
+
+    // Read an amount stored in the minor unit of the coupon currency, which must match the order `currency`
+    fn to_decimal(&self, minor_units: i32, currency: Currency) -> Result<Decimal, String> {
+        if (self.currency.as_deref() != Some(currency.code())){
+            return Err(format!(
+                "Coupon currency `{}` does not match the order currency `{}`.",
+                self.currency.as_deref().unwrap_or_default(), currency.code()
+            ));
         }
+        let exponent = currency.exponent()
+            .ok_or(format!("Currency `{}` does not have a minor unit.", currency.code()))?;
+        return Ok(Decimal::new(i64::from(minor_units), u32::from(exponent)));
+    }
+}
 
-        return Ok( Self(d) );
+pub(super) fn validate_currency(currency: &str) -> Result<(), String> {
+    // only actual currencies have a minor unit, funds and metals (e.g. `XAU`) are not accepted
+    let valid_currency = Currency::from_code(currency)
+        .map(|currency| currency.exponent().is_some())
+        .unwrap_or(false);
+    if (!valid_currency){
+        return Err(format!("Currency `{}` is not a valid ISO-4217 currency code.", currency));
     }
+    return Ok(());
 }
 
 impl AsRef<i32> for CouponDiscount {
@@ -22,29 +178,120 @@ impl AsRef<i32> for CouponDiscount {
     // This gives the caller **read-only** access,
     // they have no way to compromise our invariants!
     fn as_ref(&self) -> &i32 {
-        &self.0
+        &self.value
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::CouponDiscount;
+    use super::{CouponDiscount, DiscountType};
     use claim::{assert_err, assert_ok};
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn decimal(s: &str) -> Decimal {
+        return Decimal::from_str(s).unwrap();
+    }
 
     #[test]
     fn valid_discount_is_accepted(){
-        assert_ok!(CouponDiscount::parse(10));
+        assert_ok!(CouponDiscount::parse(10, DiscountType::Percentage, None, None, None));
     }
 
     #[test]
     fn discount_higher_than_90_is_rejected(){
-        assert_ok!(CouponDiscount::parse(90));
-        assert_err!(CouponDiscount::parse(91));
+        assert_ok!(CouponDiscount::parse(90, DiscountType::Percentage, None, None, None));
+        assert_err!(CouponDiscount::parse(91, DiscountType::Percentage, None, None, None));
     }
 
     #[test]
     fn discount_less_than_0_is_rejected(){
-        assert_ok!(CouponDiscount::parse(0));
-        assert_err!(CouponDiscount::parse(-1));
+        assert_ok!(CouponDiscount::parse(0, DiscountType::Percentage, None, None, None));
+        assert_err!(CouponDiscount::parse(-1, DiscountType::Percentage, None, None, None));
+    }
+
+    #[test]
+    fn percentage_discount_with_currency_is_rejected(){
+        assert_err!(CouponDiscount::parse(10, DiscountType::Percentage, Some("BRL".to_string()), None, None));
+    }
+
+    #[test]
+    fn valid_fixed_amount_discount_is_accepted(){
+        assert_ok!(CouponDiscount::parse(1050, DiscountType::FixedAmount, Some("BRL".to_string()), None, None));
+        // fixed amounts are not capped at 90
+        assert_ok!(CouponDiscount::parse(10000, DiscountType::FixedAmount, Some("USD".to_string()), None, None));
+    }
+
+    #[test]
+    fn fixed_amount_discount_not_positive_is_rejected(){
+        assert_err!(CouponDiscount::parse(0, DiscountType::FixedAmount, Some("BRL".to_string()), None, None));
+        assert_err!(CouponDiscount::parse(-1, DiscountType::FixedAmount, Some("BRL".to_string()), None, None));
+    }
+
+    #[test]
+    fn fixed_amount_discount_requires_a_valid_currency(){
+        assert_err!(CouponDiscount::parse(100, DiscountType::FixedAmount, None, None, None));
+        assert_err!(CouponDiscount::parse(100, DiscountType::FixedAmount, Some("XYZ".to_string()), None, None));
+        assert_err!(CouponDiscount::parse(100, DiscountType::FixedAmount, Some("brl".to_string()), None, None));
+        assert_err!(CouponDiscount::parse(100, DiscountType::FixedAmount, Some("XAU".to_string()), None, None));
+    }
+
+    #[test]
+    fn percentage_discount_is_rounded_to_the_currency_minor_unit(){
+        let discount = CouponDiscount::parse(15, DiscountType::Percentage, None, None, None).unwrap();
+        // 15% of 82.30 is 12.345
+        assert_eq!(discount.apply(decimal("82.30"), Currency::BRL), Ok(decimal("12.35")));
+        // 15% of 82.10 is 12.315
+        assert_eq!(discount.apply(decimal("82.10"), Currency::BRL), Ok(decimal("12.32")));
+        // JPY has no decimals, 15% of 999 is 149.85
+        assert_eq!(discount.apply(decimal("999"), Currency::JPY), Ok(decimal("150")));
+    }
+
+    #[test]
+    fn fixed_amount_discount_is_read_in_the_currency_minor_unit(){
+        let discount = CouponDiscount::parse(1050, DiscountType::FixedAmount, Some("BRL".to_string()), None, None).unwrap();
+        assert_eq!(discount.apply(decimal("100.00"), Currency::BRL), Ok(decimal("10.50")));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn discount_is_capped_at_the_amount(){
+        let discount = CouponDiscount::parse(1050, DiscountType::FixedAmount, Some("BRL".to_string()), None, None).unwrap();
+        assert_eq!(discount.apply(decimal("5.00"), Currency::BRL), Ok(decimal("5.00")));
+    }
+
+    #[test]
+    fn fixed_amount_discount_in_another_currency_is_rejected(){
+        let discount = CouponDiscount::parse(1050, DiscountType::FixedAmount, Some("BRL".to_string()), None, None).unwrap();
+        assert_err!(discount.apply(decimal("100.00"), Currency::USD));
+    }
+
+    #[test]
+    fn percentage_discount_with_order_limits_requires_a_currency(){
+        assert_err!(CouponDiscount::parse(10, DiscountType::Percentage, None, Some(5000), None));
+        assert_err!(CouponDiscount::parse(10, DiscountType::Percentage, None, None, Some(2000)));
+        assert_ok!(CouponDiscount::parse(10, DiscountType::Percentage, Some("BRL".to_string()), Some(5000), Some(2000)));
+    }
+
+    #[test]
+    fn order_limits_not_positive_are_rejected(){
+        assert_err!(CouponDiscount::parse(10, DiscountType::Percentage, Some("BRL".to_string()), Some(0), None));
+        assert_err!(CouponDiscount::parse(10, DiscountType::Percentage, Some("BRL".to_string()), None, Some(-1)));
+    }
+
+    #[test]
+    fn min_order_amount_is_checked(){
+        let discount = CouponDiscount::parse(10, DiscountType::Percentage, Some("BRL".to_string()), Some(5000), None).unwrap();
+        assert_eq!(discount.meets_min_order_amount(decimal("50.00"), Currency::BRL), Ok(true));
+        assert_eq!(discount.meets_min_order_amount(decimal("49.99"), Currency::BRL), Ok(false));
+        assert_err!(discount.meets_min_order_amount(decimal("100.00"), Currency::USD));
+    }
+
+    #[test]
+    fn discount_is_capped_at_the_max_discount_amount(){
+        let discount = CouponDiscount::parse(50, DiscountType::Percentage, Some("BRL".to_string()), None, Some(2000)).unwrap();
+        assert_eq!(discount.apply(decimal("30.00"), Currency::BRL), Ok(decimal("15.00")));
+        assert_eq!(discount.apply(decimal("100.00"), Currency::BRL), Ok(decimal("20.00")));
+        assert_err!(discount.apply(decimal("100.00"), Currency::USD));
+    }
+}
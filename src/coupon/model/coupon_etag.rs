@@ -0,0 +1,87 @@
+use super::CouponError;
+use actix_web::http::header::{EntityTag, IfMatch, IfNoneMatch};
+use anyhow::anyhow;
+
+
+/// Strong `ETag` of a coupon, derived from its `version` that is incremented on every update.
+/// e.g. `"3"`
+pub fn coupon_etag(version: i32) -> EntityTag {
+    return EntityTag::new_strong(version.to_string());
+}
+
+/// Check the `If-Match` header of a write request against the current `version` of the coupon.
+///
+/// Returns the version the write must be conditioned on, so a concurrent update between the
+/// check and the write is also detected. It is `None` when there is no precondition (no header or `*`).
+pub fn check_if_match(if_match: Option<&IfMatch>, version: i32) -> Result<Option<i32>, CouponError> {
+    let etag = coupon_etag(version);
+    return match if_match {
+        None | Some(IfMatch::Any) => Ok(None),
+        // actix parses a missing header as an empty list
+        Some(IfMatch::Items(etags)) if etags.is_empty() => Ok(None),
+        // `If-Match` uses the strong comparison, a weak `ETag` never matches
+        Some(IfMatch::Items(etags)) if etags.iter().any(|item| item.strong_eq(&etag)) => Ok(Some(version)),
+        Some(IfMatch::Items(_)) => Err(precondition_failed(version)),
+    };
+}
+
+/// Check the `If-None-Match` header of a GET request, `true` when the client already has the
+/// current version of the coupon (`304 Not Modified`).
+pub fn is_not_modified(if_none_match: Option<&IfNoneMatch>, version: i32) -> bool {
+    let etag = coupon_etag(version);
+    return match if_none_match {
+        None => false,
+        Some(IfNoneMatch::Any) => true,
+        // `If-None-Match` uses the weak comparison
+        Some(IfNoneMatch::Items(etags)) => etags.iter().any(|item| item.weak_eq(&etag)),
+    };
+}
+
+pub fn precondition_failed(version: i32) -> CouponError {
+    return CouponError::PreconditionFailedError(anyhow!(format!(
+        "The coupon was modified, its current ETag is `{}`.", coupon_etag(version)
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_if_match, coupon_etag, is_not_modified};
+    use actix_web::http::header::{EntityTag, IfMatch, IfNoneMatch};
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn etag_is_the_quoted_version(){
+        assert_eq!(coupon_etag(3).to_string(), "\"3\"");
+    }
+
+    #[test]
+    fn missing_or_any_if_match_has_no_precondition(){
+        assert_ok_eq!(check_if_match(None, 3), None);
+        assert_ok_eq!(check_if_match(Some(&IfMatch::Any), 3), None);
+        assert_ok_eq!(check_if_match(Some(&IfMatch::Items(vec![])), 3), None);
+    }
+
+    #[test]
+    fn if_match_with_the_current_version_is_accepted(){
+        let if_match = IfMatch::Items(vec![EntityTag::new_strong("2".to_string()), EntityTag::new_strong("3".to_string())]);
+        assert_ok_eq!(check_if_match(Some(&if_match), 3), Some(3));
+    }
+
+    #[test]
+    fn if_match_with_another_version_is_rejected(){
+        let if_match = IfMatch::Items(vec![EntityTag::new_strong("2".to_string())]);
+        assert_err!(check_if_match(Some(&if_match), 3));
+
+        let if_match = IfMatch::Items(vec![EntityTag::new_weak("3".to_string())]);
+        assert_err!(check_if_match(Some(&if_match), 3));
+    }
+
+    #[test]
+    fn if_none_match_with_the_current_version_is_not_modified(){
+        assert!(!is_not_modified(None, 3));
+        assert!(!is_not_modified(Some(&IfNoneMatch::Items(vec![])), 3));
+        assert!(is_not_modified(Some(&IfNoneMatch::Any), 3));
+        assert!(is_not_modified(Some(&IfNoneMatch::Items(vec![EntityTag::new_weak("3".to_string())])), 3));
+        assert!(!is_not_modified(Some(&IfNoneMatch::Items(vec![EntityTag::new_strong("2".to_string())])), 3));
+    }
+}
@@ -0,0 +1,282 @@
+use super::{CouponResponse, FieldError};
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Query params of `GET /coupon`, every filter is optional.
+/// e.g. `/coupon?page=2&page_size=50&active=true&code=BLACK&sort=-date_created`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CouponListQuery {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+    pub active: Option<bool>,
+    pub expired: Option<bool>,
+    // prefix of the coupon code
+    pub code: Option<String>,
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+    pub min_discount: Option<i32>,
+    pub max_discount: Option<i32>,
+    // field to sort by, prefixed with `-` for descending order
+    pub sort: Option<String>,
+    // list the deleted coupons instead, only allowed for admin sessions
+    pub deleted: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Id,
+    Code,
+    Discount,
+    DateCreated,
+    ExpirationDate,
+}
+
+impl SortField {
+    // only these column names are ever concatenated into the query
+    pub fn column(&self) -> &'static str {
+        return match self {
+            SortField::Id => "id",
+            SortField::Code => "code",
+            SortField::Discount => "discount",
+            SortField::DateCreated => "date_created",
+            SortField::ExpirationDate => "expiration_date",
+        };
+    }
+}
+
+impl TryFrom<&str> for SortField {
+    type Error = String;
+    fn try_from(field: &str) -> Result<Self, Self::Error> {
+        return match field {
+            "id" => Ok(SortField::Id),
+            "code" => Ok(SortField::Code),
+            "discount" => Ok(SortField::Discount),
+            "date_created" => Ok(SortField::DateCreated),
+            "expiration_date" => Ok(SortField::ExpirationDate),
+            other => Err(format!(
+                "`{}` is not a valid sort field. Use `id`, `code`, `discount`, `date_created` or `expiration_date`.", other
+            )),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CouponSort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for CouponSort {
+    fn default() -> Self {
+        return Self { field: SortField::Id, descending: false };
+    }
+}
+
+/// Validated filters of `GET /coupon`, used to build the `WHERE` and `ORDER BY` clauses.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponFilter {
+    pub page: u32,
+    pub page_size: u32,
+    pub active: Option<bool>,
+    pub expired: Option<bool>,
+    pub code_prefix: Option<String>,
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+    pub min_discount: Option<i32>,
+    pub max_discount: Option<i32>,
+    pub sort: CouponSort,
+    pub deleted: bool,
+}
+
+impl TryFrom<CouponListQuery> for CouponFilter {
+    type Error = FieldError;
+    fn try_from(query: CouponListQuery) -> Result<Self, Self::Error> {
+        let page = query.page.unwrap_or(1);
+        if (page == 0){
+            return Err(FieldError::new("page", "Page must be higher than 0."));
+        }
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        if (page_size == 0 || page_size > MAX_PAGE_SIZE){
+            return Err(FieldError::new("page_size", format!("Page size must be between 1 and {}.", MAX_PAGE_SIZE)));
+        }
+        if let (Some(created_from), Some(created_to)) = (query.created_from, query.created_to) {
+            if (created_from > created_to){
+                return Err(FieldError::new("created_from", "`created_from` must be before `created_to`."));
+            }
+        }
+        if let (Some(min_discount), Some(max_discount)) = (query.min_discount, query.max_discount) {
+            if (min_discount > max_discount){
+                return Err(FieldError::new("min_discount", "`min_discount` cannot be higher than `max_discount`."));
+            }
+        }
+        let code_prefix = query.code
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty());
+
+        let sort = match query.sort.as_deref().map(str::trim) {
+            None | Some("") => CouponSort::default(),
+            Some(sort) => match sort.strip_prefix('-') {
+                Some(field) => CouponSort { field: SortField::try_from(field).map_err(|error| FieldError::new("sort", error))?, descending: true },
+                None => CouponSort { field: SortField::try_from(sort).map_err(|error| FieldError::new("sort", error))?, descending: false },
+            },
+        };
+
+        return Ok( Self {
+            page,
+            page_size,
+            active: query.active,
+            expired: query.expired,
+            code_prefix,
+            created_from: query.created_from,
+            created_to: query.created_to,
+            min_discount: query.min_discount,
+            max_discount: query.max_discount,
+            sort,
+            deleted: query.deleted.unwrap_or(false),
+        });
+    }
+}
+
+impl CouponFilter {
+    pub fn offset(&self) -> u64 {
+        return u64::from(self.page - 1) * u64::from(self.page_size);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pagination {
+    pub page: u32,
+    pub page_size: u32,
+    pub total_count: i64,
+    pub total_pages: i64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponPage {
+    pub data: Vec<CouponResponse>,
+    pub pagination: Pagination,
+}
+
+impl Pagination {
+    /// Build the pagination metadata, the `next`/`prev` links keep every filter of the `query`.
+    pub fn new(query: &CouponListQuery, filter: &CouponFilter, total_count: i64, base_url: &str) -> Self {
+        let page_size = i64::from(filter.page_size);
+        let total_pages = (total_count + page_size - 1) / page_size;
+        let page = i64::from(filter.page);
+
+        let next = if (page < total_pages) { Some(page_link(query, filter.page + 1, base_url)) } else { None };
+        // a page after the last one links back to the last page
+        let prev = if (page > 1 && total_pages > 0) {
+            Some(page_link(query, (filter.page - 1).min(total_pages as u32), base_url))
+        } else {
+            None
+        };
+
+        return Self {
+            page: filter.page,
+            page_size: filter.page_size,
+            total_count,
+            total_pages,
+            next,
+            prev,
+        };
+    }
+}
+
+fn page_link(query: &CouponListQuery, page: u32, base_url: &str) -> String {
+    let mut pairs: Vec<(&str, String)> = vec![("page", page.to_string())];
+    if let Some(page_size) = query.page_size { pairs.push(("page_size", page_size.to_string())); }
+    if let Some(active) = query.active { pairs.push(("active", active.to_string())); }
+    if let Some(expired) = query.expired { pairs.push(("expired", expired.to_string())); }
+    if let Some(code) = &query.code { pairs.push(("code", code.clone())); }
+    if let Some(created_from) = query.created_from { pairs.push(("created_from", created_from.format("%Y-%m-%dT%H:%M:%S").to_string())); }
+    if let Some(created_to) = query.created_to { pairs.push(("created_to", created_to.format("%Y-%m-%dT%H:%M:%S").to_string())); }
+    if let Some(min_discount) = query.min_discount { pairs.push(("min_discount", min_discount.to_string())); }
+    if let Some(max_discount) = query.max_discount { pairs.push(("max_discount", max_discount.to_string())); }
+    if let Some(sort) = &query.sort { pairs.push(("sort", sort.clone())); }
+    if let Some(deleted) = query.deleted { pairs.push(("deleted", deleted.to_string())); }
+
+    let query_string = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(pairs)
+        .finish();
+    return format!("{}/coupon?{}", base_url.trim_end_matches('/'), query_string);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CouponFilter, CouponListQuery, CouponSort, Pagination, SortField};
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn default_filter_is_the_first_page_sorted_by_id(){
+        let filter = CouponFilter::try_from(CouponListQuery::default()).unwrap();
+
+        assert_eq!(filter.page, 1);
+        assert_eq!(filter.page_size, 20);
+        assert_eq!(filter.offset(), 0);
+        assert_eq!(filter.sort, CouponSort::default());
+    }
+
+    #[test]
+    fn sort_is_parsed(){
+        let query = CouponListQuery { sort: Some("-date_created".to_string()), ..Default::default() };
+        let filter = CouponFilter::try_from(query).unwrap();
+        assert_eq!(filter.sort, CouponSort { field: SortField::DateCreated, descending: true });
+
+        let query = CouponListQuery { sort: Some("code".to_string()), ..Default::default() };
+        let filter = CouponFilter::try_from(query).unwrap();
+        assert_eq!(filter.sort, CouponSort { field: SortField::Code, descending: false });
+
+        let query = CouponListQuery { sort: Some("date_created; DROP TABLE coupon".to_string()), ..Default::default() };
+        assert_err!(CouponFilter::try_from(query));
+    }
+
+    #[test]
+    fn invalid_filter_is_rejected(){
+        assert_err!(CouponFilter::try_from(CouponListQuery { page: Some(0), ..Default::default() }));
+        assert_err!(CouponFilter::try_from(CouponListQuery { page_size: Some(0), ..Default::default() }));
+        assert_err!(CouponFilter::try_from(CouponListQuery { page_size: Some(101), ..Default::default() }));
+        assert_err!(CouponFilter::try_from(CouponListQuery { min_discount: Some(20), max_discount: Some(10), ..Default::default() }));
+        assert_ok!(CouponFilter::try_from(CouponListQuery { page: Some(3), page_size: Some(100), ..Default::default() }));
+    }
+
+    #[test]
+    fn pagination_links_keep_the_filters(){
+        let query = CouponListQuery {
+            page: Some(2),
+            page_size: Some(10),
+            active: Some(true),
+            code: Some("BLACK FRIDAY".to_string()),
+            ..Default::default()
+        };
+        let filter = CouponFilter::try_from(query.clone()).unwrap();
+
+        let pagination = Pagination::new(&query, &filter, 25, "http://127.0.0.1/");
+
+        assert_eq!(filter.offset(), 10);
+        assert_eq!(pagination.total_pages, 3);
+        assert_eq!(pagination.next, Some("http://127.0.0.1/coupon?page=3&page_size=10&active=true&code=BLACK+FRIDAY".to_string()));
+        assert_eq!(pagination.prev, Some("http://127.0.0.1/coupon?page=1&page_size=10&active=true&code=BLACK+FRIDAY".to_string()));
+    }
+
+    #[test]
+    fn first_and_last_pages_have_no_prev_and_next_links(){
+        let query = CouponListQuery::default();
+        let filter = CouponFilter::try_from(query.clone()).unwrap();
+
+        let pagination = Pagination::new(&query, &filter, 5, "http://127.0.0.1");
+        assert_eq!(pagination.total_pages, 1);
+        assert_eq!(pagination.next, None);
+        assert_eq!(pagination.prev, None);
+
+        let pagination = Pagination::new(&query, &filter, 0, "http://127.0.0.1");
+        assert_eq!(pagination.total_pages, 0);
+        assert_eq!(pagination.next, None);
+    }
+}
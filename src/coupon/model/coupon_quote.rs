@@ -0,0 +1,120 @@
+use iso_currency::Currency;
+use rust_decimal::Decimal;
+use serde::{Serialize, Deserialize};
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponQuoteRequest {
+    // send it as a string (e.g. "100.50") to avoid floating point rounding
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponQuote {
+    pub original_amount: Decimal,
+    pub discount_amount: Decimal,
+    pub final_amount: Decimal,
+    pub currency: String,
+}
+
+#[derive(Debug)]
+pub struct OrderAmount {
+    amount: Decimal,
+    currency: Currency,
+}
+
+impl OrderAmount {
+    pub fn parse(amount: Decimal, currency: String) -> Result<Self, String> {
+        let currency = Currency::from_code(&currency)
+            .filter(|currency| currency.exponent().is_some())
+            .ok_or(format!("Currency `{}` is not a valid ISO-4217 currency code.", currency))?;
+
+        if (amount <= Decimal::ZERO){
+            return Err("Amount must be higher than 0.".to_string());
+        }
+        let exponent = currency.exponent().unwrap_or_default();
+        if (amount.normalize().scale() > u32::from(exponent)){
+            return Err(format!("Amount cannot have more than {} decimal places for `{}`.", exponent, currency.code()));
+        }
+
+        return Ok( Self { amount, currency } );
+    }
+
+    pub fn amount(&self) -> Decimal {
+        return self.amount;
+    }
+
+    pub fn currency(&self) -> Currency {
+        return self.currency;
+    }
+
+    /// An amount of the order (e.g. its discount) in the minor unit of the currency, as the amounts are stored.
+    pub fn to_minor_units(&self, amount: Decimal) -> Option<i32> {
+        let exponent = u32::from(self.currency.exponent().unwrap_or_default());
+        let mut amount = amount.round_dp(exponent);
+        amount.rescale(exponent);
+        return i32::try_from(amount.mantissa()).ok();
+    }
+}
+
+impl CouponQuote {
+    pub fn new(order: &OrderAmount, discount_amount: Decimal) -> Self {
+        // every amount is returned with the currency's number of decimal places
+        let scale = u32::from(order.currency().exponent().unwrap_or_default());
+        let mut original_amount = order.amount();
+        let mut discount_amount = discount_amount;
+        let mut final_amount = original_amount - discount_amount;
+        original_amount.rescale(scale);
+        discount_amount.rescale(scale);
+        final_amount.rescale(scale);
+
+        return Self {
+            original_amount,
+            discount_amount,
+            final_amount,
+            currency: order.currency().code().to_string(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderAmount;
+    use claim::{assert_err, assert_ok};
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    #[test]
+    fn valid_order_amount_is_accepted(){
+        assert_ok!(OrderAmount::parse(Decimal::from_str("100.50").unwrap(), "BRL".to_string()));
+        assert_ok!(OrderAmount::parse(Decimal::from_str("100.500").unwrap(), "BRL".to_string()));
+    }
+
+    #[test]
+    fn order_amount_not_positive_is_rejected(){
+        assert_err!(OrderAmount::parse(Decimal::ZERO, "BRL".to_string()));
+        assert_err!(OrderAmount::parse(Decimal::from_str("-1").unwrap(), "BRL".to_string()));
+    }
+
+    #[test]
+    fn order_amount_with_too_many_decimal_places_is_rejected(){
+        assert_err!(OrderAmount::parse(Decimal::from_str("100.505").unwrap(), "BRL".to_string()));
+        assert_err!(OrderAmount::parse(Decimal::from_str("100.5").unwrap(), "JPY".to_string()));
+    }
+
+    #[test]
+    fn amount_is_converted_to_the_minor_unit(){
+        let order = OrderAmount::parse(Decimal::from_str("100.50").unwrap(), "BRL".to_string()).unwrap();
+        assert_eq!(order.to_minor_units(Decimal::from_str("10.05").unwrap()), Some(1005));
+        assert_eq!(order.to_minor_units(Decimal::from_str("10").unwrap()), Some(1000));
+
+        let order = OrderAmount::parse(Decimal::from_str("1000").unwrap(), "JPY".to_string()).unwrap();
+        assert_eq!(order.to_minor_units(Decimal::from_str("100").unwrap()), Some(100));
+    }
+
+    #[test]
+    fn order_amount_with_invalid_currency_is_rejected(){
+        assert_err!(OrderAmount::parse(Decimal::from_str("100").unwrap(), "XYZ".to_string()));
+    }
+}
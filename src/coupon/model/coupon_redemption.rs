@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponRedemption {
+    pub id: i32,
+    pub coupon_id: i32,
+    pub customer_id: Option<String>,
+    // discount of the order sent with the redemption, in the minor unit of the `currency`
+    pub discount_amount: Option<i32>,
+    pub currency: Option<String>,
+    pub date_created: NaiveDateTime,
+}
@@ -0,0 +1,138 @@
+use super::InvalidReason;
+use chrono::{Datelike, Timelike, Weekday};
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+/// A restriction on when a coupon can be used, stored in the `coupon_rule` table.
+/// All times are evaluated in UTC.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CouponRule {
+    // Only valid on the given days of the week, e.g. `["Sat", "Sun"]`
+    Weekdays { weekdays: Vec<Weekday> },
+    // Only valid from `start_hour` (inclusive) to `end_hour` (exclusive),
+    // the window wraps around midnight when `start_hour` is higher than `end_hour`
+    HourWindow { start_hour: u32, end_hour: u32 },
+    // Only valid between `start_date` and `end_date` (both inclusive)
+    DateRange { start_date: NaiveDateTime, end_date: NaiveDateTime },
+}
+
+impl CouponRule {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            CouponRule::Weekdays { weekdays } => {
+                if (weekdays.is_empty()){
+                    return Err("Weekdays rule must have at least one weekday.".to_string());
+                }
+            },
+            CouponRule::HourWindow { start_hour, end_hour } => {
+                if (*start_hour > 23){
+                    return Err("Hour window rule `start_hour` cannot be higher than 23.".to_string());
+                }
+                if (*end_hour > 24){
+                    return Err("Hour window rule `end_hour` cannot be higher than 24.".to_string());
+                }
+                if (start_hour == end_hour){
+                    return Err("Hour window rule `start_hour` and `end_hour` cannot be equal.".to_string());
+                }
+            },
+            CouponRule::DateRange { start_date, end_date } => {
+                if (start_date > end_date){
+                    return Err("Date range rule `start_date` cannot be after `end_date`.".to_string());
+                }
+            },
+        }
+        return Ok(());
+    }
+
+    /// Evaluate the rule at the given moment, returning the reason when it is not satisfied.
+    pub fn check(&self, now: NaiveDateTime) -> Result<(), InvalidReason> {
+        let satisfied = match self {
+            CouponRule::Weekdays { weekdays } => weekdays.contains(&now.weekday()),
+            CouponRule::HourWindow { start_hour, end_hour } => {
+                let hour = now.hour();
+                if (start_hour < end_hour) {
+                    hour >= *start_hour && hour < *end_hour
+                } else {
+                    hour >= *start_hour || hour < *end_hour
+                }
+            },
+            CouponRule::DateRange { start_date, end_date } => now >= *start_date && now <= *end_date,
+        };
+
+        if (satisfied){
+            return Ok(());
+        }
+
+        return Err(match self {
+            CouponRule::Weekdays { .. } => InvalidReason::InvalidWeekday,
+            CouponRule::HourWindow { .. } => InvalidReason::InvalidHour,
+            CouponRule::DateRange { .. } => InvalidReason::OutsideDateRange,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CouponRule, InvalidReason};
+    use chrono::{NaiveDateTime, Weekday};
+    use claim::{assert_err, assert_ok};
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        return NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    }
+
+    #[test]
+    fn weekdays_rule_is_checked(){
+        let rule = CouponRule::Weekdays { weekdays: vec![Weekday::Fri] };
+        // 2023-01-06 is a Friday
+        assert_ok!(rule.check(datetime("2023-01-06 12:00:00")));
+        assert_eq!(rule.check(datetime("2023-01-07 12:00:00")), Err(InvalidReason::InvalidWeekday));
+    }
+
+    #[test]
+    fn hour_window_rule_is_checked(){
+        let rule = CouponRule::HourWindow { start_hour: 9, end_hour: 18 };
+        assert_ok!(rule.check(datetime("2023-01-06 09:00:00")));
+        assert_ok!(rule.check(datetime("2023-01-06 17:59:59")));
+        assert_eq!(rule.check(datetime("2023-01-06 18:00:00")), Err(InvalidReason::InvalidHour));
+    }
+
+    #[test]
+    fn hour_window_rule_wraps_around_midnight(){
+        let rule = CouponRule::HourWindow { start_hour: 22, end_hour: 2 };
+        assert_ok!(rule.check(datetime("2023-01-06 23:00:00")));
+        assert_ok!(rule.check(datetime("2023-01-06 01:00:00")));
+        assert_err!(rule.check(datetime("2023-01-06 12:00:00")));
+    }
+
+    #[test]
+    fn date_range_rule_is_checked(){
+        let rule = CouponRule::DateRange {
+            start_date: datetime("2023-01-01 00:00:00"),
+            end_date: datetime("2023-01-31 23:59:59"),
+        };
+        assert_ok!(rule.check(datetime("2023-01-15 00:00:00")));
+        assert_eq!(rule.check(datetime("2023-02-01 00:00:00")), Err(InvalidReason::OutsideDateRange));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected(){
+        assert_err!(CouponRule::Weekdays { weekdays: vec![] }.validate());
+        assert_err!(CouponRule::HourWindow { start_hour: 24, end_hour: 2 }.validate());
+        assert_err!(CouponRule::HourWindow { start_hour: 2, end_hour: 25 }.validate());
+        assert_err!(CouponRule::HourWindow { start_hour: 2, end_hour: 2 }.validate());
+        assert_err!(CouponRule::DateRange {
+            start_date: datetime("2023-02-01 00:00:00"),
+            end_date: datetime("2023-01-01 00:00:00"),
+        }.validate());
+        assert_ok!(CouponRule::HourWindow { start_hour: 0, end_hour: 24 }.validate());
+    }
+
+    #[test]
+    fn rules_are_deserialized_from_tagged_json(){
+        let rule: CouponRule = serde_json::from_str(r#"{"type": "weekdays", "weekdays": ["Sat", "Sun"]}"#).unwrap();
+        assert_eq!(rule, CouponRule::Weekdays { weekdays: vec![Weekday::Sat, Weekday::Sun] });
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Serialize, Deserialize};
+
+
+/// Machine-readable reason for a coupon not being valid for use.
+/// The serialized codes are part of the API, do not rename them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidReason {
+    Inactive,
+    NotYetValid,
+    Expired,
+    NoExpirationDate,
+    InvalidWeekday,
+    InvalidHour,
+    OutsideDateRange,
+    UsageLimitReached,
+    CustomerRequired,
+    CustomerUsageLimitReached,
+    OrderAmountRequired,
+    BelowMinOrderAmount,
+    ProductRequired,
+    ProductNotEligible,
+    CampaignInactive,
+    CampaignNotStarted,
+    CampaignEnded,
+}
+
+impl InvalidReason {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            InvalidReason::Inactive => "inactive",
+            InvalidReason::NotYetValid => "not_yet_valid",
+            InvalidReason::Expired => "expired",
+            InvalidReason::NoExpirationDate => "no_expiration_date",
+            InvalidReason::InvalidWeekday => "invalid_weekday",
+            InvalidReason::InvalidHour => "invalid_hour",
+            InvalidReason::OutsideDateRange => "outside_date_range",
+            InvalidReason::UsageLimitReached => "usage_limit_reached",
+            InvalidReason::CustomerRequired => "customer_required",
+            InvalidReason::CustomerUsageLimitReached => "customer_usage_limit_reached",
+            InvalidReason::OrderAmountRequired => "order_amount_required",
+            InvalidReason::BelowMinOrderAmount => "below_min_order_amount",
+            InvalidReason::ProductRequired => "product_required",
+            InvalidReason::ProductNotEligible => "product_not_eligible",
+            InvalidReason::CampaignInactive => "campaign_inactive",
+            InvalidReason::CampaignNotStarted => "campaign_not_started",
+            InvalidReason::CampaignEnded => "campaign_ended",
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CouponVerification {
+    pub valid: bool,
+    pub reasons: Vec<InvalidReason>,
+}
+
+impl From<Vec<InvalidReason>> for CouponVerification {
+    fn from(reasons: Vec<InvalidReason>) -> Self {
+        return Self {
+            valid: reasons.is_empty(),
+            reasons,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InvalidReason, CouponVerification};
+
+    #[test]
+    fn reasons_are_serialized_as_their_code(){
+        let reasons = vec![
+            InvalidReason::Inactive,
+            InvalidReason::NotYetValid,
+            InvalidReason::Expired,
+            InvalidReason::NoExpirationDate,
+            InvalidReason::InvalidWeekday,
+            InvalidReason::InvalidHour,
+            InvalidReason::OutsideDateRange,
+            InvalidReason::UsageLimitReached,
+            InvalidReason::CustomerRequired,
+            InvalidReason::CustomerUsageLimitReached,
+            InvalidReason::OrderAmountRequired,
+            InvalidReason::BelowMinOrderAmount,
+            InvalidReason::ProductRequired,
+            InvalidReason::ProductNotEligible,
+            InvalidReason::CampaignInactive,
+            InvalidReason::CampaignNotStarted,
+            InvalidReason::CampaignEnded,
+        ];
+        for reason in reasons {
+            assert_eq!(serde_json::to_value(reason).unwrap(), reason.as_str());
+        }
+    }
+
+    #[test]
+    fn verification_is_valid_only_without_reasons(){
+        assert!(CouponVerification::from(vec![]).valid);
+        assert!(!CouponVerification::from(vec![InvalidReason::Expired]).valid);
+    }
+}
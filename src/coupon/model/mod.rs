@@ -1,5 +1,25 @@
+pub mod campaign;
 pub mod coupon;
+pub mod coupon_audit;
+pub mod coupon_batch;
 pub mod coupon_discount;
+pub mod coupon_etag;
+pub mod coupon_list;
+pub mod coupon_quote;
+pub mod coupon_redemption;
+pub mod coupon_rule;
+pub mod coupon_verification;
+pub mod product;
 
+pub use self::campaign::*;
 pub use self::coupon::*;
-pub use self::coupon_discount::*;
\ No newline at end of file
+pub use self::coupon_audit::*;
+pub use self::coupon_batch::*;
+pub use self::coupon_discount::*;
+pub use self::coupon_etag::*;
+pub use self::coupon_list::*;
+pub use self::coupon_quote::*;
+pub use self::coupon_redemption::*;
+pub use self::coupon_rule::*;
+pub use self::coupon_verification::*;
+pub use self::product::*;
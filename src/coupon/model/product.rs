@@ -0,0 +1,70 @@
+use super::FieldError;
+use serde::{Serialize, Deserialize};
+use sqlx::types::chrono::{NaiveDateTime};
+
+
+/// An OldBot license plan, coupons can be restricted to specific products.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Product {
+    pub id: i32,
+    pub code: String,
+    pub name: String,
+    pub date_created: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProductInsert {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProductInsertRequest {
+    pub code: String,
+    pub name: String,
+}
+
+impl TryFrom<ProductInsertRequest> for ProductInsert {
+    type Error = FieldError;
+    fn try_from(product: ProductInsertRequest) -> Result<Self, Self::Error> {
+        let code = product.code.trim().to_string();
+        let name = product.name.trim().to_string();
+        if (code.is_empty() || code.len() > 255){
+            return Err(FieldError::new("code", "Product code must have between 1 and 255 characters."));
+        }
+        // the code is used as a path param, so it cannot be mistaken for an id
+        if (code.parse::<i32>().is_ok() || code.contains('/')){
+            return Err(FieldError::new("code", format!("Product code `{}` cannot be a number or contain `/`.", code)));
+        }
+        if (name.is_empty() || name.len() > 255){
+            return Err(FieldError::new("name", "Product name must have between 1 and 255 characters."));
+        }
+        return Ok( Self { code, name } );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProductInsert, ProductInsertRequest};
+    use claim::{assert_err, assert_ok};
+
+    fn request(code: &str, name: &str) -> ProductInsertRequest {
+        return ProductInsertRequest { code: code.to_string(), name: name.to_string() };
+    }
+
+    #[test]
+    fn valid_product_is_accepted(){
+        let product = ProductInsert::try_from(request(" oldbot-pro ", "OldBot Pro")).unwrap();
+        assert_eq!(product.code, "oldbot-pro");
+        assert_ok!(ProductInsert::try_from(request("oldbot-lite", "OldBot Lite")));
+    }
+
+    #[test]
+    fn invalid_product_is_rejected(){
+        assert_err!(ProductInsert::try_from(request("", "OldBot Pro")));
+        assert_err!(ProductInsert::try_from(request("oldbot-pro", " ")));
+        assert_err!(ProductInsert::try_from(request("123", "OldBot Pro")));
+        assert_err!(ProductInsert::try_from(request("oldbot/pro", "OldBot Pro")));
+        assert_err!(ProductInsert::try_from(request(&"a".repeat(256), "OldBot Pro")));
+    }
+}
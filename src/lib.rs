@@ -6,5 +6,6 @@
 pub mod authentication;
 pub mod coupon;
 pub mod configuration;
+pub mod problem;
 pub mod startup;
 pub mod telemetry;
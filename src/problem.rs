@@ -0,0 +1,287 @@
+use crate::coupon::{CouponError, FieldError};
+use actix_web::{
+    HttpMessage,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::{InternalError, JsonPayloadError, PathError, QueryPayloadError},
+    http::{StatusCode, header},
+};
+use serde::{Serialize, Deserialize};
+use tracing_actix_web::RequestId;
+
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Body of every error response, following RFC 7807 (`application/problem+json`).
+/// e.g. `{"type": "about:blank", "title": "Not Found", "status": 404, "code": "not_found", "message": "Coupon with id `1` not found.", "request_id": "..."}`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    // stable identifier of the error, clients should rely on it instead of the `message`
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, code: &str, message: String) -> Self {
+        return Self {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            message,
+            request_id: None,
+            errors: Vec::new(),
+        };
+    }
+
+    fn from_error(status: StatusCode, error: &actix_web::Error) -> Self {
+        // the details of internal errors are only logged, never returned to the client
+        if (status.is_server_error()){
+            return Self::new(status, "internal_error", "An unexpected error occurred.".to_string());
+        }
+
+        if let Some(error) = error.as_error::<CouponError>() {
+            let code = match error {
+                CouponError::AlreadyExistsError(_) => "already_exists",
+                CouponError::NotFoundError(_) => "not_found",
+                CouponError::ValidationError(_) => "validation_error",
+                CouponError::FieldValidationError(_) => "validation_error",
+                CouponError::InvalidCouponError(_) => "invalid_coupon",
+                CouponError::ConflictError(_) => "conflict",
+                CouponError::PreconditionFailedError(_) => "precondition_failed",
+                CouponError::ForbiddenError(_) => "forbidden",
+                CouponError::InternalError(_) | CouponError::UnexpectedError(_) => "internal_error",
+            };
+            let mut problem = Self::new(status, code, error.to_string());
+            if let CouponError::FieldValidationError(field_error) = error {
+                problem.errors.push(field_error.clone());
+            }
+            return problem;
+        }
+        if let Some(error) = error.as_error::<JsonPayloadError>() {
+            let mut problem = Self::new(status, "invalid_body", error.to_string());
+            if let JsonPayloadError::Deserialize(error) = error {
+                problem.errors.extend(field_error_from_serde(&error.to_string()));
+            }
+            return problem;
+        }
+        if let Some(error) = error.as_error::<QueryPayloadError>() {
+            let mut problem = Self::new(status, "invalid_query", error.to_string());
+            problem.errors.extend(field_error_from_serde(&error.to_string()));
+            return problem;
+        }
+        if let Some(error) = error.as_error::<PathError>() {
+            return Self::new(status, "invalid_path", error.to_string());
+        }
+
+        return Self::new(status, &status_code(status), error.to_string());
+    }
+}
+
+impl std::fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}", self.message);
+    }
+}
+
+/// Read the request id set by `TracingLogger`, it must wrap the middleware calling this.
+pub fn request_id(request: &ServiceRequest) -> Option<String> {
+    return request.extensions().get::<RequestId>().map(|request_id| request_id.to_string());
+}
+
+/// Convert every error (status >= 400) to a `ProblemDetails` body with the request id.
+/// Registered with `wrap_fn` in `startup.rs`, so it also handles the errors of the extractors
+/// (e.g. `web::Json`) and of the authentication middleware.
+pub fn problem_response<B>(result: Result<ServiceResponse<B>, actix_web::Error>, request_id: Option<String>) -> Result<ServiceResponse<BoxBody>, actix_web::Error>
+where
+    B: MessageBody + 'static,
+{
+    let response = match result {
+        Ok(response) => response,
+        Err(error) => {
+            // errors of the middlewares (e.g. authentication), the request was consumed so the error is kept
+            // with its response and headers (e.g. `WWW-Authenticate`), only the body is replaced
+            let status = error.as_response_error().status_code();
+            let mut problem = ProblemDetails::from_error(status, &error);
+            problem.request_id = request_id;
+            let body = serde_json::to_vec(&problem)
+                .map_err(|error| actix_web::error::ErrorInternalServerError(format!("Failed to serialize the error response: {}.", error)))?;
+            let mut response = error.error_response().set_body(BoxBody::new(body));
+            response.headers_mut().insert(header::CONTENT_TYPE, header::HeaderValue::from_static(PROBLEM_JSON));
+            return Err(InternalError::from_response(problem, response).into());
+        },
+    };
+    let status = response.status();
+    if (!status.is_client_error() && !status.is_server_error()){
+        return Ok(response.map_into_boxed_body());
+    }
+
+    let mut problem = match response.response().error() {
+        Some(error) => ProblemDetails::from_error(status, error),
+        // e.g. 404 for a route that does not exist
+        None => ProblemDetails::new(status, &status_code(status), status.canonical_reason().unwrap_or_default().to_string()),
+    };
+    problem.request_id = request_id;
+    let body = serde_json::to_vec(&problem)
+        .map_err(|error| actix_web::error::ErrorInternalServerError(format!("Failed to serialize the error response: {}.", error)))?;
+
+    // the original error and headers (e.g. `WWW-Authenticate`) are kept, only the body is replaced
+    return Ok(response.map_body(|head, _| {
+        head.headers_mut().insert(header::CONTENT_TYPE, header::HeaderValue::from_static(PROBLEM_JSON));
+        return BoxBody::new(body);
+    }));
+}
+
+// e.g. `Unauthorized` => `unauthorized`, `Too Many Requests` => `too_many_requests`
+fn status_code(status: StatusCode) -> String {
+    return status.canonical_reason()
+        .unwrap_or("error")
+        .to_lowercase()
+        .replace(' ', "_");
+}
+
+// serde only names the field for missing and unknown fields, e.g. "missing field `code` at line 1 column 20"
+fn field_error_from_serde(message: &str) -> Option<FieldError> {
+    for prefix in ["missing field `", "unknown field `"] {
+        if let Some(start) = message.find(prefix) {
+            let field = &message[start + prefix.len()..];
+            if let Some(end) = field.find('`') {
+                return Some(FieldError::new(&field[..end], message));
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field_error_from_serde, problem_response, request_id, status_code, ProblemDetails, PROBLEM_JSON};
+    use crate::coupon::{CouponError, CouponInsertRequest, FieldError};
+    use actix_web::{App, HttpResponse, dev::{Service, ServiceResponse}, error::ErrorUnauthorized, http::{StatusCode, header}, web};
+    use actix_web_httpauth::{extractors::AuthenticationError, headers::www_authenticate::bearer::Bearer};
+    use actix_web::test::{init_service, read_body_json, TestRequest};
+    use tracing_actix_web::TracingLogger;
+
+    async fn field_error() -> Result<HttpResponse, CouponError> {
+        return Err(CouponError::FieldValidationError(FieldError::new("discount", "Discount cannot be higher than 90.")));
+    }
+
+    async fn json_body(_: web::Json<CouponInsertRequest>) -> HttpResponse {
+        return HttpResponse::Ok().finish();
+    }
+
+    async fn call(uri: &str, body: serde_json::Value) -> (StatusCode, header::HeaderMap, ProblemDetails) {
+        let app = init_service(
+            App::new()
+                .wrap_fn(|request, service| {
+                    let request_id = request_id(&request);
+                    let response = service.call(request);
+                    async move { return problem_response(response.await, request_id); }
+                })
+                .wrap(TracingLogger::default())
+                .service(
+                    web::scope("/middleware_error")
+                        .route("", web::get().to(HttpResponse::Ok))
+                        .wrap_fn(|_, _| async { return Err::<ServiceResponse, _>(ErrorUnauthorized("Bearer token is invalid or has expired.")); })
+                )
+                .service(
+                    web::scope("/authentication_error")
+                        .route("", web::get().to(HttpResponse::Ok))
+                        .wrap_fn(|_, _| async { return Err::<ServiceResponse, _>(AuthenticationError::new(Bearer::default()).into()); })
+                )
+                .route("/field_error", web::get().to(field_error))
+                .route("/json", web::post().to(json_body))
+        ).await;
+        let request = if (body.is_null()) {
+            TestRequest::get().uri(uri).to_request()
+        } else {
+            TestRequest::post().uri(uri).set_json(body).to_request()
+        };
+
+        // the errors of the middlewares are not converted to a response by the test service
+        let response = match app.call(request).await {
+            Ok(response) => response.map_into_boxed_body(),
+            Err(error) => ServiceResponse::new(TestRequest::default().to_http_request(), error.error_response()),
+        };
+        let status = response.status();
+        let headers = response.headers().clone();
+        return (status, headers, read_body_json(response).await);
+    }
+
+    #[actix_web::test]
+    async fn coupon_errors_have_the_field_details(){
+        let (status, headers, problem) = call("/field_error", serde_json::Value::Null).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.code, "validation_error");
+        assert_eq!(problem.message, "Discount cannot be higher than 90.");
+        assert_eq!(problem.errors, vec![FieldError::new("discount", "Discount cannot be higher than 90.")]);
+        assert!(problem.request_id.is_some());
+    }
+
+    #[actix_web::test]
+    async fn json_extractor_errors_are_converted(){
+        let (status, _, problem) = call("/json", serde_json::json!({"discount": 10})).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem.code, "invalid_body");
+        assert_eq!(problem.errors[0].field, "code");
+    }
+
+    #[actix_web::test]
+    async fn middleware_errors_are_converted(){
+        let (status, headers, problem) = call("/middleware_error", serde_json::Value::Null).await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+        assert_eq!(problem.code, "unauthorized");
+        assert_eq!(problem.message, "Bearer token is invalid or has expired.");
+        assert!(problem.request_id.is_some());
+    }
+
+    #[actix_web::test]
+    async fn authentication_errors_keep_their_headers(){
+        let (status, headers, problem) = call("/authentication_error", serde_json::Value::Null).await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+        assert!(headers.get(header::WWW_AUTHENTICATE).unwrap().to_str().unwrap().starts_with("Bearer"));
+        assert_eq!(problem.code, "unauthorized");
+    }
+
+    #[actix_web::test]
+    async fn unknown_routes_are_converted(){
+        let (status, _, problem) = call("/unknown", serde_json::Value::Null).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(problem.code, "not_found");
+        assert_eq!(problem.title, "Not Found");
+    }
+
+    #[test]
+    fn status_code_is_snake_case(){
+        assert_eq!(status_code(StatusCode::UNAUTHORIZED), "unauthorized");
+        assert_eq!(status_code(StatusCode::TOO_MANY_REQUESTS), "too_many_requests");
+        assert_eq!(status_code(StatusCode::UNPROCESSABLE_ENTITY), "unprocessable_entity");
+    }
+
+    #[test]
+    fn field_is_read_from_serde_errors(){
+        let error = field_error_from_serde("Json deserialize error: missing field `code` at line 1 column 20").unwrap();
+        assert_eq!(error.field, "code");
+
+        let error = field_error_from_serde("unknown field `discont`, expected one of `code`, `discount`").unwrap();
+        assert_eq!(error.field, "discont");
+
+        assert!(field_error_from_serde("invalid type: string \"ten\", expected i32 at line 1 column 15").is_none());
+    }
+}
@@ -1,15 +1,19 @@
 use crate::{
-    configuration::{DatabaseSettings, Settings},
-    authentication::{validator, authenticate},
+    configuration::{AdminApiKey, DatabaseSettings, SessionTtl, Settings},
+    problem::{problem_response, request_id},
+    authentication::{AuthThrottle, SessionTokens, validator, authorize_role, authenticate, logout, refresh_session, get_session, get_api_keys, add_api_key, delete_api_key},
     coupon::{
-        health_check, get_coupon, get_all_coupons, add_coupon, update_coupon,
-        delete_coupon, verify_coupon
+        CouponCache, health_check, get_coupon, get_all_coupons, add_coupon, update_coupon, patch_coupon,
+        delete_coupon, restore_coupon, get_coupon_history, verify_coupon, redeem_coupon, quote_coupon, add_coupon_batch,
+        get_products, get_product, add_product, delete_product,
+        get_coupon_products, add_coupon_product, delete_coupon_product,
+        get_campaigns, get_campaign, add_campaign, update_campaign, delete_campaign,
     },
 };
 use actix_web::{
     web,
     App, HttpServer,
-    dev::Server,
+    dev::{Server, Service},
     web::{Data, scope},
 };
 use secrecy::ExposeSecret;
@@ -27,19 +31,43 @@ pub fn run(listener: TcpListener, db_pool: MySqlPool, configuration: Settings) -
     let db_pool = Data::new(db_pool);
     let base_url = Data::new(ApplicationBaseUrl(configuration.application.base_url));
     let api_key = Data::new(configuration.application.api_key);
+    let admin_api_key = Data::new(AdminApiKey(configuration.application.admin_api_key));
     let redis = redis::Client::open(configuration.redis_uri.expose_secret().to_string())
         .map_err(|e| anyhow::anyhow!(format!("Failed initialize redis client: {}.", e)))
         .unwrap();
+    let coupon_cache = Data::new(CouponCache::new(redis.clone(), configuration.application.coupon_cache_ttl));
+    let session_tokens = SessionTokens::new(configuration.application.session_mode, configuration.application.session_signing_key)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let session_tokens = Data::new(session_tokens);
+    if (configuration.application.session_ttl == 0){
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "`session_ttl` must be higher than 0."));
+    }
+    let session_ttl = Data::new(SessionTtl(configuration.application.session_ttl));
+    let auth_throttle = Data::new(AuthThrottle::new(redis.clone(), configuration.application.auth_throttle));
 
     let server = HttpServer::new(move || {
         App::new()
+            // every error response (including the extractors and auth ones) gets a JSON body,
+            // it must be registered before `TracingLogger` to read the request id
+            .wrap_fn(|request, service| {
+                let request_id = request_id(&request);
+                let response = service.call(request);
+                async move {
+                    return problem_response(response.await, request_id);
+                }
+            })
             // TracingLogger instead of default actix_web logger to return with request_id (and other information aswell)
             .wrap(TracingLogger::default())
 
             .app_data(db_pool.clone())
             .app_data(base_url.clone())
             .app_data(api_key.clone())
+            .app_data(admin_api_key.clone())
             .app_data(web::Data::new(redis.clone()))
+            .app_data(coupon_cache.clone())
+            .app_data(session_tokens.clone())
+            .app_data(session_ttl.clone())
+            .app_data(auth_throttle.clone())
 
             /*
                 all access routes (not authenticated)
@@ -59,9 +87,54 @@ pub fn run(listener: TcpListener, db_pool: MySqlPool, configuration: Settings) -
                     .service(get_all_coupons)
                     .service(get_coupon)
                     .service(add_coupon)
+                    .service(add_coupon_batch)
                     .service(update_coupon)
+                    .service(patch_coupon)
                     .service(delete_coupon)
+                    .service(restore_coupon)
                     .service(verify_coupon)
+                    .service(get_coupon_history)
+                    .service(redeem_coupon)
+                    .service(quote_coupon)
+                    .service(get_coupon_products)
+                    .service(add_coupon_product)
+                    .service(delete_coupon_product)
+                    // the last `wrap` runs first, the role is checked after the `validator`
+                    .wrap_fn(authorize_role)
+                    .wrap(api_key_auth.clone())
+                )
+            .service(
+                scope("/product")
+                    .service(get_products)
+                    .service(get_product)
+                    .service(add_product)
+                    .service(delete_product)
+                    .wrap_fn(authorize_role)
+                    .wrap(api_key_auth.clone())
+                )
+            .service(
+                scope("/campaign")
+                    .service(get_campaigns)
+                    .service(get_campaign)
+                    .service(add_campaign)
+                    .service(update_campaign)
+                    .service(delete_campaign)
+                    .wrap_fn(authorize_role)
+                    .wrap(api_key_auth.clone())
+                )
+            .service(
+                // `POST /auth` is matched first by the `authenticate` service
+                scope("/auth")
+                    .service(logout)
+                    .service(refresh_session)
+                    .service(get_session)
+                    .wrap(api_key_auth.clone())
+                )
+            .service(
+                scope("/api-key")
+                    .service(get_api_keys)
+                    .service(add_api_key)
+                    .service(delete_api_key)
                     .wrap(api_key_auth.clone())
                 )
     })
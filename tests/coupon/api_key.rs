@@ -0,0 +1,131 @@
+use coupon_api::authentication::{ApiKeyResponse, ApiKeyScope};
+use reqwest::Method;
+use serde_json::json;
+
+use crate::helpers::{spawn_app, TestApp};
+
+
+#[tokio::test]
+async fn created_api_key_authenticates_with_its_scopes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (api_key, client) = app.create_api_key_client("checkout", json!(["coupon:verify", "coupon:redeem"])).await;
+
+    // Assert
+    assert_eq!(api_key.api_key.name, "checkout");
+    assert_eq!(api_key.api_key.scopes, vec![ApiKeyScope::CouponVerify, ApiKeyScope::CouponRedeem]);
+    assert_eq!(api_key.key.len(), 40);
+
+    // coupon not found, but the scope was accepted
+    let verify_response = client.get(&format!("{}/coupon/verify/TEST", &app.address)).send().await.unwrap();
+    assert_eq!(404, verify_response.status().as_u16());
+    let redeem_response = client.post(&format!("{}/coupon/TEST/redeem", &app.address)).send().await.unwrap();
+    assert_eq!(404, redeem_response.status().as_u16());
+
+    // missing scopes
+    let get_response = client.get(&format!("{}/coupon/TEST", &app.address)).send().await.unwrap();
+    assert_eq!(403, get_response.status().as_u16());
+    let post_response = client.post(&format!("{}/coupon", &app.address)).json(&json!({})).send().await.unwrap();
+    assert_eq!(403, post_response.status().as_u16());
+    let product_response = client.get(&format!("{}/product", &app.address)).send().await.unwrap();
+    assert_eq!(403, product_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn get_api_keys_returns_the_keys_without_the_key() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_api_key_client("reader", json!(["coupon:read"])).await;
+    app.create_api_key_client("checkout", json!(["coupon:verify"])).await;
+
+    // Act
+    let response = app.request_api_key(None, Method::GET, "", json!({})).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let api_keys: Vec<ApiKeyResponse> = serde_json::from_value(body.clone()).unwrap();
+    assert_eq!(api_keys.iter().map(|api_key| api_key.name.as_str()).collect::<Vec<&str>>(), vec!["checkout", "reader"]);
+    assert!(body[0].get("key").is_none() && body[0].get("key_hash").is_none());
+}
+
+#[tokio::test]
+async fn deleted_api_key_can_not_authenticate() {
+    // Arrange
+    let app = spawn_app().await;
+    let (api_key, _) = app.create_api_key_client("checkout", json!(["coupon:verify"])).await;
+
+    // Act
+    let response = app.request_api_key(None, Method::DELETE, &format!("/{}", api_key.api_key.id), json!({})).await;
+
+    // Assert
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(401, authenticate(&app, &api_key.key).await.status().as_u16());
+    assert_eq!(404, app.request_api_key(None, Method::DELETE, &format!("/{}", api_key.api_key.id), json!({})).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn post_api_key_returns_409_for_a_name_already_used() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_api_key_client("checkout", json!(["coupon:verify"])).await;
+
+    // Act
+    let response = app.request_api_key(None, Method::POST, "", json!({"name": "checkout", "scopes": ["coupon:read"]})).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn post_api_key_returns_4xx_for_invalid_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (json!({"name": "", "scopes": ["coupon:read"]}), "empty name"),
+        (json!({"name": "admin", "scopes": ["coupon:read"]}), "reserved name"),
+        (json!({"name": "checkout", "scopes": []}), "no scopes"),
+        (json!({"name": "checkout", "scopes": ["coupon:delete"]}), "unknown scope"),
+        (json!({"name": "checkout", "scopes": ["coupon:read"], "role": "owner"}), "unknown role"),
+        (json!({"name": "checkout", "scopes": ["coupon:read"], "tenant": ""}), "empty tenant"),
+        (json!({"name": "checkout", "scopes": ["coupon:read"], "expires_at": "2020-01-01T00:00:00"}), "expired"),
+    ];
+
+    for (body, error_message) in test_cases {
+        // Act
+        let response = app.request_api_key(None, Method::POST, "", body).await;
+
+        // Assert
+        assert!(
+            response.status().is_client_error(),
+            "The API did not fail with 4xx when the payload was {}.", error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn only_admin_sessions_can_manage_api_keys() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let get_response = app.request_api_key(Some(&app.api_client), Method::GET, "", json!({})).await;
+    let post_response = app.request_api_key(Some(&app.api_client), Method::POST, "", json!({"name": "checkout", "scopes": ["coupon:read"]})).await;
+    let delete_response = app.request_api_key(Some(&app.api_client), Method::DELETE, "/1", json!({})).await;
+
+    // Assert
+    assert_eq!(403, get_response.status().as_u16());
+    assert_eq!(403, post_response.status().as_u16());
+    assert_eq!(403, delete_response.status().as_u16());
+}
+
+async fn authenticate(app: &TestApp, api_key: &str) -> reqwest::Response {
+    return reqwest::Client::new()
+        .post(&format!("{}/auth", &app.address))
+        .json(&json!({"api_key": api_key}))
+        .send()
+        .await
+        .expect("Failed to perform POST request to `/auth`.");
+}
@@ -1,8 +1,13 @@
-use reqwest::header::HeaderMap;
-use secrecy::ExposeSecret;
+use chrono::Utc;
+use coupon_api::authentication::{ApiKeyScope, Role, SessionResponse};
+use coupon_api::configuration::{get_configuration, SessionMode};
+use redis::AsyncCommands;
+use reqwest::{Method, header::HeaderMap};
+use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
+use uuid::Uuid;
 
-use crate::helpers::{spawn_app};
+use crate::helpers::{spawn_app, spawn_app_with_configuration, TestApp};
 
 
 #[tokio::test]
@@ -37,6 +42,269 @@ async fn auth_works_and_returns_valid_bearer_token() {
     assert!(bearer.contains(":"));
 }
 
+#[tokio::test]
+async fn signed_session_token_authenticates_without_a_redis_session() {
+    // Arrange
+    let app = spawn_app_with_configuration(|c| {
+        c.application.session_mode = SessionMode::Signed;
+        c.application.session_signing_key = Some(Secret::new("0123456789abcdef0123456789abcdef".to_string()));
+    }).await;
+
+    // Act
+    let response = app.get_coupon("").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+
+    let bearer = get_bearer(&app.address, app.api_key.0.expose_secret()).await;
+    let (session_id, session_token) = bearer.split_once(':').unwrap();
+    assert!(!session_token.is_empty());
+
+    // the session is not stored in Redis
+    let redis = redis::Client::open(get_configuration().unwrap().redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = redis.get_async_connection().await.unwrap();
+    let stored_session: Option<String> = connection.get(session_id).await.unwrap();
+    assert!(stored_session.is_none());
+}
+
+#[tokio::test]
+async fn tampered_signed_session_token_is_rejected() {
+    // Arrange
+    let app = spawn_app_with_configuration(|c| {
+        c.application.session_mode = SessionMode::Signed;
+        c.application.session_signing_key = Some(Secret::new("0123456789abcdef0123456789abcdef".to_string()));
+    }).await;
+    let bearer = get_bearer(&app.address, app.api_key.0.expose_secret()).await;
+    let (session_id, session_token) = bearer.split_once(':').unwrap();
+    let (_, signature) = session_token.split_once('.').unwrap();
+    let claims = json!({"sid": session_id, "exp": 4102444800_i64, "api_key_name": "admin", "admin": true});
+    let tampered_token = format!("{}.{}", base64::encode(claims.to_string()), signature);
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(&format!("{}/coupon", &app.address))
+        .header("Authorization", format!("Bearer {}", base64::encode(format!("{}:{}", session_id, tampered_token))))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn logout_ends_the_session() {
+    logout_test_request(SessionMode::Redis).await;
+}
+
+#[tokio::test]
+async fn logout_ends_the_signed_session() {
+    logout_test_request(SessionMode::Signed).await;
+}
+
+async fn logout_test_request(session_mode: SessionMode) {
+    // Arrange
+    let app = spawn_app_with_configuration(|c| {
+        c.application.session_mode = session_mode;
+        c.application.session_signing_key = Some(Secret::new("0123456789abcdef0123456789abcdef".to_string()));
+    }).await;
+    let authorization = get_authorization(&app.address, app.api_key.0.expose_secret()).await;
+    let coupons_url = format!("{}/coupon", &app.address);
+    assert_eq!(request_with_authorization(Method::GET, &coupons_url, &authorization).await.status().as_u16(), 200);
+
+    // Act
+    let response = request_with_authorization(Method::POST, &format!("{}/auth/logout", &app.address), &authorization).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(request_with_authorization(Method::GET, &coupons_url, &authorization).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn refresh_rotates_the_session() {
+    // Arrange
+    let app = spawn_app().await;
+    let authorization = get_authorization(&app.address, app.api_key.0.expose_secret()).await;
+
+    // Act
+    let response = request_with_authorization(Method::POST, &format!("{}/auth/refresh", &app.address), &authorization).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let new_authorization: String = response.json().await.expect("Failed to get `/auth/refresh` response text.");
+    assert_ne!(new_authorization, authorization);
+
+    let coupons_url = format!("{}/coupon", &app.address);
+    assert_eq!(request_with_authorization(Method::GET, &coupons_url, &new_authorization).await.status().as_u16(), 200);
+    assert_eq!(request_with_authorization(Method::GET, &coupons_url, &authorization).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn refresh_is_rejected_for_a_deleted_api_key() {
+    // Arrange
+    let app = spawn_app().await;
+    let (api_key, _) = app.create_api_key_client("checkout", json!(["coupon:verify"])).await;
+    let authorization = get_authorization(&app.address, &api_key.key).await;
+    app.request_api_key(None, Method::DELETE, &format!("/{}", api_key.api_key.id), json!({})).await;
+
+    // Act
+    let response = request_with_authorization(Method::POST, &format!("{}/auth/refresh", &app.address), &authorization).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn get_session_returns_the_identity_and_expiration_of_the_session() {
+    // Arrange
+    let app = spawn_app_with_configuration(|c| c.application.session_ttl = 120).await;
+    let (session_id, _) = get_bearer(&app.address, app.api_key.0.expose_secret()).await.split_once(':')
+        .map(|(session_id, session_token)| (session_id.to_string(), session_token.to_string()))
+        .unwrap();
+
+    // Act
+    let response = request_with_authorization(
+        Method::GET,
+        &format!("{}/auth/session", &app.address),
+        &format!("Bearer {}", base64::encode(format!("{}:", session_id))),
+    ).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let session: SessionResponse = response.json().await.expect("Failed to parse SessionResponse from response.");
+    assert_eq!(session.session_id, session_id);
+    assert_eq!(session.api_key_name, "default");
+    assert_eq!(session.role, Role::Editor);
+    assert_eq!(session.tenant, "default");
+    assert_eq!(session.scopes, ApiKeyScope::all());
+
+    let seconds_to_expire = (session.expires_at.unwrap() - Utc::now().naive_utc()).num_seconds();
+    assert!(seconds_to_expire > 100 && seconds_to_expire <= 120);
+}
+
+#[tokio::test]
+async fn legacy_empty_redis_session_is_the_default_session() {
+    // Arrange
+    let app = spawn_app().await;
+    let session_id = Uuid::new_v4().to_string();
+    let redis = redis::Client::open(get_configuration().unwrap().redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = redis.get_async_connection().await.unwrap();
+    let _: () = connection.set_ex(&session_id, "", 120).await.unwrap();
+    let authorization = format!("Bearer {}", base64::encode(format!("{}:", session_id)));
+
+    // Act
+    let session_response = request_with_authorization(Method::GET, &format!("{}/auth/session", &app.address), &authorization).await;
+    let coupon_response = request_with_authorization(Method::GET, &format!("{}/coupon", &app.address), &authorization).await;
+
+    // Assert
+    assert_eq!(session_response.status().as_u16(), 200);
+    let session: SessionResponse = session_response.json().await.expect("Failed to parse SessionResponse from response.");
+    assert_eq!(session.role, Role::Editor);
+    assert_eq!(session.tenant, "default");
+    assert_eq!(session.scopes, ApiKeyScope::all());
+    assert_eq!(coupon_response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn corrupt_redis_session_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let session_id = Uuid::new_v4().to_string();
+    let redis = redis::Client::open(get_configuration().unwrap().redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = redis.get_async_connection().await.unwrap();
+    let _: () = connection.set_ex(&session_id, "{not a session", 120).await.unwrap();
+
+    // Act
+    let response = request_with_authorization(
+        Method::GET,
+        &format!("{}/coupon", &app.address),
+        &format!("Bearer {}", base64::encode(format!("{}:", session_id))),
+    ).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn auth_locks_out_the_client_after_failed_attempts() {
+    // Arrange
+    let app = spawn_throttled_app().await;
+    let client_ip = Uuid::new_v4().to_string();
+
+    // Act 1
+    let first_response = post_auth_from(&app.address, &client_ip, "invalid").await;
+    let second_response = post_auth_from(&app.address, &client_ip, "invalid").await;
+
+    // Assert 1
+    assert_eq!(first_response.status().as_u16(), 401);
+    assert_eq!(second_response.status().as_u16(), 429);
+    assert_eq!(second_response.headers().get("Retry-After").unwrap(), "30");
+
+    // Act 2
+    let locked_out_response = post_auth_from(&app.address, &client_ip, app.api_key.0.expose_secret()).await;
+    let other_client_response = post_auth_from(&app.address, &Uuid::new_v4().to_string(), app.api_key.0.expose_secret()).await;
+
+    // Assert 2
+    // the valid key is rejected too while the client is locked out
+    assert_eq!(locked_out_response.status().as_u16(), 429);
+    assert!(locked_out_response.headers().get("Retry-After").is_some());
+    assert_eq!(other_client_response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn auth_forgets_the_failed_attempts_after_a_successful_attempt() {
+    // Arrange
+    let app = spawn_throttled_app().await;
+    let client_ip = Uuid::new_v4().to_string();
+
+    // Act
+    let failed_response = post_auth_from(&app.address, &client_ip, "invalid").await;
+    let response = post_auth_from(&app.address, &client_ip, app.api_key.0.expose_secret()).await;
+    let second_failed_response = post_auth_from(&app.address, &client_ip, "invalid").await;
+
+    // Assert
+    assert_eq!(failed_response.status().as_u16(), 401);
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(second_failed_response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn auth_lockout_ignores_the_addresses_sent_by_the_client() {
+    // Arrange
+    let app = spawn_throttled_app().await;
+    let client_ip = Uuid::new_v4().to_string();
+
+    // Act
+    // the client sends a new address on every attempt, the proxy appends the same one
+    let first_response = post_auth_from(&app.address, &format!("{}, {}", Uuid::new_v4(), client_ip), "invalid").await;
+    let second_response = post_auth_from(&app.address, &format!("{}, {}", Uuid::new_v4(), client_ip), "invalid").await;
+    let spoofed_response = post_auth_from(&app.address, &format!("{}, {}", Uuid::new_v4(), client_ip), app.api_key.0.expose_secret()).await;
+
+    // Assert
+    assert_eq!(first_response.status().as_u16(), 401);
+    assert_eq!(second_response.status().as_u16(), 429);
+    assert_eq!(spoofed_response.status().as_u16(), 429);
+}
+
+// The client IP is read from `X-Forwarded-For`, so every test has its own failed attempts
+async fn spawn_throttled_app() -> TestApp {
+    return spawn_app_with_configuration(|c| {
+        c.application.auth_throttle.max_failed_attempts = 2;
+        c.application.auth_throttle.lockout_seconds = 30;
+        c.application.auth_throttle.trust_forwarded_for = true;
+    }).await;
+}
+
+async fn post_auth_from(address: &str, client_ip: &str, api_key: &str) -> reqwest::Response {
+    return reqwest::Client::new()
+        .post(&format!("{}/auth", address))
+        .header("X-Forwarded-For", client_ip)
+        .json(&json!({"api_key": api_key}))
+        .send()
+        .await
+        .expect("Failed to perform POST request to `/auth`.");
+}
+
 #[tokio::test]
 async fn request_missing_authorization_header_is_rejected() {
     // Arrange
@@ -45,7 +313,12 @@ async fn request_missing_authorization_header_is_rejected() {
 
     // Act
     for (method, endpoint) in get_test_endpoints() {
-        authorization_test_request(401, &client, &app.address, method, endpoint, "").await;
+        let response = authorization_test_request(401, &client, &app.address, method, endpoint, "").await;
+
+        // Assert
+        // the problem body does not replace the `WWW-Authenticate` header of the error
+        assert!(response.headers().get("WWW-Authenticate").is_some(), "The `{}` request to endpoint `{}` has no `WWW-Authenticate` header.", method, endpoint);
+        assert_eq!(response.headers().get("Content-Type").unwrap(), "application/problem+json");
     }
 }
 
@@ -82,6 +355,36 @@ async fn request_with_invalid_authorization_header_is_rejected() {
     }
 }
 
+// Get the `Authorization` header value returned by `/auth`, e.g. `Bearer <base64>`
+async fn get_authorization(address: &str, api_key: &str) -> String {
+    return reqwest::Client::new()
+        .post(&format!("{}/auth", address))
+        .json(&json!({"api_key": api_key}))
+        .send()
+        .await
+        .expect("Failed to perform POST request to `/auth`.")
+        .json()
+        .await
+        .expect("Failed to get `/auth` response text.");
+}
+
+// Get the decoded bearer token, `<session_id>:<session_token>`
+async fn get_bearer(address: &str, api_key: &str) -> String {
+    let response = get_authorization(address, api_key).await;
+    let decoded = base64::decode(response.replace("Bearer ", ""))
+        .expect("Failed to decode bearer base64");
+    return String::from_utf8(decoded).expect("Failed to parse decoded to string.");
+}
+
+async fn request_with_authorization(method: Method, url: &str, authorization: &str) -> reqwest::Response {
+    return reqwest::Client::new()
+        .request(method.clone(), url)
+        .header("Authorization", authorization)
+        .send()
+        .await
+        .expect(format!("Failed to perform {} request", method.to_string()).as_str());
+}
+
 async fn authorization_test_request(expected_status: u16, client: &reqwest::Client, address: &str, method: &str, endpoint: &str, test_identifier: &str) -> reqwest::Response {
     let response: reqwest::Response;
     match method {
@@ -114,8 +417,14 @@ fn get_test_endpoints() -> Vec<(&'static str, &'static str)> {
         ("get", "/"),
         ("get", "/all"),
         ("post", "/"),
+        ("post", "/batch"),
         ("put", "/"),
         ("delete", "/id"),
         ("delete", "/code"),
+        ("post", "/id/redeem"),
+        ("post", "/id/quote"),
+        ("get", "/id/products"),
+        ("put", "/id/products/product"),
+        ("delete", "/id/products/product"),
     ];
 }
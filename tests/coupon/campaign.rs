@@ -0,0 +1,230 @@
+use crate::helpers::{spawn_app, TestApp};
+use coupon_api::coupon::{CouponResponse, InvalidReason};
+use rand::distributions::{Alphanumeric, DistString};
+use serde_json::json;
+
+/**
+ * Campaign
+ */
+#[tokio::test]
+async fn post_campaign_persists_the_new_campaign() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = get_campaign_request_json();
+
+    // Act
+    let response = app.post_campaign(body.clone()).await;
+
+    // Assert
+    assert_eq!(201, response.status().as_u16());
+    let campaign = app.get_and_deserialize_campaign(response.json::<serde_json::Value>().await.unwrap()["id"].as_i64().unwrap() as i32).await;
+    assert_eq!(campaign.campaign.name, body["name"].as_str().unwrap());
+    assert_eq!(campaign.campaign.budget, Some(500000));
+    assert_eq!(campaign.campaign.currency, Some("BRL".to_string()));
+    assert!(campaign.coupons.is_empty());
+}
+
+#[tokio::test]
+async fn post_campaign_returns_409_conflict_if_campaign_already_exists() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = get_campaign_request_json();
+    app.post_campaign(body.clone()).await;
+
+    // Act
+    let response = app.post_campaign(body).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn post_campaign_returns_4xx_for_invalid_campaign_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        (json!({"active": true}), "missing `name`", 400),
+        (json!({"name": "Campaign", "active": true, "start_date": "2023-02-01T00:00:00", "end_date": "2023-01-01T00:00:00"}), "`start_date` after `end_date`", 422),
+        (json!({"name": "Campaign", "active": true, "budget": 1000}), "missing `currency` for `budget`", 422),
+        (json!({"name": "Campaign", "active": true, "budget": 1000, "currency": "XYZ"}), "invalid `currency`", 422),
+    ];
+
+    // Act
+    for (invalid_body, error_message, expected_code) in test_cases {
+        let response = app.post_campaign(invalid_body).await;
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_code,
+            "The API did not fail with `{}` when the payload was `{}`.",
+            expected_code, error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn get_campaign_returns_its_coupons_and_redemption_stats() {
+    // Arrange
+    let app = spawn_app().await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let coupon1 = post_coupon(&app, Some(campaign.campaign.id)).await;
+    let coupon2 = post_coupon(&app, Some(campaign.campaign.id)).await;
+    post_coupon(&app, None).await;
+
+    app.redeem_coupon_as_customer(coupon1.code.clone(), "customer1").await;
+    app.redeem_coupon_as_customer(coupon1.code.clone(), "customer2").await;
+    app.redeem_coupon_as_customer(coupon2.code.clone(), "customer1").await;
+
+    // Act
+    let campaign = app.get_and_deserialize_campaign(campaign.campaign.id).await;
+
+    // Assert
+    let mut codes: Vec<String> = campaign.coupons.iter().map(|coupon| coupon.code.clone()).collect();
+    codes.sort();
+    let mut expected_codes = vec![coupon1.code, coupon2.code];
+    expected_codes.sort();
+    assert_eq!(codes, expected_codes);
+
+    assert_eq!(campaign.stats.coupon_count, 2);
+    assert_eq!(campaign.stats.redeemed_coupon_count, 2);
+    assert_eq!(campaign.stats.redemption_count, 3);
+    assert_eq!(campaign.stats.customer_count, 2);
+}
+
+#[tokio::test]
+async fn get_campaign_returns_the_rules_of_its_coupons() {
+    // Arrange
+    let app = spawn_app().await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let mut body = get_coupon_request_json(Some(campaign.campaign.id));
+    body["rules"] = json!([{"type": "hour_window", "start_hour": 8, "end_hour": 18}]);
+    let coupon = app.post_and_deserialize_coupon(body).await;
+
+    // Act
+    let campaign = app.get_and_deserialize_campaign(campaign.campaign.id).await;
+
+    // Assert
+    assert_eq!(campaign.coupons.len(), 1);
+    assert_eq!(campaign.coupons[0].rules, coupon.rules);
+    assert_eq!(campaign.coupons[0].rules.len(), 1);
+}
+
+#[tokio::test]
+async fn get_campaign_returns_the_spent_budget() {
+    // Arrange
+    let app = spawn_app().await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let coupon = post_coupon(&app, Some(campaign.campaign.id)).await;
+
+    // 10% of the orders
+    app.redeem_coupon_with_query(coupon.code.clone(), "?amount=100.00&currency=BRL").await;
+    app.redeem_coupon_with_query(coupon.code.clone(), "?amount=50.50&currency=BRL").await;
+    // only the discounts in the currency of the campaign are spent, the redemptions without the order are not known
+    app.redeem_coupon_with_query(coupon.code.clone(), "?amount=100.00&currency=USD").await;
+    app.redeem_coupon(coupon.code.clone()).await;
+
+    // Act
+    let campaign = app.get_and_deserialize_campaign(campaign.campaign.id).await;
+
+    // Assert
+    assert_eq!(campaign.stats.redemption_count, 4);
+    assert_eq!(campaign.stats.spent_amount, 1505);
+    assert_eq!(campaign.remaining_budget, Some(500000 - 1505));
+}
+
+#[tokio::test]
+async fn deactivating_a_campaign_invalidates_its_coupons() {
+    // Arrange
+    let app = spawn_app().await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let coupon = post_coupon(&app, Some(campaign.campaign.id)).await;
+    let verification = app.verify_and_deserialize_coupon(format!("/{}", coupon.code).as_str()).await;
+    assert!(verification.valid);
+
+    // Act
+    let mut body = get_campaign_request_json();
+    body["name"] = json!(campaign.campaign.name);
+    body["active"] = json!(false);
+    let response = app.put_campaign(campaign.campaign.id, body).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Assert
+    let verification = app.verify_and_deserialize_coupon(format!("/{}", coupon.code).as_str()).await;
+    assert_eq!(verification.reasons, vec![InvalidReason::CampaignInactive]);
+    assert_eq!(409, app.redeem_coupon(coupon.code.clone()).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_the_campaign_period() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut body = get_campaign_request_json();
+    body["end_date"] = json!("2000-12-31T00:00:00");
+    let campaign = app.post_and_deserialize_campaign(body).await;
+    let coupon = post_coupon(&app, Some(campaign.campaign.id)).await;
+
+    // Act
+    let verification = app.verify_and_deserialize_coupon(format!("/{}", coupon.code).as_str()).await;
+
+    // Assert
+    assert_eq!(verification.reasons, vec![InvalidReason::CampaignEnded]);
+}
+
+#[tokio::test]
+async fn post_coupon_returns_422_for_unknown_campaign() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = get_coupon_request_json(Some(123456789));
+
+    // Act
+    let response = app.post_coupon(body, false).await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn delete_campaign_keeps_its_coupons() {
+    // Arrange
+    let app = spawn_app().await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let coupon = post_coupon(&app, Some(campaign.campaign.id)).await;
+
+    // Act
+    let response = app.delete_campaign(campaign.campaign.id).await;
+
+    // Assert
+    assert_eq!(204, response.status().as_u16());
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", coupon.id).as_str()).await;
+    assert_eq!(coupon.campaign_id, None);
+    assert_eq!(404, app.delete_campaign(campaign.campaign.id).await.status().as_u16());
+}
+
+/**
+ * Helper functions
+ */
+
+async fn post_coupon(app: &TestApp, campaign_id: Option<i32>) -> CouponResponse {
+    return app.post_and_deserialize_coupon(get_coupon_request_json(campaign_id)).await;
+}
+
+fn get_coupon_request_json(campaign_id: Option<i32>) -> serde_json::Value {
+    return json!({
+        "code": Alphanumeric.sample_string(&mut rand::thread_rng(), 10),
+        "campaign_id": campaign_id,
+        "discount": 10,
+        "active": true,
+        "never_expires": true,
+    });
+}
+
+fn get_campaign_request_json() -> serde_json::Value {
+    return json!({
+        "name": format!("Campaign {}", Alphanumeric.sample_string(&mut rand::thread_rng(), 10)),
+        "description": "Giveaway on the OldBot Discord server",
+        "budget": 500000,
+        "currency": "BRL",
+        "active": true,
+    });
+}
@@ -1,8 +1,15 @@
 use crate::helpers::{spawn_app, TestApp};
-use chrono::{NaiveDateTime, Utc, Datelike};
-use coupon_api::coupon::{Coupon, CouponInsertRequest, CouponResponse, CouponUpdateRequest};
+use chrono::{NaiveDateTime, Utc, Datelike, Weekday};
+use coupon_api::coupon::{
+    Coupon, CouponInsertRequest, CouponResponse, CouponUpdateRequest, CouponRedemption,
+    CouponVerification, InvalidReason, CouponRule, DiscountType, CouponQuote, CouponBatchResponse,
+    CouponPage, CouponCache, CouponAuditAction,
+};
+use coupon_api::problem::{ProblemDetails, PROBLEM_JSON};
 use rand::distributions::{Alphanumeric, DistString};
+use reqwest::Method;
 use serde_json::json;
+use std::collections::HashSet;
 
 /**
  * GET
@@ -37,10 +44,11 @@ async fn get_coupon_by_code_returns_a_coupon() {
 async fn get_all_coupons_returns_a_list_of_coupons() {
     // Arrange
     let app = spawn_app().await;
-    let code1 = get_random_coupon_code();
+    let prefix = get_random_coupon_code();
+    let code1 = format!("{}1", prefix);
     let coupon_request1 = get_coupon_request(code1.clone());
     let body1 = get_coupon_request_json(&coupon_request1);
-    let code2 = get_random_coupon_code();
+    let code2 = format!("{}2", prefix);
     let coupon_request2 = get_coupon_request(code2.clone());
     let body2 = get_coupon_request_json(&coupon_request2);
     
@@ -49,20 +57,106 @@ async fn get_all_coupons_returns_a_list_of_coupons() {
     app.post_coupon(body1, true).await;
     app.post_coupon(body2, true).await;
 
-    // get all coupons
-    let response = app.get_coupon("").await;
+    // get all coupons with the codes we added before
+    let response = app.get_coupon(format!("?code={}", prefix).as_str()).await;
     let response_body = response.text().await.expect("failed to get response_body");
-    let coupons: Vec<Coupon> = serde_json::from_str(&response_body).expect("Failed to parse CouponResponse from response.");
+    let page: CouponPage = serde_json::from_str(&response_body).expect("Failed to parse CouponPage from response.");
 
     // Assert
-    assert!(coupons.len() > 1);
+    let codes: Vec<String> = page.data.into_iter().map(|coupon| coupon.code).collect();
+    assert_eq!(codes, vec![code1, code2]);
+    assert_eq!(page.pagination.total_count, 2);
+    assert_eq!(page.pagination.total_pages, 1);
+    assert_eq!(page.pagination.next, None);
+    assert_eq!(page.pagination.prev, None);
+}
+
+#[tokio::test]
+async fn get_all_coupons_is_paginated_and_sorted() {
+    // Arrange
+    let app = spawn_app().await;
+    let prefix = get_random_coupon_code();
+    for discount in [20, 10, 30] {
+        let mut coupon_request = get_coupon_request(format!("{}{}", prefix, discount));
+        coupon_request.discount = discount;
+        app.post_coupon(get_coupon_request_json(&coupon_request), true).await;
+    }
+
+    // Act
+    let first_page = get_coupon_page(&app, format!("?code={}&page_size=2&sort=-discount", prefix).as_str()).await;
+    let second_page = get_coupon_page(&app, format!("?code={}&page_size=2&sort=-discount&page=2", prefix).as_str()).await;
+
+    // Assert
+    let discounts: Vec<i32> = first_page.data.iter().map(|coupon| coupon.discount).collect();
+    assert_eq!(discounts, vec![30, 20]);
+    assert_eq!(first_page.pagination.total_count, 3);
+    assert_eq!(first_page.pagination.total_pages, 2);
+    assert!(first_page.pagination.next.unwrap().ends_with(format!("/coupon?page=2&page_size=2&code={}&sort=-discount", prefix).as_str()));
+    assert_eq!(first_page.pagination.prev, None);
+
+    let discounts: Vec<i32> = second_page.data.iter().map(|coupon| coupon.discount).collect();
+    assert_eq!(discounts, vec![10]);
+    assert_eq!(second_page.pagination.next, None);
+    assert!(second_page.pagination.prev.unwrap().ends_with(format!("/coupon?page=1&page_size=2&code={}&sort=-discount", prefix).as_str()));
+}
+
+#[tokio::test]
+async fn get_all_coupons_is_filtered() {
+    // Arrange
+    let app = spawn_app().await;
+    let prefix = get_random_coupon_code();
+
+    let mut inactive = get_coupon_request(format!("{}inactive", prefix));
+    inactive.active = false;
+    inactive.discount = 50;
+    let mut expired = get_coupon_request(format!("{}expired", prefix));
+    expired.expiration_date = Some(NaiveDateTime::parse_from_str("2020-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    expired.never_expires = false;
+    let mut never_expires = get_coupon_request(format!("{}never", prefix));
+    never_expires.expiration_date = None;
+    never_expires.never_expires = true;
+    for coupon_request in [&inactive, &expired, &never_expires] {
+        app.post_coupon(get_coupon_request_json(coupon_request), true).await;
+    }
 
-    // iterate through the coupons and get only the 2 coupon with the codes we added before
-    let added_coupons: Vec<Coupon> = coupons.into_iter()
-        .filter(|coupon| coupon.code == code1 || coupon.code == code2)
-        .collect();
+    let test_cases = vec![
+        ("&active=false", vec![inactive.code.clone()], "inactive coupons"),
+        ("&expired=true", vec![expired.code.clone()], "expired coupons"),
+        ("&expired=false&active=true", vec![never_expires.code.clone()], "active and not expired coupons"),
+        ("&min_discount=40&max_discount=60", vec![inactive.code.clone()], "discount range"),
+        ("&created_to=2000-01-01T00:00:00", vec![], "created date range"),
+    ];
+
+    for (filter, expected_codes, test_identifier) in test_cases {
+        // Act
+        let page = get_coupon_page(&app, format!("?code={}{}&sort=code", prefix, filter).as_str()).await;
+
+        // Assert
+        let codes: Vec<String> = page.data.into_iter().map(|coupon| coupon.code).collect();
+        assert_eq!(codes, expected_codes, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn get_all_coupons_returns_4xx_for_invalid_query() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        ("?page=0", 422, "page 0"),
+        ("?page_size=1000", 422, "page size too large"),
+        ("?sort=unknown", 422, "invalid sort field"),
+        ("?min_discount=20&max_discount=10", 422, "invalid discount range"),
+        ("?page=abc", 400, "non numeric page"),
+    ];
 
-    assert!(added_coupons.len() == 2);
+    for (query, expected_code, test_identifier) in test_cases {
+        // Act
+        let response = app.get_coupon(query).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), expected_code, "[Test `{}`]", test_identifier);
+    }
 }
 
 #[tokio::test]
@@ -114,6 +208,40 @@ async fn get_coupon_not_found_returns_404(){
      assert_coupon_fields(coupon, coupon_request);
  }
 
+#[tokio::test]
+async fn post_persists_a_fixed_amount_coupon() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.discount = 1050;
+    coupon_request.discount_type = DiscountType::FixedAmount;
+    coupon_request.currency = Some("BRL".to_string());
+
+    // Act
+    let coupon = app.post_and_deserialize_coupon(get_coupon_request_json(&coupon_request)).await;
+
+    // Assert
+    assert_coupon_fields(coupon, coupon_request);
+}
+
+#[tokio::test]
+async fn post_persists_the_coupon_rules() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.rules = vec![
+        CouponRule::Weekdays { weekdays: vec![Weekday::Sat, Weekday::Sun] },
+        CouponRule::HourWindow { start_hour: 9, end_hour: 18 },
+    ];
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request.clone()).await;
+
+    // Act
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+
+    // Assert
+    assert_eq!(added_coupon.rules, coupon_request.rules);
+    assert_eq!(coupon.rules, coupon_request.rules);
+}
+
 #[tokio::test]
 async fn post_returns_409_conflit_if_coupon_already_exists() {
     // Arrange
@@ -200,6 +328,91 @@ async fn post_returns_4xx_for_invalid_coupon_data() {
             "code": "test",
             "active": "false",
         }), "invalid `active` (`false` string)", 400),
+        (json!({
+            "discount": 0,
+            "code": "test",
+            "active": true,
+            "max_usage_per_customer": 0,
+        }), "invalid `max_usage_per_customer` (0)", 422),
+        (json!({
+            "discount": 0,
+            "code": "test",
+            "active": true,
+            "rules": [{"type": "hour_window", "start_hour": 25, "end_hour": 2}],
+        }), "invalid `rules` (hour higher than 23)", 422),
+        (json!({
+            "discount": 0,
+            "code": "test",
+            "active": true,
+            "rules": [{"type": "unknown"}],
+        }), "invalid `rules` (unknown type)", 400),
+        (json!({
+            "discount": 1000,
+            "discount_type": "fixed_amount",
+            "code": "test",
+            "active": true,
+        }), "missing `currency` for `fixed_amount`", 422),
+        (json!({
+            "discount": 1000,
+            "discount_type": "fixed_amount",
+            "currency": "XYZ",
+            "code": "test",
+            "active": true,
+        }), "invalid `currency`", 422),
+        (json!({
+            "discount": 0,
+            "discount_type": "fixed_amount",
+            "currency": "BRL",
+            "code": "test",
+            "active": true,
+        }), "invalid `discount` (`fixed_amount` not positive)", 422),
+        (json!({
+            "discount": 10,
+            "discount_type": "percentage",
+            "currency": "BRL",
+            "code": "test",
+            "active": true,
+        }), "invalid `currency` for `percentage`", 422),
+        (json!({
+            "discount": 10,
+            "code": "test",
+            "active": true,
+            "valid_from": "2100-01-01T00:00:00",
+            "expiration_date": "2099-01-01T00:00:00",
+        }), "invalid `valid_from` (after `expiration_date`)", 422),
+        (json!({
+            "discount": 10,
+            "code": "test",
+            "active": true,
+        }), "missing `expiration_date` without `never_expires`", 422),
+        (json!({
+            "discount": 10,
+            "code": "test",
+            "active": true,
+            "never_expires": true,
+            "expiration_date": "2099-01-01T00:00:00",
+        }), "`expiration_date` with `never_expires`", 422),
+        (json!({
+            "discount": 10,
+            "code": "test",
+            "active": true,
+            "never_expires": true,
+            "min_order_amount": 5000,
+        }), "missing `currency` for `min_order_amount`", 422),
+        (json!({
+            "discount": 10,
+            "currency": "BRL",
+            "code": "test",
+            "active": true,
+            "never_expires": true,
+            "max_discount_amount": 0,
+        }), "invalid `max_discount_amount` (0)", 422),
+        (json!({
+            "discount": 10,
+            "discount_type": "free",
+            "code": "test",
+            "active": true,
+        }), "invalid `discount_type`", 400),
     ];
 
     // Act 
@@ -215,6 +428,124 @@ async fn post_returns_4xx_for_invalid_coupon_data() {
     }
 }
 
+#[tokio::test]
+async fn post_returns_a_problem_json_body_for_invalid_coupon_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        (json!({
+            "discount": 91,
+            "code": "test",
+            "active": true,
+        }), 422, "validation_error", "discount"),
+        (json!({
+            "discount": 1,
+            "active": true,
+        }), 400, "invalid_body", "code"),
+    ];
+
+    for (invalid_body, expected_code, expected_error_code, expected_field) in test_cases {
+        // Act
+        let response = app.post_coupon(invalid_body, false).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), expected_code);
+        assert_eq!(response.headers()["content-type"], PROBLEM_JSON);
+
+        let problem: ProblemDetails = response.json().await
+            .expect("Failed to deserialize the error response.");
+        assert_eq!(problem.status, expected_code);
+        assert_eq!(problem.code, expected_error_code);
+        assert_eq!(problem.errors[0].field, expected_field);
+        assert!(problem.request_id.is_some());
+    }
+}
+
+#[tokio::test]
+async fn unauthorized_request_returns_a_problem_json_body() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(&format!("{}/coupon/1", &app.address))
+        .send()
+        .await
+        .expect("Failed to perform GET request");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["content-type"], PROBLEM_JSON);
+
+    let problem: ProblemDetails = response.json().await
+        .expect("Failed to deserialize the error response.");
+    assert_eq!(problem.code, "unauthorized");
+    assert!(problem.request_id.is_some());
+}
+
+
+#[tokio::test]
+async fn post_batch_persists_unique_single_use_coupons() {
+    // Arrange
+    let app = spawn_app().await;
+    let prefix = format!("{}-", get_random_coupon_code());
+    let body = json!({
+        "prefix": prefix,
+        "count": 50,
+        "code_length": 8,
+        "alphabet": "alphanumeric",
+        "discount": 15,
+        "active": true,
+        "never_expires": true,
+    });
+
+    // Act
+    let response = app.post_coupon_batch(body).await;
+    assert_eq!(201, response.status().as_u16());
+    let batch: CouponBatchResponse = response.json().await.expect("Failed to parse CouponBatchResponse from response.");
+
+    // Assert
+    assert_eq!(batch.count, 50);
+    let unique_codes: HashSet<&String> = batch.codes.iter().collect();
+    assert_eq!(unique_codes.len(), 50);
+    for code in &batch.codes {
+        assert!(code.starts_with(&prefix));
+        assert!(!code[prefix.len()..].contains(|c| "0O1IL".contains(c)), "ambiguous character in `{}`", code);
+    }
+
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", batch.codes[0]).as_str()).await;
+    assert_eq!(coupon.discount, 15);
+    assert_eq!(coupon.max_usage_count, Some(1));
+}
+
+#[tokio::test]
+async fn post_batch_returns_4xx_for_invalid_batch_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        (json!({"discount": 10, "active": true, "never_expires": true}), "missing `count`", 400),
+        (json!({"count": 0, "discount": 10, "active": true, "never_expires": true}), "invalid `count` (0)", 422),
+        (json!({"count": 1001, "discount": 10, "active": true, "never_expires": true}), "invalid `count` (too many)", 422),
+        (json!({"count": 10, "code_length": 2, "discount": 10, "active": true, "never_expires": true}), "invalid `code_length`", 422),
+        (json!({"count": 10, "alphabet": "emoji", "discount": 10, "active": true, "never_expires": true}), "invalid `alphabet`", 400),
+        (json!({"count": 10, "alphabet": "numbers", "discount": 10, "active": true, "never_expires": true}), "`numbers` without prefix", 422),
+        (json!({"count": 10, "discount": 91, "active": true, "never_expires": true}), "invalid `discount`", 422),
+    ];
+
+    // Act
+    for (invalid_body, error_message, expected_code) in test_cases {
+        let response = app.post_coupon_batch(invalid_body).await;
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_code,
+            "The API did not fail with `{}` when the payload was `{}`.",
+            expected_code, error_message
+        );
+    }
+}
 
 /**
  * PUT
@@ -238,6 +569,7 @@ async fn put_code_test_request(path_param: String) {
     coupon_update.id = added_coupon.id;
     coupon_update.discount = 66;
     coupon_update.max_usage_count = Some(123);
+    coupon_update.max_usage_per_customer = Some(3);
     coupon_update.expiration_date = Some(NaiveDateTime::parse_from_str("2099-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
     coupon_update.active = false;
 
@@ -368,10 +700,19 @@ async fn put_returns_404_for_invalid_id_path_param_data() {
     let coupon = get_default_coupon_data(get_random_coupon_code());
 
     let coupon_update = CouponUpdateRequest {
+        campaign_id: coupon.campaign_id,
         discount: coupon.discount,
+        discount_type: DiscountType::Percentage,
+        currency: None,
+        min_order_amount: coupon.min_order_amount,
+        max_discount_amount: coupon.max_discount_amount,
         active: coupon.active,
         max_usage_count: coupon.max_usage_count,
-        expiration_date: coupon.expiration_date
+        max_usage_per_customer: coupon.max_usage_per_customer,
+        valid_from: coupon.valid_from,
+        expiration_date: coupon.expiration_date,
+        never_expires: coupon.never_expires,
+        rules: vec![],
     };
 
     let body = json!(serde_json::to_value(&coupon_update).unwrap());
@@ -397,138 +738,963 @@ async fn put_returns_404_for_invalid_id_path_param_data() {
 
 
 /**
- * DELETE
+ * PATCH
  */
 #[tokio::test]
-async fn delete_coupon_by_id_successfully() {
+async fn patch_updates_only_the_supplied_fields() {
     // Arrange
     let (app, added_coupon) = spawn_app_and_post_coupon().await;
 
-    // delete added coupon
-    let response = app.delete_coupon(added_coupon.id.to_string()).await;
-    let response_status = response.status().as_u16();
-
-    assert_eq!(204, response_status);
+    // Act
+    let response = app.patch_coupon(added_coupon.code.clone(), json!({"active": false})).await;
 
-    // try to get the deleted coupon
-    let response = app.get_coupon(format!("/{}", added_coupon.id).as_str()).await;
-    let response_status = response.status().as_u16();
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let patched_coupon: CouponResponse = response.json().await
+        .expect("PATCH: Failed to parse CouponResponse from response.");
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
 
-    // Assert 2
-    assert_eq!(404, response_status);
+    assert!(!patched_coupon.active);
+    assert!(!coupon.active);
+    assert_eq!(coupon.discount, added_coupon.discount);
+    assert_eq!(coupon.max_usage_count, added_coupon.max_usage_count);
+    assert_eq!(coupon.expiration_date, added_coupon.expiration_date);
+    let _ = coupon.date_updated.unwrap();
 }
 
 #[tokio::test]
-async fn delete_coupon_by_code_successfully() {
+async fn patch_with_null_clears_the_field() {
     // Arrange
     let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    assert!(added_coupon.max_usage_count.is_some());
 
-    // delete added coupon
-    let response = app.delete_coupon(added_coupon.code.clone()).await;
-    let response_status = response.status().as_u16();
+    // Act
+    let response = app.patch_coupon(added_coupon.id.to_string(), json!({"max_usage_count": null})).await;
 
-    assert_eq!(204, response_status);
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    assert_eq!(coupon.max_usage_count, None);
+    assert_eq!(coupon.discount, added_coupon.discount);
+}
 
-    // try to get the deleted coupon
-    let response = app.get_coupon(format!("/{}", added_coupon.code).as_str()).await;
-    let response_status = response.status().as_u16();
+#[tokio::test]
+async fn patch_returns_404_for_coupon_not_found(){
+    // Arrange
+    let app = spawn_app().await;
 
-    // Assert 2
-    assert_eq!(404, response_status);
+    // Act
+    let response = app.patch_coupon("123456789".to_string(), json!({"active": false})).await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
 }
 
 #[tokio::test]
-async fn delete_returns_4xx_for_invalid_data() {
+async fn patch_returns_4xx_for_invalid_body_data() {
     // Arrange
-    let app = spawn_app().await;
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
 
     let test_cases = vec![
-        ("💩", "invalid string", 500),
-        ("-1", "invalid id (negative)", 404),
-        ("", "invalid id (empty)", 404),
+        (json!({"discount": 91}), "invalid `discount` (higher than 90)", 422),
+        (json!({"discount": null}), "null `discount`", 422),
+        (json!({"active": null}), "null `active`", 422),
+        (json!({"discount_type": "fixed_amount"}), "`fixed_amount` without `currency`", 422),
+        (json!({"expiration_date": null}), "no `expiration_date` and not `never_expires`", 422),
+        (json!({"never_expires": true}), "`never_expires` with an `expiration_date`", 422),
+        (json!({"campaign_id": 123456789}), "unknown `campaign_id`", 422),
+        (json!({"discount": "string"}), "invalid `discount` (string)", 400),
+        (json!({"code": "new code"}), "`code` can not be updated", 400),
     ];
 
-    // Act 
-    for (invalid_path_param, error_message, expected_status) in test_cases {
-        let response = app.delete_coupon(invalid_path_param.to_string()).await;
+    for (invalid_body, error_message, expected_code) in test_cases {
+        // Act
+        let response = app.patch_coupon(added_coupon.id.to_string(), invalid_body).await;
 
         // Assert
         assert_eq!(
             response.status().as_u16(),
-            expected_status,
-            "The API did not fail with {} when the payload was `{}`.",
-            expected_status, error_message
+            expected_code,
+            "The API did not fail with `{}` when the payload was `{}`.",
+            expected_code, error_message
         );
     }
+
+    // the coupon was not changed
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    assert_eq!(coupon.discount, added_coupon.discount);
+    assert_eq!(coupon.expiration_date, added_coupon.expiration_date);
 }
 
-/**
- * Verify Coupon
- */
 #[tokio::test]
-async fn verify_coupon_returns_true_for_a_valid_coupon() {
+async fn patch_validates_the_coupon_as_a_whole() {
     // Arrange
-    let coupon_request = get_coupon_request(get_random_coupon_code());
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
     // Act
-    let response_body = start_verify_test_and_post_coupon(coupon_request).await;
+    let response = app.patch_coupon(
+        added_coupon.id.to_string(),
+        json!({"never_expires": true, "expiration_date": null}),
+    ).await;
 
     // Assert
-    assert_eq!(response_body, "true");
+    assert_eq!(200, response.status().as_u16());
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    assert!(coupon.never_expires);
+    assert_eq!(coupon.expiration_date, None);
 }
 
+/**
+ * ETag
+ */
 #[tokio::test]
-async fn verify_coupon_returns_false_if_not_active() {
+async fn get_coupon_returns_an_etag_and_304_if_not_modified() {
     // Arrange
-    let mut coupon_request = get_coupon_request(get_random_coupon_code());
-    coupon_request.active = false;
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    let endpoint = format!("/{}", added_coupon.id);
+
     // Act
-    let response_body = start_verify_test_and_post_coupon(coupon_request).await;
+    let response = app.get_coupon(endpoint.as_str()).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let not_modified_response = app.request_coupon_with_header(Method::GET, endpoint.as_str(), json!({}), ("If-None-Match", &etag)).await;
+    let modified_response = app.request_coupon_with_header(Method::GET, endpoint.as_str(), json!({}), ("If-None-Match", "\"123456789\"")).await;
 
     // Assert
-    assert_eq!(response_body, "false");
+    assert_eq!(etag, format!("\"{}\"", added_coupon.version));
+    assert_eq!(304, not_modified_response.status().as_u16());
+    assert_eq!(not_modified_response.headers()["etag"].to_str().unwrap(), etag);
+    assert_eq!(200, modified_response.status().as_u16());
 }
 
 #[tokio::test]
-async fn verify_coupon_returns_false_if_expired() {
+async fn every_update_changes_the_etag() {
     // Arrange
-    let mut coupon_request = get_coupon_request(get_random_coupon_code());
-    coupon_request.expiration_date = Some(NaiveDateTime::parse_from_str("2000-12-31 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    let endpoint = format!("/{}", added_coupon.id);
+    let etag = app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap().to_string();
+
     // Act
-    let response_body = start_verify_test_and_post_coupon(coupon_request).await;
-    
+    let response = app.patch_coupon(added_coupon.id.to_string(), json!({"active": false})).await;
+
     // Assert
-    assert_eq!(response_body, "false");
+    assert_eq!(200, response.status().as_u16());
+    let patched_etag = response.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(etag, patched_etag);
+    assert_eq!(app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap(), patched_etag);
 }
 
 #[tokio::test]
-async fn verify_coupon_validates_if_today_is_friday() {
+async fn writes_with_the_current_etag_succeed() {
     // Arrange
-    // "SEXTOU" is a special coupon that is only valid on Friday
-    let coupon_request = get_coupon_request("SEXTOU".to_string());
-    // Act
-    let response_body = start_verify_test_and_post_coupon(coupon_request).await;
-    
-    // Assert
-    let weekday = Utc::now().date_naive().weekday().to_string();
-    if (weekday.to_uppercase() == "FRIDAY"){
-        assert_eq!(response_body, "true");
-    } else {
-        assert_eq!(response_body, "false");
-    }
-}
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    let endpoint = format!("/{}", added_coupon.id);
+    let mut coupon_update = get_default_coupon_data(added_coupon.code.clone());
+    coupon_update.discount = 66;
+    let body = json!(serde_json::to_value(&coupon_update).unwrap());
 
+    // Act
+    let etag = app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap().to_string();
+    let put_response = app.request_coupon_with_header(Method::PUT, endpoint.as_str(), body, ("If-Match", &etag)).await;
 
+    let etag = app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap().to_string();
+    let patch_response = app.request_coupon_with_header(Method::PATCH, endpoint.as_str(), json!({"active": false}), ("If-Match", &etag)).await;
 
-/**
- * Helper functions
- */
+    let etag = app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap().to_string();
+    let delete_response = app.request_coupon_with_header(Method::DELETE, endpoint.as_str(), json!({}), ("If-Match", &etag)).await;
 
-async fn spawn_app_and_post_coupon() -> (TestApp, CouponResponse) {
-    let coupon_request = get_coupon_request(get_random_coupon_code());
-    return spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+    // Assert
+    assert_eq!(200, put_response.status().as_u16());
+    assert_eq!(200, patch_response.status().as_u16());
+    assert_eq!(204, delete_response.status().as_u16());
 }
 
-async fn spawn_app_and_post_coupon_with_coupon_request(coupon_request: CouponInsertRequest) -> (TestApp, CouponResponse) {
+#[tokio::test]
+async fn writes_with_a_stale_etag_return_412() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    let endpoint = format!("/{}", added_coupon.id);
+    let stale_etag = app.get_coupon(endpoint.as_str()).await.headers()["etag"].to_str().unwrap().to_string();
+
+    // another client updates the coupon
+    app.patch_coupon(added_coupon.id.to_string(), json!({"discount": 20})).await;
+
+    let mut coupon_update = get_default_coupon_data(added_coupon.code.clone());
+    coupon_update.discount = 66;
+    let test_cases = vec![
+        (Method::PUT, json!(serde_json::to_value(&coupon_update).unwrap())),
+        (Method::PATCH, json!({"discount": 30})),
+        (Method::DELETE, json!({})),
+    ];
+
+    for (method, body) in test_cases {
+        // Act
+        let response = app.request_coupon_with_header(method.clone(), endpoint.as_str(), body, ("If-Match", &stale_etag)).await;
+
+        // Assert
+        assert_eq!(412, response.status().as_u16(), "The `{}` request did not fail with 412.", method);
+    }
+
+    // the coupon was neither overwritten nor deleted
+    let coupon = app.get_and_deserialize_coupon(endpoint.as_str()).await;
+    assert_eq!(coupon.discount, 20);
+}
+
+/**
+ * DELETE
+ */
+#[tokio::test]
+async fn delete_coupon_by_id_successfully() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // delete added coupon
+    let response = app.delete_coupon(added_coupon.id.to_string()).await;
+    let response_status = response.status().as_u16();
+
+    assert_eq!(204, response_status);
+
+    // try to get the deleted coupon
+    let response = app.get_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    let response_status = response.status().as_u16();
+
+    // Assert 2
+    assert_eq!(404, response_status);
+}
+
+#[tokio::test]
+async fn delete_coupon_by_code_successfully() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // delete added coupon
+    let response = app.delete_coupon(added_coupon.code.clone()).await;
+    let response_status = response.status().as_u16();
+
+    assert_eq!(204, response_status);
+
+    // try to get the deleted coupon
+    let response = app.get_coupon(format!("/{}", added_coupon.code).as_str()).await;
+    let response_status = response.status().as_u16();
+
+    // Assert 2
+    assert_eq!(404, response_status);
+}
+
+#[tokio::test]
+async fn delete_returns_4xx_for_invalid_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        ("💩", "invalid string", 500),
+        ("-1", "invalid id (negative)", 404),
+        ("", "invalid id (empty)", 404),
+    ];
+
+    // Act 
+    for (invalid_path_param, error_message, expected_status) in test_cases {
+        let response = app.delete_coupon(invalid_path_param.to_string()).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_status,
+            "The API did not fail with {} when the payload was `{}`.",
+            expected_status, error_message
+        );
+    }
+}
+
+/**
+ * Soft delete
+ */
+#[tokio::test]
+async fn deleted_coupon_is_excluded_and_can_be_restored() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    app.redeem_coupon(added_coupon.id.to_string()).await;
+
+    // Act 1
+    let delete_response = app.delete_coupon(added_coupon.id.to_string()).await;
+
+    // Assert 1
+    assert_eq!(204, delete_response.status().as_u16());
+    assert_eq!(404, app.get_coupon(format!("/{}", added_coupon.id).as_str()).await.status().as_u16());
+    assert_eq!(404, app.get_coupon(format!("/verify/{}", added_coupon.code).as_str()).await.status().as_u16());
+    assert_eq!(404, app.redeem_coupon(added_coupon.id.to_string()).await.status().as_u16());
+    assert!(get_coupon_page(&app, "").await.data.is_empty());
+
+    // Act 2
+    let restore_response = app.restore_coupon(added_coupon.id).await;
+
+    // Assert 2
+    assert_eq!(200, restore_response.status().as_u16());
+    let restored_coupon: CouponResponse = restore_response.json().await
+        .expect("Failed to parse CouponResponse from response.");
+    assert_eq!(restored_coupon.deleted_at, None);
+    assert_eq!(restored_coupon.code, added_coupon.code);
+    assert_eq!(restored_coupon.discount, added_coupon.discount);
+    assert_eq!(restored_coupon.expiration_date, added_coupon.expiration_date);
+
+    // the redemption was kept, the coupon has `max_usage_count` of 2
+    let verification = app.verify_and_deserialize_coupon(format!("/{}", added_coupon.code).as_str()).await;
+    assert!(verification.valid);
+    assert_eq!(201, app.redeem_coupon(added_coupon.id.to_string()).await.status().as_u16());
+    assert_eq!(409, app.redeem_coupon(added_coupon.id.to_string()).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn deleted_coupon_code_can_not_be_reused() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    app.delete_coupon(added_coupon.id.to_string()).await;
+
+    // Act
+    let body = get_coupon_request_json(&get_coupon_request(added_coupon.code.clone()));
+    let response = app.post_coupon(body, false).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn restore_returns_404_if_the_coupon_is_not_deleted() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // Act
+    let not_deleted_response = app.restore_coupon(added_coupon.id).await;
+    let not_found_response = app.restore_coupon(123456789).await;
+
+    // Assert
+    assert_eq!(404, not_deleted_response.status().as_u16());
+    assert_eq!(404, not_found_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn only_admin_sessions_can_list_deleted_coupons() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    app.delete_coupon(added_coupon.id.to_string()).await;
+
+    // Act
+    let response = app.get_coupon("?deleted=true").await;
+    let admin_response = app.admin_api_client
+        .get(&format!("{}/coupon?deleted=true", &app.address))
+        .send()
+        .await
+        .expect("Failed to perform GET request");
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!(200, admin_response.status().as_u16());
+
+    let page: CouponPage = admin_response.json().await
+        .expect("Failed to parse CouponPage from response.");
+    assert_eq!(page.data.len(), 1);
+    assert_eq!(page.data[0].id, added_coupon.id);
+    assert!(page.data[0].deleted_at.is_some());
+}
+
+/**
+ * History
+ */
+#[tokio::test]
+async fn history_records_every_change_of_the_coupon() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    let mut coupon_update = get_default_coupon_data(added_coupon.code.clone());
+    coupon_update.id = added_coupon.id;
+    coupon_update.discount = 66;
+
+    // Act
+    app.put_coupon(added_coupon.id.to_string(), json!(serde_json::to_value(&coupon_update).unwrap())).await;
+    app.delete_coupon(added_coupon.id.to_string()).await;
+    let history = app.get_coupon_history(added_coupon.id).await;
+
+    // Assert
+    let actions: Vec<CouponAuditAction> = history.iter().map(|audit| audit.action).collect();
+    assert_eq!(actions, vec![CouponAuditAction::Insert, CouponAuditAction::Update, CouponAuditAction::Delete]);
+    assert!(history.iter().all(|audit| audit.coupon_id == added_coupon.id));
+    assert!(history.iter().all(|audit| audit.api_key_name.as_deref() == Some("default")));
+    // only the hash of the session id, that can not be used as a bearer token
+    assert!(history.iter().all(|audit| audit.session_hash.as_ref().is_some_and(|hash| hash.len() == 64)));
+
+    let insert = &history[0];
+    assert!(insert.before.is_none());
+    assert_eq!(insert.after.as_ref().unwrap()["discount"], added_coupon.discount);
+
+    let update = &history[1];
+    assert_eq!(update.before.as_ref().unwrap()["discount"], added_coupon.discount);
+    assert_eq!(update.after.as_ref().unwrap()["discount"], 66);
+    assert_eq!(update.after.as_ref().unwrap()["version"], 2);
+
+    let delete = &history[2];
+    assert!(delete.before.as_ref().unwrap()["deleted_at"].is_null());
+    assert!(!delete.after.as_ref().unwrap()["deleted_at"].is_null());
+}
+
+#[tokio::test]
+async fn history_records_the_api_key_of_the_change() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // Act
+    app.admin_api_client
+        .delete(&format!("{}/coupon/{}", &app.address, added_coupon.id))
+        .send()
+        .await
+        .expect("Failed to perform DELETE request");
+    let history = app.get_coupon_history(added_coupon.id).await;
+
+    // Assert
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].action, CouponAuditAction::Delete);
+    assert_eq!(history[1].api_key_name.as_deref(), Some("admin"));
+}
+
+#[tokio::test]
+async fn history_returns_404_for_coupon_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_coupon("/123456789/history").await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+/**
+ * Cache
+ */
+#[tokio::test]
+async fn get_coupon_after_put_returns_the_updated_coupon() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    // cache the coupon by id and by code
+    app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    app.get_and_deserialize_coupon(format!("/{}", added_coupon.code).as_str()).await;
+
+    // Act
+    let mut coupon_update = get_default_coupon_data(added_coupon.code.clone());
+    coupon_update.discount = 77;
+    let response = app.put_coupon(added_coupon.code.clone(), json!(serde_json::to_value(&coupon_update).unwrap())).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Assert
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    assert_eq!(coupon.discount, 77);
+    let coupon = app.get_and_deserialize_coupon(format!("/{}", added_coupon.code).as_str()).await;
+    assert_eq!(coupon.discount, 77);
+}
+
+#[tokio::test]
+async fn get_coupon_after_delete_returns_404() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+    app.get_and_deserialize_coupon(format!("/{}", added_coupon.id).as_str()).await;
+    app.get_and_deserialize_coupon(format!("/{}", added_coupon.code).as_str()).await;
+
+    // Act
+    let response = app.delete_coupon(added_coupon.id.to_string()).await;
+    assert_eq!(204, response.status().as_u16());
+
+    // Assert
+    assert_eq!(404, app.get_coupon(format!("/{}", added_coupon.id).as_str()).await.status().as_u16());
+    assert_eq!(404, app.get_coupon(format!("/{}", added_coupon.code).as_str()).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn coupon_cache_is_skipped_when_redis_is_unavailable() {
+    // Arrange
+    let client = redis::Client::open("redis://127.0.0.1:1/").expect("Failed to create redis client.");
+    let cache = CouponCache::new(client, 60);
+    let coupon: CouponResponse = get_default_coupon_data(get_random_coupon_code()).try_into().unwrap();
+
+    // Act
+    // writing to the cache must not fail, it is only logged
+    cache.set("default", &coupon).await;
+    cache.invalidate("default", coupon.id, &coupon.code).await;
+
+    // Assert
+    assert!(cache.get_by_id("default", coupon.id).await.is_none());
+    assert!(cache.get_by_code("default", &coupon.code).await.is_none());
+}
+
+/**
+ * Verify Coupon
+ */
+#[tokio::test]
+async fn verify_coupon_returns_true_for_a_valid_coupon() {
+    // Arrange
+    let coupon_request = get_coupon_request(get_random_coupon_code());
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+
+    // Assert
+    assert!(verification.valid);
+    assert!(verification.reasons.is_empty());
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_false_if_not_active() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.active = false;
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::Inactive]);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_false_if_expired() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.expiration_date = Some(NaiveDateTime::parse_from_str("2000-12-31 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+    
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::Expired]);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_false_if_not_yet_valid() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.valid_from = Some(NaiveDateTime::parse_from_str("2099-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+    
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::NotYetValid]);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_true_if_valid_from_has_passed() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.valid_from = Some(NaiveDateTime::parse_from_str("2000-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+    
+    // Assert
+    assert!(verification.valid);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_true_if_coupon_never_expires() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.expiration_date = None;
+    coupon_request.never_expires = true;
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+    
+    // Assert
+    assert!(verification.valid);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_every_reason_the_coupon_is_invalid() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.active = false;
+    coupon_request.expiration_date = Some(NaiveDateTime::parse_from_str("2000-12-31 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap());
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+    
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::Inactive, InvalidReason::Expired]);
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_weekdays_rule() {
+    // Arrange
+    let today = Utc::now().date_naive().weekday();
+    let test_cases = vec![
+        (today, vec![], "today is an allowed weekday"),
+        (today.succ(), vec![InvalidReason::InvalidWeekday], "today is not an allowed weekday"),
+    ];
+
+    for (weekday, expected_reasons, test_identifier) in test_cases {
+        let mut coupon_request = get_coupon_request(get_random_coupon_code());
+        coupon_request.rules = vec![CouponRule::Weekdays { weekdays: vec![weekday] }];
+        // Act
+        let verification = start_verify_test_and_post_coupon(coupon_request).await;
+
+        // Assert
+        assert_eq!(verification.reasons, expected_reasons, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_date_range_rule() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.rules = vec![CouponRule::DateRange {
+        start_date: NaiveDateTime::parse_from_str("2000-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        end_date: NaiveDateTime::parse_from_str("2000-12-31 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+    }];
+    // Act
+    let verification = start_verify_test_and_post_coupon(coupon_request).await;
+
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::OutsideDateRange]);
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_false_if_max_usage_count_is_reached() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.max_usage_count = Some(1);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.redeem_coupon(added_coupon.code.clone()).await;
+    assert_eq!(201, response.status().as_u16());
+
+    let verification = app.verify_and_deserialize_coupon(format!("/{}", added_coupon.code).as_str()).await;
+
+    // Assert
+    assert!(!verification.valid);
+    assert_eq!(verification.reasons, vec![InvalidReason::UsageLimitReached]);
+}
+
+/**
+ * Redeem Coupon
+ */
+#[tokio::test]
+async fn redeem_coupon_by_id_returns_the_redemption() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // Act
+    let response = app.redeem_coupon(added_coupon.id.to_string()).await;
+    let response_status = response.status().as_u16();
+    let response_body = response.text().await.expect("Failed to get response_body");
+
+    // Assert
+    assert_eq!(201, response_status);
+
+    let redemption: CouponRedemption = serde_json::from_str(&response_body).expect("Failed to parse CouponRedemption from response.");
+    assert_eq!(redemption.coupon_id, added_coupon.id);
+    assert!(redemption.discount_amount.is_none() && redemption.currency.is_none());
+}
+
+#[tokio::test]
+async fn redeem_coupon_returns_409_when_max_usage_count_is_reached() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.max_usage_count = Some(2);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    for _ in 0..2 {
+        let response = app.redeem_coupon(added_coupon.code.clone()).await;
+        assert_eq!(201, response.status().as_u16());
+    }
+    let response = app.redeem_coupon(added_coupon.code.clone()).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn redeem_coupon_returns_409_if_not_active() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.active = false;
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.redeem_coupon(added_coupon.id.to_string()).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_redemptions_do_not_exceed_max_usage_count() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.max_usage_count = Some(1);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let (response1, response2) = tokio::join!(
+        app.redeem_coupon(added_coupon.code.clone()),
+        app.redeem_coupon(added_coupon.code.clone()),
+    );
+
+    // Assert
+    let mut statuses = vec![response1.status().as_u16(), response2.status().as_u16()];
+    statuses.sort();
+    assert_eq!(statuses, vec![201, 409]);
+}
+
+#[tokio::test]
+async fn redeem_coupon_returns_409_when_max_usage_per_customer_is_reached() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.max_usage_count = None;
+    coupon_request.max_usage_per_customer = Some(1);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.redeem_coupon_as_customer(added_coupon.code.clone(), "customer1").await;
+    assert_eq!(201, response.status().as_u16());
+    let redemption: CouponRedemption = response.json().await.expect("Failed to parse CouponRedemption from response.");
+    assert_eq!(redemption.customer_id, Some("customer1".to_string()));
+
+    let response_same_customer = app.redeem_coupon_as_customer(added_coupon.code.clone(), "customer1").await;
+    let response_other_customer = app.redeem_coupon_as_customer(added_coupon.code.clone(), "customer2").await;
+    let response_no_customer = app.redeem_coupon(added_coupon.code.clone()).await;
+
+    // Assert
+    assert_eq!(409, response_same_customer.status().as_u16());
+    assert_eq!(201, response_other_customer.status().as_u16());
+    assert_eq!(409, response_no_customer.status().as_u16());
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_max_usage_per_customer() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.max_usage_per_customer = Some(1);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.redeem_coupon_as_customer(added_coupon.code.clone(), "customer1").await;
+    assert_eq!(201, response.status().as_u16());
+
+    let test_cases = vec![
+        ("?customer_id=customer1", vec![InvalidReason::CustomerUsageLimitReached], "customer that reached the limit"),
+        ("?customer_id=customer2", vec![], "customer that did not use the coupon"),
+        ("", vec![InvalidReason::CustomerRequired], "missing `customer_id`"),
+    ];
+
+    for (query, expected_reasons, test_identifier) in test_cases {
+        let verification = app.verify_and_deserialize_coupon(format!("/{}{}", added_coupon.code, query).as_str()).await;
+
+        // Assert
+        assert_eq!(verification.reasons, expected_reasons, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_min_order_amount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.currency = Some("BRL".to_string());
+    coupon_request.min_order_amount = Some(5000);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    let test_cases = vec![
+        ("?amount=50.00&currency=BRL", vec![], "order reaching the minimum"),
+        ("?amount=49.99&currency=BRL", vec![InvalidReason::BelowMinOrderAmount], "order below the minimum"),
+        ("", vec![InvalidReason::OrderAmountRequired], "missing order"),
+    ];
+
+    for (query, expected_reasons, test_identifier) in test_cases {
+        // Act
+        let verification = app.verify_and_deserialize_coupon(format!("/{}{}", added_coupon.code, query).as_str()).await;
+
+        // Assert
+        assert_eq!(verification.reasons, expected_reasons, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn redeem_coupon_validates_min_order_amount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.currency = Some("BRL".to_string());
+    coupon_request.min_order_amount = Some(5000);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    let test_cases = vec![
+        ("", 409, "missing order"),
+        ("?amount=50.00", 422, "missing `currency`"),
+        ("?amount=49.99&currency=BRL", 409, "order below the minimum"),
+        ("?amount=50.00&currency=BRL", 201, "order reaching the minimum"),
+    ];
+
+    for (query, expected_status, test_identifier) in test_cases {
+        // Act
+        let response = app.redeem_coupon_with_query(added_coupon.code.clone(), query).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), expected_status, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn verify_coupon_returns_422_for_incomplete_order() {
+    // Arrange
+    let (app, added_coupon) = spawn_app_and_post_coupon().await;
+
+    // Act
+    let response = app.get_coupon(format!("/verify/{}?amount=50.00", added_coupon.code).as_str()).await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn redeem_coupon_returns_404_for_coupon_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.redeem_coupon("123456789".to_string()).await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+
+/**
+ * Quote Coupon
+ */
+#[tokio::test]
+async fn quote_applies_a_percentage_discount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.discount = 15;
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.quote_coupon(added_coupon.code.clone(), json!({"amount": "82.30", "currency": "BRL"})).await;
+    assert_eq!(200, response.status().as_u16());
+    let quote: CouponQuote = response.json().await.expect("Failed to parse CouponQuote from response.");
+
+    // Assert
+    // 15% of 82.30 is 12.345, rounded half away from zero
+    assert_eq!(quote.original_amount.to_string(), "82.30");
+    assert_eq!(quote.discount_amount.to_string(), "12.35");
+    assert_eq!(quote.final_amount.to_string(), "69.95");
+    assert_eq!(quote.currency, "BRL");
+}
+
+#[tokio::test]
+async fn quote_applies_a_fixed_amount_discount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.discount = 1050;
+    coupon_request.discount_type = DiscountType::FixedAmount;
+    coupon_request.currency = Some("BRL".to_string());
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.quote_coupon(added_coupon.id.to_string(), json!({"amount": "100", "currency": "BRL"})).await;
+    assert_eq!(200, response.status().as_u16());
+    let quote: CouponQuote = response.json().await.expect("Failed to parse CouponQuote from response.");
+
+    // Assert
+    assert_eq!(quote.original_amount.to_string(), "100.00");
+    assert_eq!(quote.discount_amount.to_string(), "10.50");
+    assert_eq!(quote.final_amount.to_string(), "89.50");
+}
+
+#[tokio::test]
+async fn quote_caps_the_discount_at_the_max_discount_amount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.discount = 50;
+    coupon_request.currency = Some("BRL".to_string());
+    coupon_request.max_discount_amount = Some(2000);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.quote_coupon(added_coupon.code.clone(), json!({"amount": "100.00", "currency": "BRL"})).await;
+    assert_eq!(200, response.status().as_u16());
+    let quote: CouponQuote = response.json().await.expect("Failed to parse CouponQuote from response.");
+
+    // Assert
+    assert_eq!(quote.discount_amount.to_string(), "20.00");
+    assert_eq!(quote.final_amount.to_string(), "80.00");
+}
+
+#[tokio::test]
+async fn quote_returns_409_for_an_order_below_the_min_order_amount() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.currency = Some("BRL".to_string());
+    coupon_request.min_order_amount = Some(5000);
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.quote_coupon(added_coupon.code.clone(), json!({"amount": "49.99", "currency": "BRL"})).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn quote_returns_409_for_an_invalid_coupon() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.active = false;
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    // Act
+    let response = app.quote_coupon(added_coupon.code.clone(), json!({"amount": "100.00", "currency": "BRL"})).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn quote_returns_4xx_for_invalid_order_data() {
+    // Arrange
+    let mut coupon_request = get_coupon_request(get_random_coupon_code());
+    coupon_request.discount = 1050;
+    coupon_request.discount_type = DiscountType::FixedAmount;
+    coupon_request.currency = Some("BRL".to_string());
+    let (app, added_coupon) = spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+
+    let test_cases = vec![
+        (json!({"currency": "BRL"}), "missing `amount`", 400),
+        (json!({"amount": "100.00"}), "missing `currency`", 400),
+        (json!({"amount": "0", "currency": "BRL"}), "invalid `amount` (zero)", 422),
+        (json!({"amount": "-10", "currency": "BRL"}), "invalid `amount` (negative)", 422),
+        (json!({"amount": "10.001", "currency": "BRL"}), "invalid `amount` (too many decimal places)", 422),
+        (json!({"amount": "100.00", "currency": "XYZ"}), "invalid `currency`", 422),
+        (json!({"amount": "100.00", "currency": "USD"}), "`currency` different from the coupon currency", 422),
+    ];
+
+    // Act
+    for (invalid_body, error_message, expected_code) in test_cases {
+        let response = app.quote_coupon(added_coupon.code.clone(), invalid_body).await;
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_code,
+            "The API did not fail with `{}` when the payload was `{}`.",
+            expected_code, error_message
+        );
+    }
+}
+
+/**
+ * Helper functions
+ */
+
+async fn spawn_app_and_post_coupon() -> (TestApp, CouponResponse) {
+    let coupon_request = get_coupon_request(get_random_coupon_code());
+    return spawn_app_and_post_coupon_with_coupon_request(coupon_request).await;
+}
+
+async fn spawn_app_and_post_coupon_with_coupon_request(coupon_request: CouponInsertRequest) -> (TestApp, CouponResponse) {
     // Arrange
     let app = spawn_app().await;
 
@@ -538,33 +1704,47 @@ async fn spawn_app_and_post_coupon_with_coupon_request(coupon_request: CouponIns
     return (app, added_coupon);
 }
 
-async fn start_verify_test_and_post_coupon(coupon_request: CouponInsertRequest) -> String {
+async fn start_verify_test_and_post_coupon(coupon_request: CouponInsertRequest) -> CouponVerification {
     let (app, _) = spawn_app_and_post_coupon_with_coupon_request(coupon_request.clone()).await;
 
-    let response = app.get_coupon(format!("/verify/{}", coupon_request.code).as_str()).await;
-    let response_body = response.text().await.expect("Failed to get response_body");
-
-    return response_body;
+    return app.verify_and_deserialize_coupon(format!("/{}", coupon_request.code).as_str()).await;
 }
 
 fn assert_coupon_fields(coupon_response: CouponResponse, coupon_expected: CouponInsertRequest){
     assert_eq!(coupon_response.code, coupon_expected.code);
     assert_eq!(coupon_response.discount, coupon_expected.discount);
+    assert_eq!(coupon_response.discount_type, coupon_expected.discount_type);
+    assert_eq!(coupon_response.currency, coupon_expected.currency);
+    assert_eq!(coupon_response.min_order_amount, coupon_expected.min_order_amount);
+    assert_eq!(coupon_response.max_discount_amount, coupon_expected.max_discount_amount);
     assert_eq!(coupon_response.active, coupon_expected.active);
     assert_eq!(coupon_response.max_usage_count, coupon_expected.max_usage_count);
+    assert_eq!(coupon_response.max_usage_per_customer, coupon_expected.max_usage_per_customer);
+    assert_eq!(coupon_response.valid_from, coupon_expected.valid_from);
     assert_eq!(coupon_response.expiration_date, coupon_expected.expiration_date);
+    assert_eq!(coupon_response.never_expires, coupon_expected.never_expires);
 }
 
 fn get_default_coupon_data(code: String) -> Coupon {
     return Coupon { 
         id: 123456789,
         code,
+        campaign_id: None,
         discount: 10,
+        discount_type: "percentage".to_string(),
+        currency: None,
+        min_order_amount: None,
+        max_discount_amount: None,
         max_usage_count: Some(2),
+        max_usage_per_customer: None,
+        valid_from: None,
         expiration_date: Some(NaiveDateTime::parse_from_str("2100-12-31 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()),
+        never_expires: false,
         active: true,
         date_created: None,
         date_updated: None,
+        deleted_at: None,
+        version: 1,
     };
 }
 
@@ -577,13 +1757,28 @@ fn get_coupon_request(code: String) -> CouponInsertRequest {
     let coupon = get_default_coupon_data(code);
     return CouponInsertRequest {
         code: coupon.code,
+        campaign_id: coupon.campaign_id,
         discount: coupon.discount,
+        discount_type: DiscountType::Percentage,
+        currency: None,
+        min_order_amount: coupon.min_order_amount,
+        max_discount_amount: coupon.max_discount_amount,
         active: true,
         max_usage_count: coupon.max_usage_count,
+        max_usage_per_customer: coupon.max_usage_per_customer,
+        valid_from: coupon.valid_from,
         expiration_date: coupon.expiration_date,
+        never_expires: coupon.never_expires,
+        rules: vec![],
     };
 }
 
+async fn get_coupon_page(app: &TestApp, query: &str) -> CouponPage {
+    let response = app.get_coupon(query).await;
+    assert_eq!(200, response.status().as_u16(), "GET `/coupon{}` failed.", query);
+    return response.json().await.expect("Failed to parse CouponPage from response.");
+}
+
 fn get_random_coupon_code() -> String {
     return Alphanumeric.sample_string(&mut rand::thread_rng(), 10);
 }
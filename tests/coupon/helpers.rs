@@ -1,18 +1,20 @@
 use coupon_api::{
-    configuration::{get_configuration, DatabaseSettings, Settings, ApiKey},
+    configuration::{get_configuration, DatabaseSettings, ApiKey, Settings},
     telemetry::{get_subscriber, init_subscriber},
     startup::{get_connection_pool, Application},
-    coupon::{CouponResponse},
+    authentication::{ApiKeyCreatedResponse, ApiKeyScope},
+    coupon::{CouponResponse, CouponVerification, CouponAuditResponse, Product, CampaignResponse},
 };
 use reqwest::{
     Method,
     header:: HeaderMap,
 };
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
 use std::panic;
 use sqlx::{MySqlPool, MySqlConnection, Connection, Executor};
 use once_cell::sync::Lazy;
+use uuid::Uuid;
 
 pub struct TestApp {
     pub address: String,
@@ -20,6 +22,8 @@ pub struct TestApp {
     pub db_name: String,
     pub port: u16,
     pub api_client: reqwest::Client,
+    // authenticated with the `admin_api_key`
+    pub admin_api_client: reqwest::Client,
     pub api_key: ApiKey,
 }
 
@@ -42,10 +46,21 @@ impl TestApp {
         return coupon;
     }
 
+    pub async fn verify_and_deserialize_coupon(&self, endpoint: &str) -> CouponVerification {
+        let response = self.get_coupon(format!("/verify{}", endpoint).as_str()).await;
+        let response_body = response.text().await.expect("failed to get response_body");
+        let verification: CouponVerification = serde_json::from_str(&response_body).expect("VERIFY: Failed to parse CouponVerification from response.");
+        return verification;
+    }
+
     pub async fn post_coupon(&self, body: serde_json::Value, error_for_status: bool) -> reqwest::Response {
         return self.request_coupon(Method::POST, "", body, error_for_status).await;
     }
     
+    pub async fn post_coupon_batch(&self, body: serde_json::Value) -> reqwest::Response {
+        return self.request_coupon(Method::POST, "/batch", body, false).await;
+    }
+
     pub async fn get_coupon(&self, endpoint: &str) -> reqwest::Response {
         return self.api_client
             .get(&format!("{}/coupon{}", &self.address, endpoint))
@@ -64,11 +79,171 @@ impl TestApp {
             .expect("Failed to perform GET request");
     }
 
+    pub async fn patch_coupon(&self, path_param: String, body: serde_json::Value) -> reqwest::Response {
+        let endpoint = format!("/{}", path_param);
+        return self.request_coupon(Method::PATCH, endpoint.as_str(), body, false).await;
+    }
+
     pub async fn delete_coupon(&self, path_param: String) -> reqwest::Response {
         let endpoint = format!("/{}", path_param);
         return self.request_coupon(Method::DELETE, endpoint.as_str(), json!({}), false).await;
     }
 
+    pub async fn restore_coupon(&self, id: i32) -> reqwest::Response {
+        let endpoint = format!("/{}/restore", id);
+        return self.request_coupon(Method::POST, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn get_coupon_history(&self, id: i32) -> Vec<CouponAuditResponse> {
+        let endpoint = format!("/{}/history", id);
+        return self.request_coupon(Method::GET, endpoint.as_str(), json!({}), true).await
+            .json().await
+            .expect("Failed to parse CouponAuditResponse from response.");
+    }
+
+    pub async fn redeem_coupon(&self, path_param: String) -> reqwest::Response {
+        let endpoint = format!("/{}/redeem", path_param);
+        return self.request_coupon(Method::POST, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn redeem_coupon_with_query(&self, path_param: String, query: &str) -> reqwest::Response {
+        let endpoint = format!("/{}/redeem{}", path_param, query);
+        return self.request_coupon(Method::POST, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn redeem_coupon_as_customer(&self, path_param: String, customer_id: &str) -> reqwest::Response {
+        let endpoint = format!("/{}/redeem?customer_id={}", path_param, customer_id);
+        return self.request_coupon(Method::POST, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn quote_coupon(&self, path_param: String, body: serde_json::Value) -> reqwest::Response {
+        let endpoint = format!("/{}/quote", path_param);
+        return self.request_coupon(Method::POST, endpoint.as_str(), body, false).await;
+    }
+
+    pub async fn link_coupon_product(&self, path_param: String, product_code: &str) -> reqwest::Response {
+        let endpoint = format!("/{}/products/{}", path_param, product_code);
+        return self.request_coupon(Method::PUT, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn unlink_coupon_product(&self, path_param: String, product_code: &str) -> reqwest::Response {
+        let endpoint = format!("/{}/products/{}", path_param, product_code);
+        return self.request_coupon(Method::DELETE, endpoint.as_str(), json!({}), false).await;
+    }
+
+    pub async fn get_coupon_products(&self, path_param: String) -> Vec<Product> {
+        let response = self.get_coupon(format!("/{}/products", path_param).as_str()).await;
+        return response.json().await.expect("GET: Failed to parse Vec<Product> from response.");
+    }
+
+    pub async fn post_product(&self, body: serde_json::Value) -> reqwest::Response {
+        return self.request_product(Method::POST, "", body).await;
+    }
+
+    pub async fn post_and_deserialize_product(&self, code: &str) -> Product {
+        let response = self.post_product(json!({"code": code, "name": format!("OldBot {}", code)})).await;
+        return response.json().await.expect("POST: Failed to parse Product from response.");
+    }
+
+    pub async fn get_product(&self, code: &str) -> reqwest::Response {
+        return self.request_product(Method::GET, format!("/{}", code).as_str(), json!({})).await;
+    }
+
+    pub async fn delete_product(&self, code: &str) -> reqwest::Response {
+        return self.request_product(Method::DELETE, format!("/{}", code).as_str(), json!({})).await;
+    }
+
+    pub async fn request_product(&self, method: Method, endpoint: &str, body: serde_json::Value) -> reqwest::Response {
+        return self.api_client
+            .request(method.clone(), &format!("{}/product{}", &self.address, endpoint))
+            .json(&body)
+            .send()
+            .await
+            .expect(format!("Failed to perform {} request", method.to_string()).as_str());
+    }
+
+    pub async fn post_campaign(&self, body: serde_json::Value) -> reqwest::Response {
+        return self.request_campaign(Method::POST, "", body).await;
+    }
+
+    pub async fn post_and_deserialize_campaign(&self, body: serde_json::Value) -> CampaignResponse {
+        let response = self.post_campaign(body).await;
+        return response.json().await.expect("POST: Failed to parse CampaignResponse from response.");
+    }
+
+    pub async fn get_and_deserialize_campaign(&self, id: i32) -> CampaignResponse {
+        let response = self.request_campaign(Method::GET, format!("/{}", id).as_str(), json!({})).await;
+        return response.json().await.expect("GET: Failed to parse CampaignResponse from response.");
+    }
+
+    pub async fn put_campaign(&self, id: i32, body: serde_json::Value) -> reqwest::Response {
+        return self.request_campaign(Method::PUT, format!("/{}", id).as_str(), body).await;
+    }
+
+    pub async fn delete_campaign(&self, id: i32) -> reqwest::Response {
+        return self.request_campaign(Method::DELETE, format!("/{}", id).as_str(), json!({})).await;
+    }
+
+    pub async fn request_campaign(&self, method: Method, endpoint: &str, body: serde_json::Value) -> reqwest::Response {
+        return self.api_client
+            .request(method.clone(), &format!("{}/campaign{}", &self.address, endpoint))
+            .json(&body)
+            .send()
+            .await
+            .expect(format!("Failed to perform {} request", method.to_string()).as_str());
+    }
+
+    // Request to the `/api-key` scope, with the admin session by default
+    pub async fn request_api_key(&self, client: Option<&reqwest::Client>, method: Method, endpoint: &str, body: serde_json::Value) -> reqwest::Response {
+        return client.unwrap_or(&self.admin_api_client)
+            .request(method.clone(), &format!("{}/api-key{}", &self.address, endpoint))
+            .json(&body)
+            .send()
+            .await
+            .expect(format!("Failed to perform {} request", method.to_string()).as_str());
+    }
+
+    /// Create an API key with the `scopes` and return a client authenticated with it.
+    pub async fn create_api_key_client(&self, name: &str, scopes: serde_json::Value) -> (ApiKeyCreatedResponse, reqwest::Client) {
+        return self.create_api_key_client_from(json!({"name": name, "scopes": scopes})).await;
+    }
+
+    // Create an API key with every scope and the `role`
+    pub async fn create_role_client(&self, name: &str, role: &str) -> (ApiKeyCreatedResponse, reqwest::Client) {
+        return self.create_api_key_client_from(json!({"name": name, "scopes": ApiKeyScope::all(), "role": role})).await;
+    }
+
+    // Create an API key with every scope for the `tenant`
+    pub async fn create_tenant_client(&self, name: &str, tenant: &str) -> (ApiKeyCreatedResponse, reqwest::Client) {
+        return self.create_api_key_client_from(json!({"name": name, "scopes": ApiKeyScope::all(), "tenant": tenant})).await;
+    }
+
+    /// Create an `admin` API key bound to the `tenant` and return a client authenticated with it.
+    pub async fn create_tenant_admin_client(&self, name: &str, tenant: &str) -> (ApiKeyCreatedResponse, reqwest::Client) {
+        return self.create_api_key_client_from(json!({"name": name, "scopes": ApiKeyScope::all(), "tenant": tenant, "role": "admin"})).await;
+    }
+
+    async fn create_api_key_client_from(&self, body: serde_json::Value) -> (ApiKeyCreatedResponse, reqwest::Client) {
+        let api_key: ApiKeyCreatedResponse = self.request_api_key(None, Method::POST, "", body).await
+            .error_for_status()
+            .expect("Failed to create the API key.")
+            .json().await
+            .expect("Failed to parse ApiKeyCreatedResponse from response.");
+        let client = create_reqwest_client(&ApiKey(Secret::new(api_key.key.clone())), &self.address).await;
+        return (api_key, client);
+    }
+
+    // Request with a conditional header, e.g. `If-Match`
+    pub async fn request_coupon_with_header(&self, method: Method, endpoint: &str, body: serde_json::Value, header: (&str, &str)) -> reqwest::Response {
+        return self.api_client
+            .request(method.clone(), &format!("{}/coupon{}", &self.address, endpoint))
+            .header(header.0, header.1)
+            .json(&body)
+            .send()
+            .await
+            .expect(format!("Failed to perform {} request", method.to_string()).as_str());
+    }
+
     pub async fn request_coupon(&self, method: Method, endpoint: &str, body: serde_json::Value, error_for_status: bool) -> reqwest::Response {
         if (error_for_status == true){
             return self.api_client
@@ -91,6 +266,11 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    return spawn_app_with_configuration(|_| {}).await;
+}
+
+// Spawn the app with changes to the test configuration, e.g. the `session_mode`
+pub async fn spawn_app_with_configuration(configure: impl FnOnce(&mut Settings)) -> TestApp {
     // The first time `initialize` is invoked the code in `TRACING` is executed.
     // All other invocations will instead skip execution.
     Lazy::force(&TRACING);
@@ -100,6 +280,8 @@ pub async fn spawn_app() -> TestApp {
         let mut c = get_configuration().expect("Failed to read configuration.");
         // Use a random OS port
         c.application.port = 0;
+        c.application.admin_api_key = Some(ApiKey(Secret::new(Uuid::new_v4().to_string())));
+        configure(&mut c);
         c
     };
 
@@ -121,14 +303,15 @@ pub async fn spawn_app() -> TestApp {
         port: application_port,
         db_pool: get_connection_pool(&configuration.database, true),
         db_name: configuration.database.test_database_name.clone(),
-        api_client: create_reqwest_client(&configuration, &address).await,
+        api_client: create_reqwest_client(&configuration.application.api_key, &address).await,
+        admin_api_client: create_reqwest_client(configuration.application.admin_api_key.as_ref().unwrap(), &address).await,
         api_key: configuration.application.api_key,
     };
 }
 
-async fn create_reqwest_client(configuration: &Settings, address: &String) -> reqwest::Client {
+async fn create_reqwest_client(api_key: &ApiKey, address: &String) -> reqwest::Client {
     // request to `/auth` to get a Bearer token and set in in the header for next requests
-    let body = json!({"api_key": &configuration.application.api_key.0.expose_secret()});
+    let body = json!({"api_key": api_key.0.expose_secret()});
     let response = reqwest::Client::new()
         .post(&format!("{}/auth", address))
         .json(&body)
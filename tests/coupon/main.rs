@@ -1,7 +1,12 @@
 
 #![allow(unused_parens)]
 
+mod campaign;
 mod coupon;
 mod auth;
+mod api_key;
+mod role;
+mod tenant;
 mod helpers;
 mod health_check;
+mod product;
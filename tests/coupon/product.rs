@@ -0,0 +1,224 @@
+use crate::helpers::{spawn_app, TestApp};
+use coupon_api::coupon::{CouponResponse, InvalidReason, Product};
+use rand::distributions::{Alphanumeric, DistString};
+use reqwest::Method;
+use serde_json::json;
+
+/**
+ * Product catalog
+ */
+#[tokio::test]
+async fn post_product_persists_the_new_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let code = get_random_product_code();
+
+    // Act
+    let response = app.post_product(json!({"code": code, "name": "OldBot Pro"})).await;
+    assert_eq!(201, response.status().as_u16());
+    let product: Product = response.json().await.expect("Failed to parse Product from response.");
+
+    // Assert
+    assert_eq!(product.code, code);
+    assert_eq!(product.name, "OldBot Pro");
+
+    let response = app.get_product(&code).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn post_product_returns_409_conflict_if_product_already_exists() {
+    // Arrange
+    let app = spawn_app().await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+
+    // Act
+    let response = app.post_product(json!({"code": product.code, "name": "OldBot Pro"})).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn post_product_returns_4xx_for_invalid_product_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = vec![
+        (json!({"name": "OldBot Pro"}), "missing `code`", 400),
+        (json!({"code": "oldbot-pro"}), "missing `name`", 400),
+        (json!({"code": "", "name": "OldBot Pro"}), "empty `code`", 422),
+        (json!({"code": "123", "name": "OldBot Pro"}), "numeric `code`", 422),
+        (json!({"code": "oldbot-pro", "name": " "}), "empty `name`", 422),
+    ];
+
+    // Act
+    for (invalid_body, error_message, expected_code) in test_cases {
+        let response = app.post_product(invalid_body).await;
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_code,
+            "The API did not fail with `{}` when the payload was `{}`.",
+            expected_code, error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn delete_product_removes_the_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+
+    // Act
+    let response = app.delete_product(&product.code).await;
+
+    // Assert
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(404, app.get_product(&product.code).await.status().as_u16());
+    assert_eq!(404, app.delete_product(&product.code).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn delete_product_returns_409_while_a_coupon_is_restricted_to_it() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+    app.link_coupon_product(coupon.code.clone(), &product.code).await;
+
+    // Act
+    let response = app.delete_product(&product.code).await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+    // the coupon is still restricted to the product
+    assert_eq!(app.get_coupon_products(coupon.code.clone()).await.len(), 1);
+
+    app.unlink_coupon_product(coupon.code.clone(), &product.code).await;
+    assert_eq!(204, app.delete_product(&product.code).await.status().as_u16());
+}
+
+/**
+ * Coupon products
+ */
+#[tokio::test]
+async fn link_coupon_product_restricts_the_coupon_to_the_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+
+    // Act
+    let response = app.link_coupon_product(coupon.code.clone(), &product.code).await;
+    assert_eq!(204, response.status().as_u16());
+    // linking it again is a no-op
+    let response = app.link_coupon_product(coupon.id.to_string(), &product.code).await;
+    assert_eq!(204, response.status().as_u16());
+
+    // Assert
+    let products = app.get_coupon_products(coupon.code.clone()).await;
+    assert_eq!(products.len(), 1);
+    assert_eq!(products[0].code, product.code);
+}
+
+#[tokio::test]
+async fn unlink_coupon_product_removes_the_restriction() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+    app.link_coupon_product(coupon.code.clone(), &product.code).await;
+
+    // Act
+    let response = app.unlink_coupon_product(coupon.code.clone(), &product.code).await;
+
+    // Assert
+    assert_eq!(204, response.status().as_u16());
+    assert!(app.get_coupon_products(coupon.code.clone()).await.is_empty());
+    // the product is not linked anymore
+    let response = app.unlink_coupon_product(coupon.code.clone(), &product.code).await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn link_coupon_product_returns_404_for_unknown_coupon_or_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+
+    // Act
+    let unknown_product = app.link_coupon_product(coupon.code.clone(), "unknown-product").await;
+    let unknown_coupon = app.link_coupon_product("123456789".to_string(), &product.code).await;
+
+    // Assert
+    assert_eq!(404, unknown_product.status().as_u16());
+    assert_eq!(404, unknown_coupon.status().as_u16());
+}
+
+#[tokio::test]
+async fn verify_coupon_validates_the_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+    let other_product = app.post_and_deserialize_product(&get_random_product_code()).await;
+
+    // a coupon without products applies to every product
+    let verification = app.verify_and_deserialize_coupon(format!("/{}?product={}", coupon.code, other_product.code).as_str()).await;
+    assert!(verification.valid);
+
+    app.link_coupon_product(coupon.code.clone(), &product.code).await;
+
+    let test_cases = vec![
+        (format!("?product={}", product.code), vec![], "product linked to the coupon"),
+        (format!("?product={}", other_product.code), vec![InvalidReason::ProductNotEligible], "product not linked to the coupon"),
+        ("".to_string(), vec![InvalidReason::ProductRequired], "missing `product`"),
+    ];
+
+    for (query, expected_reasons, test_identifier) in test_cases {
+        // Act
+        let verification = app.verify_and_deserialize_coupon(format!("/{}{}", coupon.code, query).as_str()).await;
+
+        // Assert
+        assert_eq!(verification.reasons, expected_reasons, "[Test `{}`]", test_identifier);
+    }
+}
+
+#[tokio::test]
+async fn redeem_coupon_requires_an_eligible_product() {
+    // Arrange
+    let app = spawn_app().await;
+    let coupon = post_coupon(&app).await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+    app.link_coupon_product(coupon.code.clone(), &product.code).await;
+
+    // Act
+    let without_product = app.redeem_coupon(coupon.code.clone()).await;
+    let endpoint = format!("/{}/redeem?product={}", coupon.code, product.code);
+    let with_product = app.request_coupon(Method::POST, endpoint.as_str(), json!({}), false).await;
+
+    // Assert
+    assert_eq!(409, without_product.status().as_u16());
+    assert_eq!(201, with_product.status().as_u16());
+}
+
+/**
+ * Helper functions
+ */
+
+async fn post_coupon(app: &TestApp) -> CouponResponse {
+    let body = json!({
+        "code": Alphanumeric.sample_string(&mut rand::thread_rng(), 10),
+        "discount": 10,
+        "active": true,
+        "never_expires": true,
+    });
+    return app.post_and_deserialize_coupon(body).await;
+}
+
+fn get_random_product_code() -> String {
+    return format!("oldbot-{}", Alphanumeric.sample_string(&mut rand::thread_rng(), 8));
+}
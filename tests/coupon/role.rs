@@ -0,0 +1,78 @@
+use coupon_api::authentication::Role;
+use reqwest::Method;
+use serde_json::json;
+
+use crate::helpers::spawn_app;
+
+
+#[tokio::test]
+async fn verifier_can_only_verify_coupons() {
+    // Arrange
+    let app = spawn_app().await;
+    let (api_key, client) = app.create_role_client("storefront", "verifier").await;
+
+    // Act
+    let verify_response = client.get(&format!("{}/coupon/verify/TEST", &app.address)).send().await.unwrap();
+    let get_response = client.get(&format!("{}/coupon", &app.address)).send().await.unwrap();
+    let post_response = client.post(&format!("{}/coupon", &app.address)).json(&json!({})).send().await.unwrap();
+    let delete_response = client.delete(&format!("{}/coupon/1", &app.address)).send().await.unwrap();
+    let redeem_response = client.post(&format!("{}/coupon/TEST/redeem", &app.address)).send().await.unwrap();
+
+    // Assert
+    assert_eq!(api_key.api_key.role, Role::Verifier);
+    // coupon not found, but the role was accepted
+    assert_eq!(404, verify_response.status().as_u16());
+    assert_eq!(403, get_response.status().as_u16());
+    assert_eq!(403, post_response.status().as_u16());
+    assert_eq!(403, delete_response.status().as_u16());
+    assert_eq!(403, redeem_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn reader_can_not_change_coupons_products_or_campaigns() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, client) = app.create_role_client("reports", "reader").await;
+
+    // Act
+    let get_response = client.get(&format!("{}/coupon", &app.address)).send().await.unwrap();
+    let post_response = client.post(&format!("{}/coupon", &app.address)).json(&json!({})).send().await.unwrap();
+    let product_response = client.post(&format!("{}/product", &app.address)).json(&json!({})).send().await.unwrap();
+    let campaign_response = client.delete(&format!("{}/campaign/1", &app.address)).send().await.unwrap();
+
+    // Assert
+    assert_eq!(200, get_response.status().as_u16());
+    assert_eq!(403, post_response.status().as_u16());
+    assert_eq!(403, product_response.status().as_u16());
+    assert_eq!(403, campaign_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn api_key_role_defaults_to_editor() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (api_key, client) = app.create_api_key_client("backoffice", json!(["coupon:read", "coupon:write"])).await;
+
+    // Assert
+    assert_eq!(api_key.api_key.role, Role::Editor);
+    // invalid coupon, but the role was accepted
+    let post_response = client.post(&format!("{}/coupon", &app.address)).json(&json!({})).send().await.unwrap();
+    assert_eq!(400, post_response.status().as_u16());
+    let api_key_response = app.request_api_key(Some(&client), Method::GET, "", json!({})).await;
+    assert_eq!(403, api_key_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn admin_role_can_manage_api_keys() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, client) = app.create_role_client("ops", "admin").await;
+
+    // Act
+    let response = app.request_api_key(Some(&client), Method::GET, "", json!({})).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
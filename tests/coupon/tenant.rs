@@ -0,0 +1,221 @@
+use coupon_api::authentication::{ApiKeyCreatedResponse, ApiKeyResponse, SessionResponse};
+use coupon_api::coupon::{Campaign, CampaignResponse, CouponPage, CouponResponse, Product};
+use rand::distributions::{Alphanumeric, DistString};
+use serde_json::json;
+
+use crate::helpers::spawn_app;
+
+
+#[tokio::test]
+async fn coupon_code_is_unique_per_tenant() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let body = get_coupon_request_json();
+
+    // Act
+    let default_response = app.post_coupon(body.clone(), false).await;
+    let acme_response = acme_client.post(&format!("{}/coupon", &app.address)).json(&body).send().await.unwrap();
+    let duplicate_response = acme_client.post(&format!("{}/coupon", &app.address)).json(&body).send().await.unwrap();
+
+    // Assert
+    assert_eq!(201, default_response.status().as_u16());
+    assert_eq!(201, acme_response.status().as_u16());
+    assert_eq!(409, duplicate_response.status().as_u16());
+
+    let default_coupon: CouponResponse = default_response.json().await.unwrap();
+    let acme_coupon: CouponResponse = acme_response.json().await.unwrap();
+    assert_eq!(default_coupon.code, acme_coupon.code);
+    assert_ne!(default_coupon.id, acme_coupon.id);
+
+    let acme_by_code: CouponResponse = acme_client.get(&format!("{}/coupon/{}", &app.address, acme_coupon.code)).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(acme_by_code.id, acme_coupon.id);
+}
+
+#[tokio::test]
+async fn coupons_of_another_tenant_are_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let coupon: CouponResponse = app.post_coupon(get_coupon_request_json(), true).await.json().await.unwrap();
+
+    // Act
+    let get_response = acme_client.get(&format!("{}/coupon/{}", &app.address, coupon.id)).send().await.unwrap();
+    let verify_response = acme_client.get(&format!("{}/coupon/verify/{}", &app.address, coupon.code)).send().await.unwrap();
+    let redeem_response = acme_client.post(&format!("{}/coupon/{}/redeem", &app.address, coupon.id)).send().await.unwrap();
+    let history_response = acme_client.get(&format!("{}/coupon/{}/history", &app.address, coupon.id)).send().await.unwrap();
+    let delete_response = acme_client.delete(&format!("{}/coupon/{}", &app.address, coupon.id)).send().await.unwrap();
+    let list_response = acme_client.get(&format!("{}/coupon", &app.address)).send().await.unwrap();
+
+    // Assert
+    assert_eq!(404, get_response.status().as_u16());
+    assert_eq!(404, verify_response.status().as_u16());
+    assert_eq!(404, redeem_response.status().as_u16());
+    assert_eq!(404, history_response.status().as_u16());
+    assert_eq!(404, delete_response.status().as_u16());
+    let page: CouponPage = list_response.json().await.unwrap();
+    assert!(page.data.is_empty());
+
+    // the coupon was not deleted
+    assert_eq!(200, app.request_coupon(reqwest::Method::GET, &format!("/{}", coupon.id), json!({}), false).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn session_is_bound_to_the_tenant_of_the_api_key() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (api_key, client) = app.create_tenant_client("acme-backoffice", "acme").await;
+
+    // Assert
+    assert_eq!(api_key.api_key.tenant, "acme");
+    let session: SessionResponse = client.get(&format!("{}/auth/session", &app.address)).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(session.tenant, "acme");
+}
+
+#[tokio::test]
+async fn campaign_name_is_unique_per_tenant() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let body = get_campaign_request_json();
+
+    // Act
+    let default_response = app.post_campaign(body.clone()).await;
+    let acme_response = acme_client.post(&format!("{}/campaign", &app.address)).json(&body).send().await.unwrap();
+    let duplicate_response = acme_client.post(&format!("{}/campaign", &app.address)).json(&body).send().await.unwrap();
+
+    // Assert
+    assert_eq!(201, default_response.status().as_u16());
+    assert_eq!(201, acme_response.status().as_u16());
+    assert_eq!(409, duplicate_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn campaigns_of_another_tenant_are_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let campaign = app.post_and_deserialize_campaign(get_campaign_request_json()).await;
+    let campaign_id = campaign.campaign.id;
+
+    // Act
+    let get_response = acme_client.get(&format!("{}/campaign/{}", &app.address, campaign_id)).send().await.unwrap();
+    let put_response = acme_client.put(&format!("{}/campaign/{}", &app.address, campaign_id)).json(&get_campaign_request_json()).send().await.unwrap();
+    let delete_response = acme_client.delete(&format!("{}/campaign/{}", &app.address, campaign_id)).send().await.unwrap();
+    let list_response = acme_client.get(&format!("{}/campaign", &app.address)).send().await.unwrap();
+    let mut coupon_body = get_coupon_request_json();
+    coupon_body["campaign_id"] = json!(campaign_id);
+    let coupon_response = acme_client.post(&format!("{}/coupon", &app.address)).json(&coupon_body).send().await.unwrap();
+
+    // Assert
+    assert_eq!(404, get_response.status().as_u16());
+    assert_eq!(404, put_response.status().as_u16());
+    assert_eq!(404, delete_response.status().as_u16());
+    let campaigns: Vec<Campaign> = list_response.json().await.unwrap();
+    assert!(campaigns.iter().all(|other| other.id != campaign_id));
+    // a coupon can not be added to the campaign of another tenant
+    assert_eq!(422, coupon_response.status().as_u16());
+
+    // the campaign was not updated nor deleted
+    let default_campaign: CampaignResponse = app.get_and_deserialize_campaign(campaign_id).await;
+    assert_eq!(default_campaign.campaign.name, campaign.campaign.name);
+}
+
+#[tokio::test]
+async fn product_code_is_unique_per_tenant() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let body = json!({"code": get_random_product_code(), "name": "OldBot"});
+
+    // Act
+    let default_response = app.post_product(body.clone()).await;
+    let acme_response = acme_client.post(&format!("{}/product", &app.address)).json(&body).send().await.unwrap();
+    let duplicate_response = acme_client.post(&format!("{}/product", &app.address)).json(&body).send().await.unwrap();
+
+    // Assert
+    assert_eq!(201, default_response.status().as_u16());
+    assert_eq!(201, acme_response.status().as_u16());
+    assert_eq!(409, duplicate_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn products_of_another_tenant_are_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_client) = app.create_tenant_client("acme-backoffice", "acme").await;
+    let product = app.post_and_deserialize_product(&get_random_product_code()).await;
+    let acme_coupon: CouponResponse = acme_client.post(&format!("{}/coupon", &app.address)).json(&get_coupon_request_json()).send().await.unwrap()
+        .json().await.unwrap();
+
+    // Act
+    let get_response = acme_client.get(&format!("{}/product/{}", &app.address, product.code)).send().await.unwrap();
+    let delete_response = acme_client.delete(&format!("{}/product/{}", &app.address, product.code)).send().await.unwrap();
+    let list_response = acme_client.get(&format!("{}/product", &app.address)).send().await.unwrap();
+    let link_response = acme_client.put(&format!("{}/coupon/{}/products/{}", &app.address, acme_coupon.id, product.code)).send().await.unwrap();
+
+    // Assert
+    assert_eq!(404, get_response.status().as_u16());
+    assert_eq!(404, delete_response.status().as_u16());
+    let products: Vec<Product> = list_response.json().await.unwrap();
+    assert!(products.iter().all(|other| other.id != product.id));
+    // a coupon can not be restricted to the product of another tenant
+    assert_eq!(404, link_response.status().as_u16());
+
+    // the product was not deleted
+    assert_eq!(200, app.get_product(&product.code).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn admin_of_a_tenant_only_manages_the_api_keys_of_its_tenant() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, acme_admin_client) = app.create_tenant_admin_client("acme-admin", "acme").await;
+    let (globex_api_key, _) = app.create_tenant_client("globex-backoffice", "globex").await;
+    let url = format!("{}/api-key", &app.address);
+
+    // Act
+    let list_response = acme_admin_client.get(&url).send().await.unwrap();
+    let delete_response = acme_admin_client.delete(&format!("{}/{}", url, globex_api_key.api_key.id)).send().await.unwrap();
+    let other_tenant_response = acme_admin_client.post(&url).json(&json!({"name": "globex-admin", "scopes": ["coupon:read"], "role": "admin", "tenant": "globex"})).send().await.unwrap();
+    let own_tenant_response = acme_admin_client.post(&url).json(&json!({"name": "acme-checkout", "scopes": ["coupon:verify"]})).send().await.unwrap();
+
+    // Assert
+    let api_keys: Vec<ApiKeyResponse> = list_response.json().await.unwrap();
+    assert!(!api_keys.is_empty());
+    assert!(api_keys.iter().all(|api_key| api_key.tenant == "acme"));
+    assert_eq!(404, delete_response.status().as_u16());
+    assert_eq!(403, other_tenant_response.status().as_u16());
+    assert_eq!(201, own_tenant_response.status().as_u16());
+    let created: ApiKeyCreatedResponse = own_tenant_response.json().await.unwrap();
+    assert_eq!(created.api_key.tenant, "acme");
+
+    // the `admin_api_key` manages the keys of every tenant, the key of the other tenant was not deleted
+    let all_api_keys: Vec<ApiKeyResponse> = app.request_api_key(None, reqwest::Method::GET, "", json!({})).await.json().await.unwrap();
+    assert!(all_api_keys.iter().any(|api_key| api_key.id == globex_api_key.api_key.id));
+    assert!(all_api_keys.iter().all(|api_key| api_key.name != "globex-admin"));
+}
+
+fn get_random_product_code() -> String {
+    return format!("oldbot-{}", Alphanumeric.sample_string(&mut rand::thread_rng(), 8));
+}
+
+fn get_campaign_request_json() -> serde_json::Value {
+    return json!({
+        "name": format!("Campaign {}", Alphanumeric.sample_string(&mut rand::thread_rng(), 10)),
+        "active": true,
+    });
+}
+
+fn get_coupon_request_json() -> serde_json::Value {
+    return json!({
+        "code": Alphanumeric.sample_string(&mut rand::thread_rng(), 10),
+        "discount": 10,
+        "active": true,
+        "never_expires": true,
+    });
+}